@@ -0,0 +1,872 @@
+// Copyright (c) 2025 mcpeaps_HD
+//
+// This software is released under the MIT License.
+// https://opensource.org/licenses/MIT
+use crate::schema_browser;
+use crate::shared::{Dialect, SchemaTable};
+use crate::splitter;
+
+/// Most suggestions a completion offers.
+pub const MAX_SUGGESTIONS: usize = 50;
+
+/// Letters typed before the popup opens by itself; after a `.` it opens right away.
+pub const MIN_PREFIX: usize = 2;
+
+const KEYWORDS: &[&str] = &[
+    "ADD",
+    "ALL",
+    "ALTER",
+    "AND",
+    "AS",
+    "ASC",
+    "BEGIN",
+    "BETWEEN",
+    "BY",
+    "CASE",
+    "CHECK",
+    "COLUMN",
+    "COMMIT",
+    "CONSTRAINT",
+    "CREATE",
+    "CROSS",
+    "DEFAULT",
+    "DELETE",
+    "DESC",
+    "DISTINCT",
+    "DROP",
+    "ELSE",
+    "END",
+    "EXCEPT",
+    "EXISTS",
+    "EXPLAIN",
+    "FALSE",
+    "FOREIGN",
+    "FROM",
+    "FULL",
+    "GROUP",
+    "HAVING",
+    "IN",
+    "INDEX",
+    "INNER",
+    "INSERT",
+    "INTERSECT",
+    "INTO",
+    "IS",
+    "JOIN",
+    "KEY",
+    "LEFT",
+    "LIKE",
+    "LIMIT",
+    "NOT",
+    "NULL",
+    "OFFSET",
+    "ON",
+    "OR",
+    "ORDER",
+    "OUTER",
+    "PRIMARY",
+    "REFERENCES",
+    "RIGHT",
+    "ROLLBACK",
+    "SELECT",
+    "SET",
+    "TABLE",
+    "THEN",
+    "TRUE",
+    "UNION",
+    "UNIQUE",
+    "UPDATE",
+    "USING",
+    "VALUES",
+    "VIEW",
+    "WHEN",
+    "WHERE",
+    "WITH",
+];
+
+const MYSQL_KEYWORDS: &[&str] = &[
+    "AUTO_INCREMENT",
+    "DATABASES",
+    "DESCRIBE",
+    "DUPLICATE",
+    "ENGINE",
+    "IGNORE",
+    "REPLACE",
+    "SHOW",
+    "STRAIGHT_JOIN",
+    "TABLES",
+];
+
+const POSTGRES_KEYWORDS: &[&str] = &[
+    "ANALYZE",
+    "CONFLICT",
+    "DO",
+    "ILIKE",
+    "LATERAL",
+    "NOTHING",
+    "RETURNING",
+    "SERIAL",
+    "SIMILAR",
+    "VACUUM",
+];
+
+const SQLITE_KEYWORDS: &[&str] = &[
+    "AUTOINCREMENT",
+    "GLOB",
+    "PRAGMA",
+    "REPLACE",
+    "ROWID",
+    "VACUUM",
+    "WITHOUT",
+];
+
+const FUNCTIONS: &[&str] = &[
+    "ABS",
+    "AVG",
+    "CAST",
+    "COALESCE",
+    "COUNT",
+    "CURRENT_DATE",
+    "CURRENT_TIMESTAMP",
+    "DENSE_RANK",
+    "LAG",
+    "LEAD",
+    "LENGTH",
+    "LOWER",
+    "MAX",
+    "MIN",
+    "NULLIF",
+    "RANK",
+    "REPLACE",
+    "ROUND",
+    "ROW_NUMBER",
+    "SUBSTRING",
+    "SUM",
+    "TRIM",
+    "UPPER",
+];
+
+const MYSQL_FUNCTIONS: &[&str] = &[
+    "CONCAT",
+    "CONCAT_WS",
+    "CURDATE",
+    "DATEDIFF",
+    "DATE_ADD",
+    "DATE_FORMAT",
+    "DATE_SUB",
+    "FROM_UNIXTIME",
+    "GROUP_CONCAT",
+    "IF",
+    "IFNULL",
+    "JSON_ARRAYAGG",
+    "JSON_EXTRACT",
+    "JSON_OBJECT",
+    "LAST_INSERT_ID",
+    "NOW",
+    "STR_TO_DATE",
+    "UNIX_TIMESTAMP",
+];
+
+const POSTGRES_FUNCTIONS: &[&str] = &[
+    "AGE",
+    "ARRAY_AGG",
+    "DATE_TRUNC",
+    "EXTRACT",
+    "GENERATE_SERIES",
+    "GEN_RANDOM_UUID",
+    "GREATEST",
+    "JSONB_AGG",
+    "JSONB_BUILD_OBJECT",
+    "JSON_AGG",
+    "LEAST",
+    "NOW",
+    "REGEXP_REPLACE",
+    "SPLIT_PART",
+    "STRING_AGG",
+    "TO_CHAR",
+    "TO_DATE",
+    "TO_TIMESTAMP",
+    "UNNEST",
+];
+
+const SQLITE_FUNCTIONS: &[&str] = &[
+    "DATE",
+    "DATETIME",
+    "GROUP_CONCAT",
+    "IFNULL",
+    "IIF",
+    "INSTR",
+    "JSON_EXTRACT",
+    "JULIANDAY",
+    "LAST_INSERT_ROWID",
+    "PRINTF",
+    "RANDOM",
+    "STRFTIME",
+    "TIME",
+    "TYPEOF",
+];
+
+/// Keywords after which a table name is expected.
+const TABLE_KEYWORDS: &[&str] = &["FROM", "JOIN", "UPDATE", "INTO", "TABLE"];
+
+/// Keywords that end a table reference, so they are never taken for an alias.
+const CLAUSE_KEYWORDS: &[&str] = &[
+    "CROSS",
+    "EXCEPT",
+    "FOR",
+    "FULL",
+    "GROUP",
+    "HAVING",
+    "INNER",
+    "INTERSECT",
+    "JOIN",
+    "LEFT",
+    "LIMIT",
+    "NATURAL",
+    "OFFSET",
+    "ON",
+    "ORDER",
+    "OUTER",
+    "RETURNING",
+    "RIGHT",
+    "SELECT",
+    "SET",
+    "UNION",
+    "USING",
+    "VALUES",
+    "WHERE",
+    "WINDOW",
+];
+
+/// Keywords of the dialect, without duplicates and sorted.
+pub fn keywords(dialect: Dialect) -> Vec<&'static str> {
+    let extra = match dialect {
+        Dialect::MySql => MYSQL_KEYWORDS,
+        Dialect::Postgres => POSTGRES_KEYWORDS,
+        Dialect::Sqlite => SQLITE_KEYWORDS,
+        Dialect::Generic => &[],
+    };
+    sorted_words(KEYWORDS, extra)
+}
+
+/// Built-in functions of the dialect, without duplicates and sorted.
+pub fn functions(dialect: Dialect) -> Vec<&'static str> {
+    let extra = match dialect {
+        Dialect::MySql => MYSQL_FUNCTIONS,
+        Dialect::Postgres => POSTGRES_FUNCTIONS,
+        Dialect::Sqlite => SQLITE_FUNCTIONS,
+        Dialect::Generic => &[],
+    };
+    sorted_words(FUNCTIONS, extra)
+}
+
+fn sorted_words(common: &[&'static str], extra: &[&'static str]) -> Vec<&'static str> {
+    let mut words: Vec<&'static str> = common.iter().chain(extra).copied().collect();
+    words.sort_unstable();
+    words.dedup();
+    words
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SuggestionKind {
+    Column,
+    Table,
+    Function,
+    Keyword,
+}
+impl SuggestionKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SuggestionKind::Column => "column",
+            SuggestionKind::Table => "table",
+            SuggestionKind::Function => "function",
+            SuggestionKind::Keyword => "keyword",
+        }
+    }
+}
+
+/// One entry of the completion popup.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Suggestion {
+    /// Text that replaces the word being typed, quoted where needed.
+    pub text: String,
+    pub kind: SuggestionKind,
+    /// Column type, owning table or kind, shown dimmed next to the text.
+    pub detail: String,
+}
+
+/// Suggestions for the word ending at the cursor.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Completion {
+    /// Byte range of the script the accepted suggestion replaces.
+    pub start: usize,
+    pub end: usize,
+    pub suggestions: Vec<Suggestion>,
+}
+
+/// The word being typed at byte `offset`: where it starts, the typed part and the name
+/// before a `.`, e.g. `u` for `u.na`.
+pub fn word_at(script: &str, offset: usize) -> (usize, &str, Option<String>) {
+    let before = &script[..offset];
+    let start = before
+        .char_indices()
+        .rev()
+        .take_while(|(_, c)| is_word_char(*c))
+        .last()
+        .map(|(i, _)| i)
+        .unwrap_or(offset);
+    let prefix = &script[start..offset];
+    let qualifier =
+        before[..start]
+            .strip_suffix('.')
+            .and_then(|rest| match rest.chars().next_back() {
+                Some(quote @ ('"' | '`' | ']')) => {
+                    let open = if quote == ']' { '[' } else { quote };
+                    let body = &rest[..rest.len() - 1];
+                    body.rfind(open).map(|i| body[i + 1..].to_string())
+                }
+                _ => {
+                    let name: String = rest
+                        .chars()
+                        .rev()
+                        .take_while(|c| is_word_char(*c))
+                        .collect::<Vec<_>>()
+                        .into_iter()
+                        .rev()
+                        .collect();
+                    (!name.is_empty()).then_some(name)
+                }
+            });
+    (start, prefix, qualifier)
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_' || c == '$'
+}
+
+/// A table named in a statement, e.g. `public.users u`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TableRef {
+    pub schema: Option<String>,
+    pub name: String,
+    pub alias: Option<String>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Word { text: String, quoted: bool },
+    Dot,
+    Comma,
+    Other,
+}
+impl Token {
+    fn keyword(&self) -> Option<String> {
+        match self {
+            Token::Word {
+                text,
+                quoted: false,
+            } => Some(text.to_ascii_uppercase()),
+            _ => None,
+        }
+    }
+}
+
+/// Splits SQL into words and punctuation, dropping strings, comments and whitespace.
+fn tokenize(sql: &str) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    let mut chars = sql.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '\'' => {
+                while let Some((_, c)) = chars.next() {
+                    match c {
+                        '\\' => {
+                            chars.next();
+                        }
+                        '\'' if chars.peek().is_some_and(|(_, c)| *c == '\'') => {
+                            chars.next();
+                        }
+                        '\'' => break,
+                        _ => {}
+                    }
+                }
+                tokens.push(Token::Other);
+            }
+            '"' | '`' | '[' => {
+                let close = if c == '[' { ']' } else { c };
+                let text: String = chars
+                    .by_ref()
+                    .map(|(_, c)| c)
+                    .take_while(|c| *c != close)
+                    .collect();
+                tokens.push(Token::Word { text, quoted: true });
+            }
+            '-' if sql[i..].starts_with("--") => {
+                chars.by_ref().find(|(_, c)| *c == '\n');
+            }
+            '/' if sql[i..].starts_with("/*") => {
+                chars.next();
+                let mut previous = ' ';
+                for (_, c) in chars.by_ref() {
+                    if previous == '*' && c == '/' {
+                        break;
+                    }
+                    previous = c;
+                }
+            }
+            '.' => tokens.push(Token::Dot),
+            ',' => tokens.push(Token::Comma),
+            c if is_word_char(c) => {
+                let mut end = i + c.len_utf8();
+                while let Some((j, c)) = chars.next_if(|(_, c)| is_word_char(*c)) {
+                    end = j + c.len_utf8();
+                }
+                tokens.push(Token::Word {
+                    text: sql[i..end].to_string(),
+                    quoted: false,
+                });
+            }
+            c if c.is_whitespace() => {}
+            _ => tokens.push(Token::Other),
+        }
+    }
+    tokens
+}
+
+/// Tables the statement reads or writes, with their aliases.
+pub fn table_refs(sql: &str) -> Vec<TableRef> {
+    let tokens = tokenize(sql);
+    let mut refs = Vec::new();
+    let mut i = 0;
+    while i < tokens.len() {
+        let keyword = tokens[i].keyword().unwrap_or_default();
+        i += 1;
+        if !TABLE_KEYWORDS.contains(&keyword.as_str()) {
+            continue;
+        }
+        // FROM a, b c lists several tables
+        while let Some((table, next)) = table_ref_at(&tokens, i) {
+            refs.push(table);
+            i = next;
+            if keyword != "FROM" || tokens.get(i) != Some(&Token::Comma) {
+                break;
+            }
+            i += 1;
+        }
+    }
+    refs
+}
+
+fn table_ref_at(tokens: &[Token], mut i: usize) -> Option<(TableRef, usize)> {
+    let name_at = |i: usize| match tokens.get(i) {
+        Some(token @ Token::Word { text, .. }) if !is_clause_keyword(token) => Some(text.clone()),
+        _ => None,
+    };
+    let mut name = name_at(i)?;
+    let mut schema = None;
+    i += 1;
+    if tokens.get(i) == Some(&Token::Dot)
+        && let Some(table) = name_at(i + 1)
+    {
+        schema = Some(std::mem::replace(&mut name, table));
+        i += 2;
+    }
+    if tokens.get(i).and_then(Token::keyword).as_deref() == Some("AS") {
+        i += 1;
+    }
+    let alias = name_at(i).filter(|alias| !alias.eq_ignore_ascii_case("AS"));
+    if alias.is_some() {
+        i += 1;
+    }
+    Some((
+        TableRef {
+            schema,
+            name,
+            alias,
+        },
+        i,
+    ))
+}
+
+fn is_clause_keyword(token: &Token) -> bool {
+    token
+        .keyword()
+        .is_some_and(|keyword| CLAUSE_KEYWORDS.contains(&keyword.as_str()))
+}
+
+/// Whether the word being typed follows FROM, JOIN and the like, or a comma in a FROM list.
+fn expects_table(tokens_before: &[Token]) -> bool {
+    let last_keyword =
+        |tokens: &[Token]| {
+            tokens.iter().rev().filter_map(Token::keyword).find(|k| {
+                TABLE_KEYWORDS.contains(&k.as_str()) || CLAUSE_KEYWORDS.contains(&k.as_str())
+            })
+        };
+    match tokens_before.last() {
+        Some(token) => match token.keyword() {
+            Some(keyword) => TABLE_KEYWORDS.contains(&keyword.as_str()),
+            None => {
+                *token == Token::Comma && last_keyword(tokens_before).as_deref() == Some("FROM")
+            }
+        },
+        None => false,
+    }
+}
+
+/// Completes the word ending at byte `offset` of the script.
+///
+/// After `name.` the columns of the table or alias `name` are offered, or the tables of the
+/// schema `name`. Otherwise columns of the tables in the statement come first, or tables right
+/// after FROM, JOIN, UPDATE and INTO, then functions and keywords. Returns `None` when fewer
+/// than `min_prefix` characters are typed or nothing matches.
+pub fn complete(
+    script: &str,
+    offset: usize,
+    dialect: Dialect,
+    tables: &[SchemaTable],
+    min_prefix: usize,
+) -> Option<Completion> {
+    let (start, prefix, qualifier) = word_at(script, offset);
+    if qualifier.is_none() && prefix.chars().count() < min_prefix {
+        return None;
+    }
+    let statement = splitter::statement_at(script, offset, dialect);
+    let (sql, statement_start) = match &statement {
+        Some(statement) => (
+            &script[statement.start..statement.end.max(offset)],
+            statement.start,
+        ),
+        None => (script, 0),
+    };
+    let refs = table_refs(sql);
+    let matches = |name: &str| {
+        name.len() >= prefix.len()
+            && name.is_char_boundary(prefix.len())
+            && name[..prefix.len()].eq_ignore_ascii_case(prefix)
+    };
+
+    let mut suggestions = Vec::new();
+    if let Some(qualifier) = qualifier {
+        qualified_suggestions(&qualifier, &refs, tables, dialect, &mut suggestions);
+        suggestions.retain(|s: &Suggestion| matches(unquoted(&s.text)));
+    } else {
+        let tokens_before = tokenize(&script[statement_start.min(start)..start]);
+        let referenced: Vec<&SchemaTable> =
+            refs.iter().filter_map(|r| resolve(r, tables)).collect();
+        let column_tables = if referenced.is_empty() {
+            tables.iter().collect()
+        } else {
+            referenced
+        };
+        let mut columns = Vec::new();
+        for table in column_tables {
+            for column in &table.columns {
+                if matches(&column.name)
+                    && !columns
+                        .iter()
+                        .any(|s: &Suggestion| unquoted(&s.text) == column.name)
+                {
+                    columns.push(column_suggestion(table, column, dialect));
+                }
+            }
+        }
+        let table_suggestions = tables
+            .iter()
+            .filter(|t| matches(&t.name))
+            .map(|t| table_suggestion(t, dialect));
+        let upper = !prefix.chars().any(|c| c.is_lowercase());
+        let word = |word: &str, kind| Suggestion {
+            text: if upper {
+                word.to_string()
+            } else {
+                word.to_lowercase()
+            },
+            kind,
+            detail: kind.as_str().to_string(),
+        };
+        let function_suggestions = functions(dialect)
+            .into_iter()
+            .filter(|f| matches(f))
+            .map(|f| word(f, SuggestionKind::Function));
+        let keyword_suggestions = keywords(dialect)
+            .into_iter()
+            .filter(|k| matches(k))
+            .map(|k| word(k, SuggestionKind::Keyword));
+
+        if expects_table(&tokens_before) {
+            suggestions.extend(table_suggestions);
+            suggestions.extend(keyword_suggestions);
+        } else {
+            suggestions.extend(columns);
+            suggestions.extend(table_suggestions);
+            suggestions.extend(function_suggestions);
+            suggestions.extend(keyword_suggestions);
+        }
+    }
+    // the word is already complete
+    if suggestions.len() == 1 && suggestions[0].text == prefix {
+        suggestions.clear();
+    }
+    suggestions.truncate(MAX_SUGGESTIONS);
+    (!suggestions.is_empty()).then_some(Completion {
+        start,
+        end: offset,
+        suggestions,
+    })
+}
+
+fn qualified_suggestions(
+    qualifier: &str,
+    refs: &[TableRef],
+    tables: &[SchemaTable],
+    dialect: Dialect,
+    suggestions: &mut Vec<Suggestion>,
+) {
+    // an alias wins over a table of the same name
+    let aliased = refs
+        .iter()
+        .find(|r| {
+            r.alias
+                .as_deref()
+                .is_some_and(|alias| alias.eq_ignore_ascii_case(qualifier))
+        })
+        .or_else(|| {
+            refs.iter()
+                .find(|r| r.alias.is_none() && r.name.eq_ignore_ascii_case(qualifier))
+        })
+        .and_then(|r| resolve(r, tables));
+    let table = aliased.or_else(|| {
+        tables.iter().find(|t| {
+            t.name.eq_ignore_ascii_case(qualifier)
+                && !tables
+                    .iter()
+                    .any(|s| s.schema.eq_ignore_ascii_case(qualifier))
+        })
+    });
+    match table {
+        Some(table) => suggestions.extend(
+            table
+                .columns
+                .iter()
+                .map(|column| column_suggestion(table, column, dialect)),
+        ),
+        None => suggestions.extend(
+            tables
+                .iter()
+                .filter(|t| t.schema.eq_ignore_ascii_case(qualifier))
+                .map(|t| Suggestion {
+                    text: schema_browser::identifier(&t.name, dialect),
+                    kind: SuggestionKind::Table,
+                    detail: table_detail(t),
+                }),
+        ),
+    }
+}
+
+fn resolve<'a>(table_ref: &TableRef, tables: &'a [SchemaTable]) -> Option<&'a SchemaTable> {
+    tables.iter().find(|t| {
+        t.name.eq_ignore_ascii_case(&table_ref.name)
+            && table_ref
+                .schema
+                .as_deref()
+                .is_none_or(|schema| t.schema.eq_ignore_ascii_case(schema))
+    })
+}
+
+fn column_suggestion(
+    table: &SchemaTable,
+    column: &crate::shared::ColumnMeta,
+    dialect: Dialect,
+) -> Suggestion {
+    Suggestion {
+        text: schema_browser::identifier(&column.name, dialect),
+        kind: SuggestionKind::Column,
+        detail: format!("{} {}", table.name, column.type_name.to_lowercase()),
+    }
+}
+
+fn table_suggestion(table: &SchemaTable, dialect: Dialect) -> Suggestion {
+    Suggestion {
+        text: schema_browser::qualified_name(table, dialect),
+        kind: SuggestionKind::Table,
+        detail: table_detail(table),
+    }
+}
+
+fn table_detail(table: &SchemaTable) -> String {
+    if table.is_view { "view" } else { "table" }.to_string()
+}
+
+/// The name inside identifier quotes, to match it against the typed prefix.
+fn unquoted(text: &str) -> &str {
+    let name = text.rsplit('.').next().unwrap_or(text);
+    name.trim_matches(|c| matches!(c, '"' | '`' | '[' | ']'))
+}
+
+// tests
+
+#[allow(dead_code)]
+fn test_tables() -> Vec<SchemaTable> {
+    use crate::shared::ColumnMeta;
+    let column = |name: &str, type_name: &str| ColumnMeta {
+        name: name.to_string(),
+        type_name: type_name.to_string(),
+        nullable: Some(true),
+    };
+    vec![
+        SchemaTable {
+            schema: "public".to_string(),
+            name: "users".to_string(),
+            columns: vec![
+                column("id", "INT4"),
+                column("name", "TEXT"),
+                column("email", "TEXT"),
+            ],
+            ..SchemaTable::default()
+        },
+        SchemaTable {
+            schema: "public".to_string(),
+            name: "orders".to_string(),
+            columns: vec![
+                column("id", "INT4"),
+                column("user_id", "INT4"),
+                column("total", "NUMERIC"),
+            ],
+            ..SchemaTable::default()
+        },
+        SchemaTable {
+            schema: "audit".to_string(),
+            name: "events".to_string(),
+            is_view: true,
+            columns: vec![column("name", "TEXT")],
+            ..SchemaTable::default()
+        },
+    ]
+}
+
+#[allow(dead_code)]
+fn texts(completion: Option<Completion>) -> Vec<String> {
+    completion
+        .map(|c| c.suggestions.into_iter().map(|s| s.text).collect())
+        .unwrap_or_default()
+}
+
+#[test]
+fn test_word_at() {
+    assert_eq!(word_at("SELECT na", 9), (7, "na", None));
+    assert_eq!(word_at("SELECT u.na", 11), (9, "na", Some("u".to_string())));
+    assert_eq!(word_at("SELECT u.", 9), (9, "", Some("u".to_string())));
+    assert_eq!(
+        word_at("SELECT \"My Table\".", 18),
+        (18, "", Some("My Table".to_string()))
+    );
+    assert_eq!(word_at("SELECT ", 7), (7, "", None));
+}
+
+#[test]
+fn test_table_refs_with_aliases() {
+    let refs = table_refs(
+        "SELECT * FROM users u JOIN public.orders AS o ON o.user_id = u.id, audit.events WHERE 1",
+    );
+    assert_eq!(
+        refs,
+        vec![
+            TableRef {
+                schema: None,
+                name: "users".to_string(),
+                alias: Some("u".to_string()),
+            },
+            TableRef {
+                schema: Some("public".to_string()),
+                name: "orders".to_string(),
+                alias: Some("o".to_string()),
+            },
+        ]
+    );
+    let refs = table_refs("SELECT * FROM users, \"Order Items\" oi WHERE 1");
+    assert_eq!(refs.len(), 2);
+    assert_eq!(refs[1].name, "Order Items");
+    assert_eq!(refs[1].alias.as_deref(), Some("oi"));
+    // strings and comments do not count
+    assert!(table_refs("SELECT 'from users' -- from users").is_empty());
+}
+
+#[test]
+fn test_complete_columns_of_alias() {
+    let tables = test_tables();
+    let script = "SELECT u. FROM users u";
+    assert_eq!(
+        texts(complete(script, 9, Dialect::Postgres, &tables, MIN_PREFIX)),
+        vec!["id", "name", "email"]
+    );
+    let script = "SELECT o.t FROM users u JOIN orders o ON o.user_id = u.id";
+    let completion = complete(script, 10, Dialect::Postgres, &tables, MIN_PREFIX).unwrap();
+    assert_eq!((completion.start, completion.end), (9, 10));
+    assert_eq!(completion.suggestions[0].text, "total");
+    assert_eq!(completion.suggestions[0].detail, "orders numeric");
+    // a table name works without an alias and a schema lists its tables
+    assert_eq!(
+        texts(complete(
+            "SELECT orders.u",
+            15,
+            Dialect::Postgres,
+            &tables,
+            0
+        )),
+        vec!["user_id"]
+    );
+    assert_eq!(
+        texts(complete(
+            "SELECT * FROM audit.",
+            20,
+            Dialect::Postgres,
+            &tables,
+            0
+        )),
+        vec!["events"]
+    );
+}
+
+#[test]
+fn test_complete_ranks_by_context() {
+    let tables = test_tables();
+    // columns of the statement's tables before functions and keywords
+    let script = "SELECT na FROM users; SELECT 1";
+    let completion = complete(script, 9, Dialect::Postgres, &tables, MIN_PREFIX).unwrap();
+    assert_eq!(completion.suggestions[0].text, "name");
+    assert_eq!(completion.suggestions[0].kind, SuggestionKind::Column);
+    // tables after FROM, qualified outside the default schema
+    let script = "SELECT * FROM ev";
+    assert_eq!(
+        texts(complete(script, 16, Dialect::Postgres, &tables, MIN_PREFIX)),
+        vec!["audit.events"]
+    );
+    let script = "SELECT * FROM users, or";
+    assert_eq!(
+        texts(complete(script, 23, Dialect::Postgres, &tables, MIN_PREFIX))[0],
+        "orders"
+    );
+    // keyword case follows the typed prefix
+    let script = "sel";
+    assert_eq!(
+        texts(complete(script, 3, Dialect::Postgres, &tables, MIN_PREFIX)),
+        vec!["select"]
+    );
+    assert!(complete("s", 1, Dialect::Postgres, &tables, MIN_PREFIX).is_none());
+    assert!(complete("SELECT", 6, Dialect::Postgres, &tables, MIN_PREFIX).is_none());
+}
+
+#[test]
+fn test_dialect_functions() {
+    assert!(functions(Dialect::Postgres).contains(&"STRING_AGG"));
+    assert!(!functions(Dialect::MySql).contains(&"STRING_AGG"));
+    assert!(functions(Dialect::MySql).contains(&"GROUP_CONCAT"));
+    assert!(keywords(Dialect::Sqlite).contains(&"PRAGMA"));
+    assert!(!keywords(Dialect::Generic).contains(&"PRAGMA"));
+    let tables = test_tables();
+    assert_eq!(
+        texts(complete("SELECT ILI", 10, Dialect::Postgres, &tables, 0)),
+        vec!["ILIKE"]
+    );
+    assert!(complete("SELECT ILI", 10, Dialect::MySql, &tables, 0).is_none());
+}
@@ -0,0 +1,519 @@
+// Copyright (c) 2025 mcpeaps_HD
+//
+// This software is released under the MIT License.
+// https://opensource.org/licenses/MIT
+use crate::shared::{ColumnMeta, SimpleSqlError, Table, Value};
+use arrow_array::{
+    ArrayRef, BinaryArray, BooleanArray, Date32Array, Decimal128Array, Float64Array, Int64Array,
+    RecordBatch, StringArray, Time64MicrosecondArray, TimestampMicrosecondArray,
+};
+use arrow_schema::{DECIMAL128_MAX_PRECISION, DataType, Field, Schema, TimeUnit};
+use chrono::{NaiveDate, NaiveTime, Timelike};
+use std::fs::File;
+use std::sync::Arc;
+
+/// Binary file formats a result set can be exported to.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum FileFormat {
+    Xlsx,
+    Parquet,
+    /// Arrow IPC file, also known as Feather v2.
+    Arrow,
+}
+impl FileFormat {
+    /// Picks the format from the extension of `path`, e.g. `results.xlsx`.
+    pub fn from_path(path: &str) -> Option<Self> {
+        let extension = std::path::Path::new(path).extension()?.to_str()?;
+        match extension.to_ascii_lowercase().as_str() {
+            "xlsx" => Some(FileFormat::Xlsx),
+            "parquet" => Some(FileFormat::Parquet),
+            "arrow" | "ipc" | "feather" => Some(FileFormat::Arrow),
+            _ => None,
+        }
+    }
+}
+
+/// Writes `table` to the file at `path`.
+pub fn export_table(table: &Table, format: FileFormat, path: &str) -> Result<(), SimpleSqlError> {
+    match format {
+        FileFormat::Xlsx => write_xlsx(table, path),
+        FileFormat::Parquet => {
+            let batch = record_batch(table)?;
+            let properties = parquet::file::properties::WriterProperties::builder()
+                .set_compression(parquet::basic::Compression::SNAPPY)
+                .build();
+            let mut writer = parquet::arrow::ArrowWriter::try_new(
+                File::create(path)?,
+                batch.schema(),
+                Some(properties),
+            )?;
+            writer.write(&batch)?;
+            writer.close()?;
+            Ok(())
+        }
+        FileFormat::Arrow => {
+            let batch = record_batch(table)?;
+            let mut writer =
+                arrow_ipc::writer::FileWriter::try_new(File::create(path)?, &batch.schema())?;
+            writer.write(&batch)?;
+            writer.finish()?;
+            Ok(())
+        }
+    }
+}
+
+/// Type of an exported column.
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum ColumnType {
+    Bool,
+    Int,
+    Float,
+    /// NUMERIC/DECIMAL, with the precision and scale needed by the values of the column.
+    Decimal {
+        precision: u8,
+        scale: i8,
+    },
+    Text,
+    Binary,
+    Date,
+    Time,
+    Timestamp,
+    /// Stored as UTC.
+    TimestampTz,
+}
+impl ColumnType {
+    /// Maps the SQL type of the column; types without a typed counterpart become text.
+    fn from_sql(type_name: &str) -> Self {
+        let upper = type_name.to_ascii_uppercase();
+        if upper.ends_with("[]") || upper.starts_with('_') {
+            return ColumnType::Text;
+        }
+        match upper.as_str() {
+            "BOOL" | "BOOLEAN" => ColumnType::Bool,
+            "INT2" | "INT4" | "INT8" | "SMALLINT" | "INT" | "INTEGER" | "BIGINT" | "TINYINT"
+            | "MEDIUMINT" | "SERIAL" | "BIGSERIAL" | "SMALLINT UNSIGNED" | "INT UNSIGNED"
+            | "TINYINT UNSIGNED" | "MEDIUMINT UNSIGNED" | "YEAR" => ColumnType::Int,
+            "FLOAT4" | "FLOAT8" | "REAL" | "FLOAT" | "DOUBLE" | "DOUBLE PRECISION" => {
+                ColumnType::Float
+            }
+            "BYTEA" | "BLOB" | "TINYBLOB" | "MEDIUMBLOB" | "LONGBLOB" | "BINARY" | "VARBINARY" => {
+                ColumnType::Binary
+            }
+            "DATE" => ColumnType::Date,
+            "TIME" => ColumnType::Time,
+            "TIMESTAMP" | "DATETIME" => ColumnType::Timestamp,
+            "TIMESTAMPTZ" => ColumnType::TimestampTz,
+            // the result set does not tell the precision, `of_column` takes it from the values
+            "NUMERIC" | "DECIMAL" => ColumnType::Decimal {
+                precision: 0,
+                scale: 0,
+            },
+            _ => ColumnType::Text,
+        }
+    }
+
+    fn accepts(self, value: &Value) -> bool {
+        matches!(
+            (self, value),
+            (_, Value::Null)
+                | (ColumnType::Text, _)
+                | (ColumnType::Bool, Value::Bool(_))
+                | (ColumnType::Int, Value::Int(_))
+                | (ColumnType::Float, Value::Float(_) | Value::Int(_))
+                | (
+                    ColumnType::Decimal { .. },
+                    Value::Decimal(_) | Value::Int(_)
+                )
+                | (ColumnType::Binary, Value::Bytes(_))
+                | (ColumnType::Date, Value::Date(_))
+                | (ColumnType::Time, Value::Time(_))
+                | (ColumnType::Timestamp, Value::Timestamp(_))
+                | (ColumnType::TimestampTz, Value::TimestampTz(_))
+        )
+    }
+
+    /// Type for column `idx`, falling back to text when a value does not fit the SQL type,
+    /// e.g. in SQLite where a column can hold anything, or a decimal has more than 38 digits.
+    fn of_column(table: &Table, idx: usize) -> Self {
+        let declared = ColumnType::from_sql(&table.columns[idx].type_name);
+        let values = || table.rows.iter().filter_map(|row| row.get(idx));
+        if !values().all(|value| declared.accepts(value)) {
+            return ColumnType::Text;
+        }
+        if let ColumnType::Decimal { .. } = declared {
+            return decimal_type(values()).unwrap_or(ColumnType::Text);
+        }
+        declared
+    }
+
+    fn data_type(self) -> DataType {
+        match self {
+            ColumnType::Bool => DataType::Boolean,
+            ColumnType::Int => DataType::Int64,
+            ColumnType::Float => DataType::Float64,
+            ColumnType::Decimal { precision, scale } => DataType::Decimal128(precision, scale),
+            ColumnType::Text => DataType::Utf8,
+            ColumnType::Binary => DataType::Binary,
+            ColumnType::Date => DataType::Date32,
+            ColumnType::Time => DataType::Time64(TimeUnit::Microsecond),
+            ColumnType::Timestamp => DataType::Timestamp(TimeUnit::Microsecond, None),
+            ColumnType::TimestampTz => {
+                DataType::Timestamp(TimeUnit::Microsecond, Some("UTC".into()))
+            }
+        }
+    }
+}
+
+fn record_batch(table: &Table) -> Result<RecordBatch, SimpleSqlError> {
+    let mut fields = Vec::with_capacity(table.columns.len());
+    let mut arrays = Vec::with_capacity(table.columns.len());
+    for (idx, column) in table.columns.iter().enumerate() {
+        let column_type = ColumnType::of_column(table, idx);
+        let values: Vec<&Value> = table
+            .rows
+            .iter()
+            .map(|row| row.get(idx).unwrap_or(&Value::Null))
+            .collect();
+        let has_nulls = values.iter().any(|v| v.is_null());
+        fields.push(Field::new(
+            column.name.as_str(),
+            column_type.data_type(),
+            has_nulls || column.nullable != Some(false),
+        ));
+        arrays.push(arrow_array(column_type, &values)?);
+    }
+    Ok(RecordBatch::try_new(Arc::new(Schema::new(fields)), arrays)?)
+}
+
+fn arrow_array(column_type: ColumnType, values: &[&Value]) -> Result<ArrayRef, SimpleSqlError> {
+    let epoch = NaiveDate::from_ymd_opt(1970, 1, 1).unwrap_or_default();
+    Ok(match column_type {
+        ColumnType::Bool => Arc::new(BooleanArray::from_iter(values.iter().map(|v| match v {
+            Value::Bool(b) => Some(*b),
+            _ => None,
+        }))),
+        ColumnType::Int => Arc::new(Int64Array::from_iter(values.iter().map(|v| match v {
+            Value::Int(i) => Some(*i),
+            _ => None,
+        }))),
+        ColumnType::Float => Arc::new(Float64Array::from_iter(values.iter().map(|v| match v {
+            Value::Float(x) => Some(*x),
+            Value::Int(i) => Some(*i as f64),
+            _ => None,
+        }))),
+        ColumnType::Decimal { precision, scale } => Arc::new(
+            Decimal128Array::from_iter(values.iter().map(|v| match v {
+                Value::Decimal(d) => decimal_unscaled(d, scale),
+                Value::Int(i) => decimal_unscaled(&i.to_string(), scale),
+                _ => None,
+            }))
+            .with_precision_and_scale(precision, scale)?,
+        ),
+        ColumnType::Binary => Arc::new(BinaryArray::from_iter(values.iter().map(|v| match v {
+            Value::Bytes(bytes) => Some(bytes.as_slice()),
+            _ => None,
+        }))),
+        ColumnType::Date => Arc::new(Date32Array::from_iter(values.iter().map(|v| match v {
+            Value::Date(d) => Some((*d - epoch).num_days() as i32),
+            _ => None,
+        }))),
+        ColumnType::Time => Arc::new(Time64MicrosecondArray::from_iter(values.iter().map(
+            |v| match v {
+                Value::Time(t) => Some(micros_since_midnight(t)),
+                _ => None,
+            },
+        ))),
+        ColumnType::Timestamp => Arc::new(TimestampMicrosecondArray::from_iter(values.iter().map(
+            |v| match v {
+                Value::Timestamp(t) => Some(t.and_utc().timestamp_micros()),
+                _ => None,
+            },
+        ))),
+        ColumnType::TimestampTz => Arc::new(
+            TimestampMicrosecondArray::from_iter(values.iter().map(|v| match v {
+                Value::TimestampTz(t) => Some(t.timestamp_micros()),
+                _ => None,
+            }))
+            .with_timezone("UTC"),
+        ),
+        ColumnType::Text => Arc::new(StringArray::from_iter(values.iter().map(|v| match v {
+            Value::Null => None,
+            v => Some(v.to_plain_string()),
+        }))),
+    })
+}
+
+/// Splits a decimal such as `-12.340` into its sign and the digits before and after the point,
+/// `None` for anything else, e.g. PostgreSQL's `NaN`.
+fn decimal_parts(text: &str) -> Option<(bool, &str, &str)> {
+    let (negative, digits) = match text.strip_prefix('-') {
+        Some(digits) => (true, digits),
+        None => (false, text.strip_prefix('+').unwrap_or(text)),
+    };
+    let (int, frac) = digits.split_once('.').unwrap_or((digits, ""));
+    let valid = !(int.is_empty() && frac.is_empty())
+        && int.bytes().chain(frac.bytes()).all(|b| b.is_ascii_digit());
+    valid.then_some((negative, int, frac))
+}
+
+/// Decimal128 type that holds every value, `None` when one is not a decimal or needs more
+/// digits than Decimal128 has.
+fn decimal_type<'a>(values: impl Iterator<Item = &'a Value>) -> Option<ColumnType> {
+    let (mut int_digits, mut scale) = (0, 0);
+    for value in values {
+        let text = match value {
+            Value::Decimal(d) => d.clone(),
+            Value::Int(i) => i.to_string(),
+            _ => continue,
+        };
+        let (_, int, frac) = decimal_parts(&text)?;
+        int_digits = int_digits.max(int.trim_start_matches('0').len());
+        scale = scale.max(frac.len());
+    }
+    let precision = (int_digits + scale).max(1);
+    (precision <= DECIMAL128_MAX_PRECISION as usize).then_some(ColumnType::Decimal {
+        precision: precision as u8,
+        scale: scale as i8,
+    })
+}
+
+/// The value of a decimal in units of `10^-scale`, e.g. 1234 for `12.34` at scale 2.
+fn decimal_unscaled(text: &str, scale: i8) -> Option<i128> {
+    let (negative, int, frac) = decimal_parts(text)?;
+    let digits = format!("{int}{frac:0<width$}", width = scale as usize);
+    let unscaled: i128 = digits.parse().ok()?;
+    Some(if negative { -unscaled } else { unscaled })
+}
+
+/// A decimal as a double when that keeps all of its digits, i.e. it has at most 15 of them.
+fn decimal_as_f64(text: &str) -> Option<f64> {
+    let (_, int, frac) = decimal_parts(text)?;
+    let digits = format!("{int}{frac}");
+    let significant = digits.trim_start_matches('0').trim_end_matches('0');
+    if significant.len() > XLSX_MAX_EXACT_DIGITS {
+        return None;
+    }
+    text.parse().ok()
+}
+
+fn micros_since_midnight(time: &NaiveTime) -> i64 {
+    time.num_seconds_from_midnight() as i64 * 1_000_000 + (time.nanosecond() / 1_000) as i64
+}
+
+/// Excel stores numbers as doubles, larger integers are written as text to keep their digits.
+const XLSX_MAX_EXACT_INT: i64 = 1 << 53;
+/// Decimal digits a double keeps; decimals with more are written as text.
+const XLSX_MAX_EXACT_DIGITS: usize = 15;
+const XLSX_MAX_COLUMN_WIDTH: usize = 60;
+
+fn write_xlsx(table: &Table, path: &str) -> Result<(), SimpleSqlError> {
+    use rust_xlsxwriter::{Format, Workbook};
+
+    let mut workbook = Workbook::new();
+    let sheet = workbook.add_worksheet();
+    let header = Format::new().set_bold();
+    let date = Format::new().set_num_format("yyyy-mm-dd");
+    let time = Format::new().set_num_format("hh:mm:ss");
+    let timestamp = Format::new().set_num_format("yyyy-mm-dd hh:mm:ss");
+
+    for (col, column) in table.columns.iter().enumerate() {
+        let col = col as u16;
+        sheet.write_string_with_format(0, col, &column.name, &header)?;
+        sheet.set_column_width(col, column_width(table, col as usize) as f64)?;
+    }
+    sheet.set_freeze_panes(1, 0)?;
+
+    for (row_idx, row) in table.rows.iter().enumerate() {
+        let row_num = row_idx as u32 + 1;
+        for (col, value) in row.iter().enumerate() {
+            let col = col as u16;
+            match value {
+                Value::Null => {}
+                Value::Bool(b) => {
+                    sheet.write_boolean(row_num, col, *b)?;
+                }
+                Value::Int(i) if i.unsigned_abs() <= XLSX_MAX_EXACT_INT as u64 => {
+                    sheet.write_number(row_num, col, *i as f64)?;
+                }
+                Value::Float(x) if x.is_finite() => {
+                    sheet.write_number(row_num, col, *x)?;
+                }
+                Value::Decimal(d) => match decimal_as_f64(d) {
+                    Some(x) => {
+                        sheet.write_number(row_num, col, x)?;
+                    }
+                    None => {
+                        sheet.write_string(row_num, col, d)?;
+                    }
+                },
+                Value::Date(d) => {
+                    sheet.write_datetime_with_format(row_num, col, d, &date)?;
+                }
+                Value::Time(t) => {
+                    sheet.write_datetime_with_format(row_num, col, t, &time)?;
+                }
+                Value::Timestamp(t) => {
+                    sheet.write_datetime_with_format(row_num, col, t, &timestamp)?;
+                }
+                Value::TimestampTz(t) => {
+                    sheet.write_datetime_with_format(row_num, col, t.naive_utc(), &timestamp)?;
+                }
+                other => {
+                    sheet.write_string(row_num, col, other.to_plain_string())?;
+                }
+            }
+        }
+    }
+    workbook.save(path)?;
+    Ok(())
+}
+
+/// Width in characters that fits the header and the widest value, capped for long texts.
+fn column_width(table: &Table, idx: usize) -> usize {
+    let header = table
+        .columns
+        .get(idx)
+        .map(|c: &ColumnMeta| c.name.chars().count());
+    let widest = table
+        .rows
+        .iter()
+        .filter_map(|row| row.get(idx))
+        .map(|v| v.to_plain_string().chars().count())
+        .max();
+    (header.unwrap_or(0).max(widest.unwrap_or(0)) + 2).min(XLSX_MAX_COLUMN_WIDTH)
+}
+
+// tests
+
+#[allow(dead_code)]
+fn typed_table() -> Table {
+    let column = |name: &str, type_name: &str| ColumnMeta {
+        name: name.to_string(),
+        type_name: type_name.to_string(),
+        nullable: Some(true),
+    };
+    Table {
+        columns: vec![
+            column("id", "INT8"),
+            column("price", "NUMERIC"),
+            column("created", "TIMESTAMP"),
+            column("mixed", "INTEGER"),
+        ],
+        rows: vec![
+            vec![
+                Value::Int(1),
+                Value::Decimal("9.99".to_string()),
+                Value::Timestamp(
+                    NaiveDate::from_ymd_opt(2025, 1, 2)
+                        .unwrap()
+                        .and_hms_opt(3, 4, 5)
+                        .unwrap(),
+                ),
+                Value::Int(7),
+            ],
+            vec![
+                Value::Null,
+                Value::Null,
+                Value::Null,
+                Value::Text("seven".to_string()),
+            ],
+        ],
+        ..Table::default()
+    }
+}
+
+#[test]
+fn test_file_format_from_path() {
+    assert_eq!(FileFormat::from_path("a/b.XLSX"), Some(FileFormat::Xlsx));
+    assert_eq!(
+        FileFormat::from_path("b.parquet"),
+        Some(FileFormat::Parquet)
+    );
+    assert_eq!(FileFormat::from_path("b.feather"), Some(FileFormat::Arrow));
+    assert_eq!(FileFormat::from_path("b.csv"), None);
+}
+
+#[test]
+fn test_record_batch_types_follow_column_metadata() {
+    let batch = record_batch(&typed_table()).unwrap();
+    let types: Vec<DataType> = batch
+        .schema()
+        .fields()
+        .iter()
+        .map(|f| f.data_type().clone())
+        .collect();
+    assert_eq!(
+        types,
+        vec![
+            DataType::Int64,
+            DataType::Decimal128(3, 2),
+            DataType::Timestamp(TimeUnit::Microsecond, None),
+            // SQLite stored text in an INTEGER column
+            DataType::Utf8,
+        ]
+    );
+    assert_eq!(batch.num_rows(), 2);
+    assert_eq!(batch.column(0).null_count(), 1);
+}
+
+#[test]
+fn test_export_files() {
+    let dir = std::env::temp_dir().join(format!("simplesql_export_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    for name in ["out.xlsx", "out.parquet", "out.arrow"] {
+        let path = dir.join(name);
+        let path = path.to_str().unwrap();
+        let format = FileFormat::from_path(path).unwrap();
+        export_table(&typed_table(), format, path).unwrap();
+        assert!(std::fs::metadata(path).unwrap().len() > 0);
+    }
+    let parquet = std::fs::read(dir.join("out.parquet")).unwrap();
+    assert!(parquet.starts_with(b"PAR1"));
+    let arrow = std::fs::read(dir.join("out.arrow")).unwrap();
+    assert!(arrow.starts_with(b"ARROW1"));
+    // integers beyond 2^53 are written as text; i64::MIN has no positive counterpart
+    let extremes = Table {
+        columns: typed_table().columns[..1].to_vec(),
+        rows: vec![vec![Value::Int(i64::MIN)], vec![Value::Int(i64::MAX)]],
+        ..Table::default()
+    };
+    let path = dir.join("extremes.xlsx");
+    write_xlsx(&extremes, path.to_str().unwrap()).unwrap();
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_decimals() {
+    let column = ColumnMeta {
+        name: "amount".to_string(),
+        type_name: "NUMERIC".to_string(),
+        nullable: None,
+    };
+    let table = |values: &[&str]| Table {
+        columns: vec![column.clone()],
+        rows: values
+            .iter()
+            .map(|v| vec![Value::Decimal(v.to_string())])
+            .collect(),
+        ..Table::default()
+    };
+    let batch = record_batch(&table(&["-12.5", "0.001", "300"])).unwrap();
+    let array = batch
+        .column(0)
+        .as_any()
+        .downcast_ref::<Decimal128Array>()
+        .unwrap();
+    assert_eq!(array.precision(), 6);
+    assert_eq!(array.scale(), 3);
+    assert_eq!(array.values().to_vec(), vec![-12500, 1, 300000]);
+    // more digits than Decimal128 holds, or no number at all
+    let wide = record_batch(&table(&["1234567890123456789012345678901234567890"])).unwrap();
+    assert_eq!(wide.schema().field(0).data_type(), &DataType::Utf8);
+    let nan = record_batch(&table(&["NaN"])).unwrap();
+    assert_eq!(nan.schema().field(0).data_type(), &DataType::Utf8);
+
+    assert_eq!(decimal_as_f64("-12.3400"), Some(-12.34));
+    assert_eq!(decimal_as_f64("0.000000000000000001"), Some(1e-18));
+    assert_eq!(decimal_as_f64("1234567890.1234567"), None);
+    assert_eq!(decimal_as_f64("NaN"), None);
+}
@@ -0,0 +1,427 @@
+// Copyright (c) 2025 mcpeaps_HD
+//
+// This software is released under the MIT License.
+// https://opensource.org/licenses/MIT
+use crate::shared::{self, SimpleSqlError};
+#[allow(unused_imports)]
+use log::{debug, error, info, trace, warn};
+use std::path::{MAIN_SEPARATOR, Path, PathBuf};
+
+/// Files kept in the recent files list.
+pub const MAX_RECENT: usize = 10;
+
+/// A row of a directory listing.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FileEntry {
+    pub name: String,
+    pub is_dir: bool,
+}
+
+/// A row of the picker: a recent file or an entry of the listed directory.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PickerItem {
+    pub path: String,
+    pub label: String,
+    pub is_dir: bool,
+    pub recent: bool,
+}
+
+/// The directory listing and recent files behind the F8/F9 popup, following the typed path.
+#[derive(Clone, Debug)]
+pub struct FilePicker {
+    /// List every file instead of only `.sql` files.
+    pub show_all: bool,
+    /// Most recent first.
+    pub recent: Vec<String>,
+    /// Directory of the typed path.
+    pub dir: PathBuf,
+    /// Entries of [`FilePicker::dir`], or why it cannot be read.
+    pub entries: Result<Vec<FileEntry>, String>,
+    /// Index into [`FilePicker::items`]; `None` while the typed path is used.
+    pub selected: Option<usize>,
+}
+impl FilePicker {
+    pub fn new(recent: Vec<String>, input: &str) -> Self {
+        let mut picker = FilePicker {
+            show_all: false,
+            recent,
+            dir: PathBuf::new(),
+            entries: Ok(Vec::new()),
+            selected: None,
+        };
+        picker.reload(input);
+        picker
+    }
+
+    /// Follows a change of the typed path, listing its directory again when it changed.
+    pub fn update(&mut self, input: &str) {
+        self.selected = None;
+        if split_input(input).0 != self.dir {
+            self.reload(input);
+        }
+    }
+
+    /// Lists the directory of the typed path again.
+    pub fn reload(&mut self, input: &str) {
+        self.dir = split_input(input).0;
+        self.entries = list_dir(&self.dir, self.show_all).map_err(|e| e.to_string());
+        self.selected = None;
+    }
+
+    /// Recent files, then the entries of the directory, that start with the typed file name.
+    pub fn items(&self, input: &str) -> Vec<PickerItem> {
+        let prefix = split_input(input).1;
+        let recent = self
+            .recent
+            .iter()
+            .filter(|path| starts_with(file_name(path), &prefix))
+            .map(|path| PickerItem {
+                path: path.clone(),
+                label: path.clone(),
+                is_dir: false,
+                recent: true,
+            });
+        let entries = self
+            .entries
+            .iter()
+            .flatten()
+            .filter(|entry| starts_with(&entry.name, &prefix) && shown(entry, &prefix))
+            .map(|entry| PickerItem {
+                path: match entry.name.as_str() {
+                    ".." => self.dir.parent().unwrap_or(&self.dir).to_path_buf(),
+                    _ => self.dir.join(&entry.name),
+                }
+                .to_string_lossy()
+                .into_owned(),
+                label: match entry.is_dir {
+                    true => format!("{}{}", entry.name, MAIN_SEPARATOR),
+                    false => entry.name.clone(),
+                },
+                is_dir: entry.is_dir,
+                recent: false,
+            });
+        recent.chain(entries).collect()
+    }
+
+    pub fn selected_item(&self, input: &str) -> Option<PickerItem> {
+        self.items(input).get(self.selected?).cloned()
+    }
+
+    /// Moves the selection `step` rows, back to the typed path above the first row.
+    pub fn move_selection(&mut self, step: isize, input: &str) {
+        let count = self.items(input).len() as isize;
+        let next = self.selected.map_or(-1, |selected| selected as isize) + step;
+        self.selected = match next {
+            n if n < 0 => None,
+            n => Some(n.min(count - 1) as usize).filter(|_| count > 0),
+        };
+    }
+
+    /// Completes the typed file name as far as the directory entries agree, ignoring case like
+    /// the listing; a single directory gets its separator so the next Tab goes on inside it.
+    pub fn complete(&self, input: &str) -> Option<String> {
+        let prefix = split_input(input).1;
+        let entries: Vec<&FileEntry> = self
+            .entries
+            .iter()
+            .flatten()
+            .filter(|entry| entry.name != ".." && starts_with(&entry.name, &prefix))
+            .filter(|entry| shown(entry, &prefix))
+            .collect();
+        let first = entries.first()?;
+        let common = entries
+            .iter()
+            .fold(first.name.chars().count(), |common, entry| {
+                first
+                    .name
+                    .chars()
+                    .zip(entry.name.chars())
+                    .take(common)
+                    .take_while(|(a, b)| a.to_lowercase().eq(b.to_lowercase()))
+                    .count()
+            });
+        // the typed part keeps its case unless a single entry tells the right one
+        let completion = match entries.len() {
+            1 => first.name.clone(),
+            _ => {
+                let rest: String = first
+                    .name
+                    .chars()
+                    .take(common)
+                    .skip(prefix.chars().count())
+                    .collect();
+                format!("{}{}", prefix, rest)
+            }
+        };
+        // the prefix comes from the path with `~` expanded, which the input may not be
+        let expanded = expand_home(input);
+        let mut completed = format!(
+            "{}{}",
+            &expanded[..expanded.len() - prefix.len()],
+            completion
+        );
+        if entries.len() == 1 && first.is_dir {
+            completed.push(MAIN_SEPARATOR);
+        }
+        if expanded != input {
+            let home = &expanded[..expanded.len() - (input.len() - 1)];
+            if let Some(rest) = completed.strip_prefix(home) {
+                completed = format!("~{}", rest);
+            }
+        }
+        (completed != input).then_some(completed)
+    }
+}
+
+fn file_name(path: &str) -> &str {
+    Path::new(path)
+        .file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or(path)
+}
+
+/// Hidden files are only listed once their name is being typed.
+fn shown(entry: &FileEntry, prefix: &str) -> bool {
+    entry.name == ".." || !entry.name.starts_with('.') || prefix.starts_with('.')
+}
+
+/// Whether `name` starts with `prefix`, ignoring case like most file systems users meet.
+fn starts_with(name: &str, prefix: &str) -> bool {
+    name.to_lowercase().starts_with(&prefix.to_lowercase())
+}
+
+/// The directory of a typed path and the file name typed in it so far. `~` stands for the home
+/// directory, relative paths start in the working directory.
+pub fn split_input(input: &str) -> (PathBuf, String) {
+    let expanded = expand_home(input);
+    let (dir, prefix) = match expanded.rfind(['/', MAIN_SEPARATOR]) {
+        Some(idx) => (&expanded[..=idx], &expanded[idx + 1..]),
+        None => ("", expanded.as_str()),
+    };
+    let dir = match dir {
+        "" => std::env::current_dir().unwrap_or_default(),
+        dir => PathBuf::from(dir),
+    };
+    (dir, prefix.to_string())
+}
+
+/// The path with a leading `~` replaced by the home directory.
+pub fn expand_home(input: &str) -> String {
+    match (input.strip_prefix('~'), std::env::var("HOME")) {
+        (Some(rest), Ok(home)) if rest.is_empty() || rest.starts_with(['/', MAIN_SEPARATOR]) => {
+            format!("{}{}", home, rest)
+        }
+        _ => input.to_string(),
+    }
+}
+
+/// Directories first, then files, each sorted by name; only `.sql` files unless `show_all`.
+pub fn list_dir(dir: &Path, show_all: bool) -> std::io::Result<Vec<FileEntry>> {
+    let mut dirs = Vec::new();
+    let mut files = Vec::new();
+    for dir_entry in std::fs::read_dir(dir)? {
+        let dir_entry = dir_entry?;
+        let name = dir_entry.file_name().to_string_lossy().into_owned();
+        // follows links, so a link to a directory can be entered
+        if dir_entry.path().is_dir() {
+            dirs.push(FileEntry { name, is_dir: true });
+        } else if show_all || name.to_lowercase().ends_with(".sql") {
+            files.push(FileEntry {
+                name,
+                is_dir: false,
+            });
+        }
+    }
+    dirs.sort_by(|a, b| a.name.cmp(&b.name));
+    files.sort_by(|a, b| a.name.cmp(&b.name));
+    let parent = dir.parent().map(|_| FileEntry {
+        name: "..".to_string(),
+        is_dir: true,
+    });
+    Ok(parent.into_iter().chain(dirs).chain(files).collect())
+}
+
+/// Adds `.sql` to a file name without an extension.
+pub fn with_sql_extension(path: &str) -> String {
+    match Path::new(path).extension() {
+        Some(_) => path.to_string(),
+        None => format!("{}.sql", path),
+    }
+}
+
+/// Recently loaded and saved files that still exist, most recent first.
+pub fn load_recent() -> Vec<String> {
+    shared::get_recent_files_path()
+        .ok()
+        .and_then(|path| shared::read_file(&path).ok())
+        .map(|content| {
+            content
+                .lines()
+                .filter(|path| Path::new(path).is_file())
+                .map(str::to_string)
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Puts `path` on top of the recent files and stores the list.
+pub fn remember_recent(path: &str) -> Result<(), SimpleSqlError> {
+    let recent = push_recent(load_recent(), path);
+    let mut content = recent.join("\n");
+    content.push('\n');
+    shared::write_file(&shared::get_recent_files_path()?, &content)?;
+    Ok(())
+}
+
+fn push_recent(mut recent: Vec<String>, path: &str) -> Vec<String> {
+    let path = std::fs::canonicalize(path)
+        .map(|path| path.to_string_lossy().into_owned())
+        .unwrap_or_else(|_| path.to_string());
+    recent.retain(|other| *other != path);
+    recent.insert(0, path);
+    recent.truncate(MAX_RECENT);
+    recent
+}
+
+// tests
+
+#[allow(dead_code)]
+fn test_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("simplesql_{}_{}", name, std::process::id()));
+    std::fs::create_dir_all(dir.join("reports")).unwrap();
+    for file in [
+        "a.sql",
+        "b.SQL",
+        "notes.txt",
+        ".hidden.sql",
+        "reports/r.sql",
+    ] {
+        shared::write_file(dir.join(file).to_str().unwrap(), "SELECT 1").unwrap();
+    }
+    dir
+}
+
+#[test]
+fn test_complete_home() {
+    let Ok(home) = std::env::var("HOME") else {
+        return;
+    };
+    let picker = FilePicker::new(Vec::new(), "~");
+    let completed = picker.complete("~");
+    assert!(
+        completed.is_none() || completed.as_deref() == Some(&format!("~{}", MAIN_SEPARATOR)),
+        "{:?}",
+        completed
+    );
+    let dir = test_dir("home");
+    let picker = FilePicker::new(Vec::new(), "~/x");
+    assert!(
+        picker
+            .complete("~/x")
+            .is_none_or(|path| path.starts_with("~/x"))
+    );
+    // a path below the home directory keeps its `~`
+    if let Ok(rest) = dir.strip_prefix(&home) {
+        let input = format!("~/{}/re", rest.to_str().unwrap());
+        let picker = FilePicker::new(Vec::new(), &input);
+        assert_eq!(
+            picker.complete(&input),
+            Some(format!("~/{}/reports/", rest.to_str().unwrap()))
+        );
+    }
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_split_input() {
+    let (dir, prefix) = split_input("/tmp/queries/rep");
+    assert_eq!(dir, PathBuf::from("/tmp/queries/"));
+    assert_eq!(prefix, "rep");
+    let (dir, prefix) = split_input("/tmp/queries/");
+    assert_eq!(dir, PathBuf::from("/tmp/queries/"));
+    assert_eq!(prefix, "");
+    let (dir, prefix) = split_input("query.sql");
+    assert_eq!(dir, std::env::current_dir().unwrap());
+    assert_eq!(prefix, "query.sql");
+    assert_eq!(with_sql_extension("/tmp/q"), "/tmp/q.sql");
+    assert_eq!(with_sql_extension("/tmp/q.txt"), "/tmp/q.txt");
+}
+
+#[test]
+fn test_listing_and_completion() {
+    let dir = test_dir("picker");
+    let base = format!("{}/", dir.to_str().unwrap());
+    let mut picker = FilePicker::new(Vec::new(), &base);
+    let labels = |picker: &FilePicker, input: &str| -> Vec<String> {
+        picker
+            .items(input)
+            .into_iter()
+            .map(|item| item.label)
+            .collect()
+    };
+    assert_eq!(
+        labels(&picker, &base),
+        vec!["../", "reports/", "a.sql", "b.SQL"]
+    );
+    picker.show_all = true;
+    picker.reload(&base);
+    assert_eq!(
+        labels(&picker, &base),
+        vec!["../", "reports/", "a.sql", "b.SQL", "notes.txt"]
+    );
+    assert_eq!(labels(&picker, &format!("{}.h", base)), vec![".hidden.sql"]);
+    // a single directory gets its separator, several files their common start
+    let input = format!("{}rep", base);
+    assert_eq!(picker.complete(&input), Some(format!("{}reports/", base)));
+    assert_eq!(
+        picker.complete(&format!("{}no", base)),
+        Some(format!("{}notes.txt", base))
+    );
+    assert_eq!(picker.complete(&base), None);
+    // entries differing in case still complete as far as they agree, never shorter
+    for file in ["Sales_2024.sql", "sales_2025.sql"] {
+        shared::write_file(&format!("{}{}", base, file), "SELECT 1").unwrap();
+    }
+    picker.reload(&base);
+    assert_eq!(
+        picker.complete(&format!("{}sal", base)),
+        Some(format!("{}sales_202", base))
+    );
+    assert_eq!(picker.complete(&format!("{}SALES_202", base)), None);
+    assert_eq!(
+        picker.complete(&format!("{}SALES_2024", base)),
+        Some(format!("{}Sales_2024.sql", base))
+    );
+    picker.update(&format!("{}reports/", base));
+    assert_eq!(
+        labels(&picker, &format!("{}reports/", base)),
+        vec!["../", "r.sql"]
+    );
+    // the selection goes back to the typed path above the first row
+    picker.move_selection(1, &base);
+    assert_eq!(picker.selected, Some(0));
+    picker.move_selection(-1, &base);
+    assert_eq!(picker.selected, None);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_recent_files() {
+    let mut recent = Vec::new();
+    for i in 0..12 {
+        recent = push_recent(recent, &format!("/nowhere/{}.sql", i));
+    }
+    recent = push_recent(recent, "/nowhere/5.sql");
+    assert_eq!(recent.len(), MAX_RECENT);
+    assert_eq!(recent[0], "/nowhere/5.sql");
+    assert_eq!(recent[1], "/nowhere/11.sql");
+    assert_eq!(recent.iter().filter(|p| *p == "/nowhere/5.sql").count(), 1);
+    let picker = FilePicker {
+        recent,
+        ..FilePicker::new(Vec::new(), "/nowhere/")
+    };
+    assert!(picker.entries.is_err());
+    let items = picker.items("/nowhere/1");
+    assert_eq!(items.len(), 2);
+    assert!(items.iter().all(|item| item.recent));
+}
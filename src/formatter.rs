@@ -0,0 +1,575 @@
+// Copyright (c) 2025 mcpeaps_HD
+//
+// This software is released under the MIT License.
+// https://opensource.org/licenses/MIT
+use crate::shared::{ColumnMeta, Dialect, Table, Value};
+use std::io::{self, Write};
+
+/// Serializations of a result set, shared by the headless CLI and the TUI export.
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub enum Format {
+    /// Aligned text table for terminals.
+    #[default]
+    Table,
+    Csv,
+    /// Tab separated, escaped like PostgreSQL `COPY ... TO` text format.
+    Tsv,
+    /// One JSON array of row objects.
+    Json,
+    /// One JSON object per line.
+    Ndjson,
+    Markdown,
+    Html,
+    /// `INSERT INTO ...` statements that recreate the rows.
+    Insert,
+}
+impl Format {
+    pub const NAMES: &'static str = "table, csv, tsv, json, ndjson, markdown, html, insert";
+
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "table" => Some(Format::Table),
+            "csv" => Some(Format::Csv),
+            "tsv" => Some(Format::Tsv),
+            "json" => Some(Format::Json),
+            "ndjson" | "jsonl" => Some(Format::Ndjson),
+            "markdown" | "md" => Some(Format::Markdown),
+            "html" => Some(Format::Html),
+            "insert" | "sql" => Some(Format::Insert),
+            _ => None,
+        }
+    }
+
+    /// Picks the format from the extension of `path`, e.g. `results.csv`.
+    pub fn from_path(path: &str) -> Option<Self> {
+        let extension = std::path::Path::new(path).extension()?.to_str()?;
+        match extension.to_ascii_lowercase().as_str() {
+            "txt" => Some(Format::Table),
+            "htm" => Some(Format::Html),
+            "table" => None,
+            other => Format::parse(other),
+        }
+    }
+}
+
+/// Writes `table` in `format`. `dialect` decides how `INSERT` statements quote their values.
+pub fn write(
+    out: &mut impl Write,
+    table: &Table,
+    format: Format,
+    dialect: Dialect,
+) -> io::Result<()> {
+    match format {
+        Format::Table => write_table(out, table),
+        Format::Csv => write_csv(out, table),
+        Format::Tsv => write_tsv(out, table),
+        Format::Json => write_json(out, table),
+        Format::Ndjson => write_ndjson(out, table),
+        Format::Markdown => write_markdown(out, table),
+        Format::Html => write_html(out, table),
+        Format::Insert => write_inserts(out, table, dialect),
+    }
+}
+
+/// Like [`write`], into a string.
+pub fn to_string(table: &Table, format: Format, dialect: Dialect) -> String {
+    let mut out = Vec::new();
+    // writing to a Vec cannot fail
+    let _ = write(&mut out, table, format, dialect);
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+/// Writes the result set as an aligned text table, like the `psql` and `mysql` clients.
+pub fn write_table(out: &mut impl Write, table: &Table) -> io::Result<()> {
+    let rows: Vec<Vec<String>> = table
+        .rows
+        .iter()
+        .map(|row| row.iter().map(|v| v.to_plain_string()).collect())
+        .collect();
+    let mut widths: Vec<usize> = table
+        .columns
+        .iter()
+        .map(|c| c.name.chars().count())
+        .collect();
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+
+    let header: Vec<String> = table
+        .columns
+        .iter()
+        .zip(&widths)
+        .map(|(c, w)| format!("{:<w$}", c.name, w = *w))
+        .collect();
+    writeln!(out, "{}", header.join(" | ").trim_end())?;
+    let separator: Vec<String> = widths.iter().map(|w| "-".repeat(*w)).collect();
+    writeln!(out, "{}", separator.join("-+-"))?;
+    for (row, values) in rows.iter().zip(&table.rows) {
+        let cells: Vec<String> = row
+            .iter()
+            .zip(values)
+            .zip(&widths)
+            .map(|((cell, value), w)| {
+                if value.is_numeric() {
+                    format!("{:>w$}", cell, w = *w)
+                } else {
+                    format!("{:<w$}", cell, w = *w)
+                }
+            })
+            .collect();
+        writeln!(out, "{}", cells.join(" | ").trim_end())?;
+    }
+    Ok(())
+}
+
+/// Writes every row as a block of `column | value` lines, like `\x` in `psql`.
+pub fn write_expanded(out: &mut impl Write, table: &Table) -> io::Result<()> {
+    let name_width = table
+        .columns
+        .iter()
+        .map(|c| c.name.chars().count())
+        .max()
+        .unwrap_or(0);
+    let value_width = table
+        .rows
+        .iter()
+        .flatten()
+        .map(|v| v.to_plain_string().chars().count())
+        .max()
+        .unwrap_or(0);
+    for (idx, row) in table.rows.iter().enumerate() {
+        let title = format!("-[ RECORD {} ]", idx + 1);
+        let width = (name_width + 3 + value_width).max(title.chars().count());
+        writeln!(out, "{:-<width$}", title, width = width)?;
+        for (column, value) in table.columns.iter().zip(row) {
+            let line = format!(
+                "{:<w$} | {}",
+                column.name,
+                value.to_plain_string(),
+                w = name_width
+            );
+            writeln!(out, "{}", line.trim_end())?;
+        }
+    }
+    Ok(())
+}
+
+/// Writes the result set as RFC 4180 CSV with a header line. NULL becomes an empty field.
+pub fn write_csv(out: &mut impl Write, table: &Table) -> io::Result<()> {
+    let header: Vec<String> = table.columns.iter().map(|c| csv_field(&c.name)).collect();
+    write!(out, "{}\r\n", header.join(","))?;
+    for row in &table.rows {
+        let fields: Vec<String> = row
+            .iter()
+            .map(|v| {
+                if v.is_null() {
+                    String::new()
+                } else {
+                    csv_field(&v.to_plain_string())
+                }
+            })
+            .collect();
+        write!(out, "{}\r\n", fields.join(","))?;
+    }
+    Ok(())
+}
+
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\r', '\n']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+/// Writes the result set as tab separated values with a header line.
+///
+/// NULL becomes `\N`; backslashes, tabs and line breaks are escaped with a backslash.
+pub fn write_tsv(out: &mut impl Write, table: &Table) -> io::Result<()> {
+    let header: Vec<String> = table.columns.iter().map(|c| tsv_field(&c.name)).collect();
+    writeln!(out, "{}", header.join("\t"))?;
+    for row in &table.rows {
+        let fields: Vec<String> = row
+            .iter()
+            .map(|v| {
+                if v.is_null() {
+                    "\\N".to_string()
+                } else {
+                    tsv_field(&v.to_plain_string())
+                }
+            })
+            .collect();
+        writeln!(out, "{}", fields.join("\t"))?;
+    }
+    Ok(())
+}
+
+fn tsv_field(field: &str) -> String {
+    let mut escaped = String::with_capacity(field.len());
+    for c in field.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            '\t' => escaped.push_str("\\t"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Writes the rows as a JSON array of objects keyed by column name.
+pub fn write_json(out: &mut impl Write, table: &Table) -> io::Result<()> {
+    if table.rows.is_empty() {
+        return writeln!(out, "[]");
+    }
+    writeln!(out, "[")?;
+    for (idx, row) in table.rows.iter().enumerate() {
+        let separator = if idx + 1 < table.rows.len() { "," } else { "" };
+        writeln!(out, "  {}{}", json_object(&table.columns, row), separator)?;
+    }
+    writeln!(out, "]")
+}
+
+/// Writes one JSON object per row and line.
+pub fn write_ndjson(out: &mut impl Write, table: &Table) -> io::Result<()> {
+    for row in &table.rows {
+        writeln!(out, "{}", json_object(&table.columns, row))?;
+    }
+    Ok(())
+}
+
+/// Builds the object by hand, so the keys keep the column order and duplicates survive.
+fn json_object(columns: &[ColumnMeta], row: &[Value]) -> String {
+    let fields: Vec<String> = columns
+        .iter()
+        .zip(row)
+        .map(|(column, value)| {
+            format!(
+                "{}:{}",
+                serde_json::Value::from(column.name.as_str()),
+                json_value(value)
+            )
+        })
+        .collect();
+    format!("{{{}}}", fields.join(","))
+}
+
+/// Numbers and booleans stay JSON scalars; exact decimals are kept as strings so no
+/// precision is lost, and JSON columns are embedded as they are.
+fn json_value(value: &Value) -> serde_json::Value {
+    match value {
+        Value::Null => serde_json::Value::Null,
+        Value::Bool(b) => (*b).into(),
+        Value::Int(i) => (*i).into(),
+        Value::Float(x) => serde_json::Number::from_f64(*x)
+            .map(serde_json::Value::Number)
+            .unwrap_or_else(|| x.to_string().into()),
+        Value::Json(j) => j.clone(),
+        Value::Array(items) => items.iter().map(json_value).collect(),
+        other => other.to_string().into(),
+    }
+}
+
+/// Writes a GitHub flavoured Markdown table; numeric columns are right-aligned.
+pub fn write_markdown(out: &mut impl Write, table: &Table) -> io::Result<()> {
+    let header: Vec<String> = table
+        .columns
+        .iter()
+        .map(|c| markdown_cell(&c.name))
+        .collect();
+    writeln!(out, "| {} |", header.join(" | "))?;
+    let alignment: Vec<&str> = (0..table.columns.len())
+        .map(|idx| {
+            let numeric = table
+                .rows
+                .iter()
+                .filter_map(|row| row.get(idx))
+                .find(|v| !v.is_null())
+                .is_some_and(Value::is_numeric);
+            if numeric { "---:" } else { "---" }
+        })
+        .collect();
+    writeln!(out, "| {} |", alignment.join(" | "))?;
+    for row in &table.rows {
+        let cells: Vec<String> = row
+            .iter()
+            .map(|v| markdown_cell(&v.to_plain_string()))
+            .collect();
+        writeln!(out, "| {} |", cells.join(" | "))?;
+    }
+    Ok(())
+}
+
+fn markdown_cell(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace('|', "\\|")
+        .replace("\r\n", "<br>")
+        .replace('\n', "<br>")
+}
+
+/// Writes an HTML `<table>`; NULL cells get the class `null` so they can be styled.
+pub fn write_html(out: &mut impl Write, table: &Table) -> io::Result<()> {
+    writeln!(out, "<table>")?;
+    writeln!(out, "  <thead>")?;
+    let header: String = table
+        .columns
+        .iter()
+        .map(|c| format!("<th>{}</th>", html_escape(&c.name)))
+        .collect();
+    writeln!(out, "    <tr>{}</tr>", header)?;
+    writeln!(out, "  </thead>")?;
+    writeln!(out, "  <tbody>")?;
+    for row in &table.rows {
+        let cells: String = row
+            .iter()
+            .map(|v| match v {
+                Value::Null => "<td class=\"null\">NULL</td>".to_string(),
+                v => format!("<td>{}</td>", html_escape(&v.to_plain_string())),
+            })
+            .collect();
+        writeln!(out, "    <tr>{}</tr>", cells)?;
+    }
+    writeln!(out, "  </tbody>")?;
+    writeln!(out, "</table>")
+}
+
+fn html_escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Writes one `INSERT INTO` statement per row.
+///
+/// The target is the table after the first `FROM` of the statement, or `result`.
+pub fn write_inserts(out: &mut impl Write, table: &Table, dialect: Dialect) -> io::Result<()> {
+    let target =
+        insert_target(&table.statement).unwrap_or_else(|| quote_identifier("result", dialect));
+    let columns: Vec<String> = table
+        .columns
+        .iter()
+        .map(|c| quote_identifier(&c.name, dialect))
+        .collect();
+    for row in &table.rows {
+        let values: Vec<String> = row.iter().map(|v| sql_literal(v, dialect)).collect();
+        writeln!(
+            out,
+            "INSERT INTO {} ({}) VALUES ({});",
+            target,
+            columns.join(", "),
+            values.join(", ")
+        )?;
+    }
+    Ok(())
+}
+
+/// Returns the (possibly qualified and quoted) table name following the first `FROM`.
+fn insert_target(statement: &str) -> Option<String> {
+    let mut words = statement.split_whitespace();
+    words.find(|w| w.eq_ignore_ascii_case("from"))?;
+    let name: String = words
+        .next()?
+        .chars()
+        .take_while(|c| !matches!(c, ';' | ',' | '(' | ')'))
+        .collect();
+    (!name.is_empty()).then_some(name)
+}
+
+pub fn quote_identifier(name: &str, dialect: Dialect) -> String {
+    match dialect {
+        Dialect::MySql => format!("`{}`", name.replace('`', "``")),
+        _ => format!("\"{}\"", name.replace('"', "\"\"")),
+    }
+}
+
+fn quote_string(text: &str, dialect: Dialect) -> String {
+    let escaped = match dialect {
+        Dialect::MySql => text.replace('\\', "\\\\").replace('\'', "''"),
+        _ => text.replace('\'', "''"),
+    };
+    format!("'{}'", escaped)
+}
+
+/// Renders `value` as a literal that the database of `dialect` reads back as the same value.
+pub fn sql_literal(value: &Value, dialect: Dialect) -> String {
+    match value {
+        Value::Null => "NULL".to_string(),
+        Value::Bool(true) => "TRUE".to_string(),
+        Value::Bool(false) => "FALSE".to_string(),
+        Value::Int(i) => i.to_string(),
+        Value::Float(x) if x.is_finite() => x.to_string(),
+        Value::Decimal(d) => d.clone(),
+        Value::Bytes(bytes) => {
+            let hex: String = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+            match dialect {
+                Dialect::Postgres => format!("'\\x{}'", hex),
+                _ => format!("X'{}'", hex),
+            }
+        }
+        Value::Array(items) if dialect == Dialect::Postgres && !items.is_empty() => {
+            let items: Vec<String> = items.iter().map(|v| sql_literal(v, dialect)).collect();
+            format!("ARRAY[{}]", items.join(", "))
+        }
+        Value::Array(items) if dialect == Dialect::Postgres && items.is_empty() => {
+            "'{}'".to_string()
+        }
+        other => quote_string(&other.to_plain_string(), dialect),
+    }
+}
+
+// tests
+
+#[allow(dead_code)]
+fn test_table() -> Table {
+    Table {
+        columns: ["id", "name"]
+            .iter()
+            .map(|name| ColumnMeta {
+                name: name.to_string(),
+                ..ColumnMeta::default()
+            })
+            .collect(),
+        rows: vec![
+            vec![Value::Int(1), Value::Text("plain".to_string())],
+            vec![
+                Value::Int(10),
+                Value::Text("a, \"quoted\"\nvalue".to_string()),
+            ],
+            vec![Value::Int(100), Value::Null],
+        ],
+        ..Table::default()
+    }
+}
+
+#[test]
+fn test_write_csv_quotes_fields() {
+    let mut out = Vec::new();
+    write_csv(&mut out, &test_table()).unwrap();
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        "id,name\r\n1,plain\r\n10,\"a, \"\"quoted\"\"\nvalue\"\r\n100,\r\n"
+    );
+}
+
+#[test]
+fn test_write_table_aligns_columns() {
+    let mut table = test_table();
+    table.rows.truncate(1);
+    table.rows.push(vec![Value::Int(100), Value::Null]);
+    let mut out = Vec::new();
+    write_table(&mut out, &table).unwrap();
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        "id  | name\n----+------\n  1 | plain\n100 | NULL\n"
+    );
+}
+
+#[test]
+fn test_write_expanded() {
+    let mut table = test_table();
+    table.rows.truncate(1);
+    let mut out = Vec::new();
+    write_expanded(&mut out, &table).unwrap();
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        "-[ RECORD 1 ]\nid   | 1\nname | plain\n"
+    );
+}
+
+#[test]
+fn test_format_parse_and_extension() {
+    assert_eq!(Format::parse("CSV"), Some(Format::Csv));
+    assert_eq!(Format::parse("jsonl"), Some(Format::Ndjson));
+    assert_eq!(Format::parse("xml"), None);
+    assert_eq!(Format::from_path("/tmp/out.md"), Some(Format::Markdown));
+    assert_eq!(Format::from_path("out.HTM"), Some(Format::Html));
+    assert_eq!(Format::from_path("out.sql"), Some(Format::Insert));
+    assert_eq!(Format::from_path("out"), None);
+}
+
+#[test]
+fn test_write_tsv_escapes_fields() {
+    assert_eq!(
+        to_string(&test_table(), Format::Tsv, Dialect::Generic),
+        "id\tname\n1\tplain\n10\ta, \"quoted\"\\nvalue\n100\t\\N\n"
+    );
+}
+
+#[test]
+fn test_write_json_and_ndjson() {
+    let mut table = test_table();
+    table.rows.push(vec![
+        Value::Float(0.5),
+        Value::Json(serde_json::json!({"a": [1]})),
+    ]);
+    assert_eq!(
+        to_string(&table, Format::Ndjson, Dialect::Generic),
+        "{\"id\":1,\"name\":\"plain\"}\n\
+         {\"id\":10,\"name\":\"a, \\\"quoted\\\"\\nvalue\"}\n\
+         {\"id\":100,\"name\":null}\n\
+         {\"id\":0.5,\"name\":{\"a\":[1]}}\n"
+    );
+    table.rows.truncate(1);
+    assert_eq!(
+        to_string(&table, Format::Json, Dialect::Generic),
+        "[\n  {\"id\":1,\"name\":\"plain\"}\n]\n"
+    );
+    table.rows.clear();
+    assert_eq!(to_string(&table, Format::Json, Dialect::Generic), "[]\n");
+}
+
+#[test]
+fn test_write_markdown() {
+    let mut table = test_table();
+    table.rows[0][1] = Value::Text("a|b".to_string());
+    assert_eq!(
+        to_string(&table, Format::Markdown, Dialect::Generic),
+        "| id | name |\n| ---: | --- |\n| 1 | a\\|b |\n\
+         | 10 | a, \"quoted\"<br>value |\n| 100 | NULL |\n"
+    );
+}
+
+#[test]
+fn test_write_html_escapes_cells() {
+    let mut table = test_table();
+    table.rows = vec![vec![Value::Text("<b>&</b>".to_string()), Value::Null]];
+    assert_eq!(
+        to_string(&table, Format::Html, Dialect::Generic),
+        "<table>\n  <thead>\n    <tr><th>id</th><th>name</th></tr>\n  </thead>\n  <tbody>\n\
+         \x20   <tr><td>&lt;b&gt;&amp;&lt;/b&gt;</td><td class=\"null\">NULL</td></tr>\n\
+         \x20 </tbody>\n</table>\n"
+    );
+}
+
+#[test]
+fn test_write_inserts() {
+    let mut table = test_table();
+    table.statement = "select id, name from app.users where id > 0".to_string();
+    table.rows = vec![
+        vec![Value::Int(1), Value::Text("it's \\\\ ok".to_string())],
+        vec![Value::Bytes(vec![0xde, 0xad]), Value::Null],
+    ];
+    assert_eq!(
+        to_string(&table, Format::Insert, Dialect::Postgres),
+        "INSERT INTO app.users (\"id\", \"name\") VALUES (1, 'it''s \\\\ ok');\n\
+         INSERT INTO app.users (\"id\", \"name\") VALUES ('\\xdead', NULL);\n"
+    );
+    table.statement = "show tables".to_string();
+    table.rows.truncate(1);
+    assert_eq!(
+        to_string(&table, Format::Insert, Dialect::MySql),
+        "INSERT INTO `result` (`id`, `name`) VALUES (1, 'it''s \\\\\\\\ ok');\n"
+    );
+}
@@ -0,0 +1,326 @@
+// Copyright (c) 2025 mcpeaps_HD
+//
+// This software is released under the MIT License.
+// https://opensource.org/licenses/MIT
+use crate::formatter::{self, Format};
+use crate::shared::{
+    self, AppState, Config, ConnectionStatus, Credential, Dialect, SimpleSqlError, Table,
+};
+use crate::splitter::{self, Statement};
+use std::io::{self, Write};
+use std::time::Duration;
+
+/// Exit codes of the non-interactive mode.
+#[derive(Clone, Copy, PartialEq, Debug)]
+#[repr(i32)]
+pub enum ExitCode {
+    Ok = 0,
+    /// A statement failed.
+    QueryFailed = 1,
+    /// Unknown credential, connection or output format.
+    Usage = 2,
+    /// The config file cannot be read or has no credentials.
+    Config = 3,
+    /// The database cannot be reached.
+    Connection = 4,
+}
+
+/// What to run and where, as given on the command line.
+#[derive(Clone, Debug, Default)]
+pub struct Options {
+    pub sql: String,
+    pub connection: Option<String>,
+    pub user: Option<String>,
+    pub db: Option<String>,
+    pub format: Format,
+    /// Overrides `continue_on_error` from the config when set.
+    pub continue_on_error: Option<bool>,
+    /// Run the whole script in one transaction.
+    pub transaction: bool,
+}
+
+/// Runs `options.sql` without the TUI and returns the exit code for the process.
+///
+/// Result sets go to stdout, the status and timing of every statement to stderr.
+pub fn run(options: &Options) -> ExitCode {
+    let mut state = match load_state(options) {
+        Ok(state) => state,
+        Err(code) => return code,
+    };
+    state.sql_query = options.sql.clone();
+    let transaction = {
+        let mut config = state.config.lock().unwrap();
+        if let Some(continue_on_error) = options.continue_on_error {
+            config.settings.continue_on_error = continue_on_error;
+        }
+        config.settings.transaction |= options.transaction;
+        config.settings.transaction
+    };
+    let statements = splitter::split_statements(&options.sql, state.dialect());
+
+    let outcome = shared::run_query(&mut state);
+    let results = state.results.lock().unwrap().clone();
+    if outcome.is_ok() || !results.is_empty() {
+        eprint!(
+            "{}",
+            status_report(&statements, &results, transaction && outcome.is_err())
+        );
+    }
+    if let Err(e) = print_results(&results, options.format, state.dialect())
+        && e.kind() != io::ErrorKind::BrokenPipe
+    {
+        eprintln!("Cannot write results: {}", e);
+        return ExitCode::QueryFailed;
+    }
+    match outcome {
+        Ok(()) if results.iter().any(|t| t.error.is_some()) => ExitCode::QueryFailed,
+        Ok(()) => ExitCode::Ok,
+        Err(e) => match e {
+            SimpleSqlError::ConnectionNotFound(_) => {
+                eprintln!("{}", e);
+                ExitCode::Config
+            }
+            SimpleSqlError::Database(_)
+                if matches!(state.connection_status(), ConnectionStatus::Failed(_)) =>
+            {
+                eprintln!("Cannot connect to {}: {}", state.user.connection, e);
+                ExitCode::Connection
+            }
+            // the failing statement is already reported with the results
+            SimpleSqlError::Database(_) => ExitCode::QueryFailed,
+            e => {
+                eprintln!("{}", e);
+                ExitCode::QueryFailed
+            }
+        },
+    }
+}
+
+/// Loads the config and selects the credential and database given in `options`.
+///
+/// Problems are reported on stderr; the error is the exit code to give up with.
+pub fn load_state(options: &Options) -> Result<AppState, ExitCode> {
+    let mut state = AppState::load().map_err(|e| {
+        eprintln!("{}", e);
+        ExitCode::Config
+    })?;
+    let user = {
+        let config = state.config.lock().unwrap();
+        select_credential(
+            &config,
+            options.connection.as_deref(),
+            options.user.as_deref(),
+        )
+    };
+    state.user = user.map_err(|message| {
+        eprintln!("{}", message);
+        ExitCode::Usage
+    })?;
+    state.db = options
+        .db
+        .clone()
+        .or_else(|| state.user.default_database.clone())
+        .unwrap_or_default();
+    Ok(state)
+}
+
+/// Picks the credential to connect with.
+///
+/// `user` selects a credential by name, `connection` alone the first credential of that
+/// connection. Without either the first credential of the config is used.
+pub fn select_credential(
+    config: &Config,
+    connection: Option<&str>,
+    user: Option<&str>,
+) -> Result<Credential, String> {
+    if let Some(connection) = connection
+        && !config.connections.iter().any(|c| c.name == connection)
+    {
+        return Err(format!("Connection \"{}\" not found in config", connection));
+    }
+    let credential = match (connection, user) {
+        (_, Some(user)) => config
+            .credentials
+            .iter()
+            .find(|c| c.name == user)
+            .ok_or_else(|| format!("User \"{}\" not found in config", user))?,
+        (Some(connection), None) => config
+            .credentials
+            .iter()
+            .find(|c| c.connection == connection)
+            .ok_or_else(|| format!("No user configured for connection \"{}\"", connection))?,
+        (None, None) => config
+            .credentials
+            .first()
+            .ok_or_else(|| SimpleSqlError::NoCredentials.to_string())?,
+    };
+    if let Some(connection) = connection
+        && credential.connection != connection
+    {
+        return Err(format!(
+            "User \"{}\" belongs to connection \"{}\", not \"{}\"",
+            credential.name, credential.connection, connection
+        ));
+    }
+    Ok(credential.clone())
+}
+
+/// One line per statement with its outcome and timing, followed by a total.
+///
+/// Statements without a result were skipped after an error.
+pub fn status_report(statements: &[Statement], results: &[Table], rolled_back: bool) -> String {
+    let total = statements.len().max(results.len());
+    let mut report = String::new();
+    let mut failed = 0;
+    let mut elapsed = Duration::ZERO;
+    for (idx, table) in results.iter().enumerate() {
+        let status = if table.error.is_some() {
+            failed += 1;
+            "FAILED"
+        } else {
+            "OK"
+        };
+        elapsed += table.duration;
+        report.push_str(&format!(
+            "[{}/{}] {} ({:.1?}) {}: {}\n",
+            idx + 1,
+            total,
+            status,
+            table.duration,
+            excerpt(&table.statement),
+            table.summary().trim_start_matches("Error: ")
+        ));
+    }
+    for (idx, statement) in statements.iter().enumerate().skip(results.len()) {
+        report.push_str(&format!(
+            "[{}/{}] SKIPPED {}\n",
+            idx + 1,
+            total,
+            excerpt(&statement.sql)
+        ));
+    }
+    if rolled_back {
+        report.push_str("Transaction rolled back\n");
+    }
+    report.push_str(&format!(
+        "{} statement(s), {} failed, {} skipped in {:.1?}\n",
+        total,
+        failed,
+        total - results.len(),
+        elapsed
+    ));
+    report
+}
+
+/// First line of a statement, shortened for status lines.
+fn excerpt(sql: &str) -> String {
+    const MAX_CHARS: usize = 60;
+    let line = sql.lines().next().unwrap_or_default().trim();
+    if line.chars().count() > MAX_CHARS || sql.trim_end().contains('\n') {
+        let short: String = line.chars().take(MAX_CHARS).collect();
+        format!("{}...", short.trim_end())
+    } else {
+        line.to_string()
+    }
+}
+
+fn print_results(results: &[Table], format: Format, dialect: Dialect) -> io::Result<()> {
+    let stdout = io::stdout();
+    let mut out = stdout.lock();
+    let mut first = true;
+    for table in results
+        .iter()
+        .filter(|t| t.error.is_none() && !t.columns.is_empty())
+    {
+        if !first {
+            writeln!(out)?;
+        }
+        first = false;
+        formatter::write(&mut out, table, format, dialect)?;
+    }
+    out.flush()
+}
+
+// tests
+
+#[allow(dead_code)]
+fn test_config() -> Config {
+    toml::from_str(
+        r#"
+[[connections]]
+  name = "pg"
+  type = "postgres"
+[[connections]]
+  name = "lite"
+  type = "sqlite"
+[[credentials]]
+  name = "pg_admin"
+  connection = "pg"
+  username = "admin"
+  password = ""
+[[credentials]]
+  name = "lite_user"
+  connection = "lite"
+  username = ""
+  password = ""
+  default_database = "main"
+"#,
+    )
+    .unwrap()
+}
+
+#[test]
+fn test_select_credential() {
+    let config = test_config();
+    let name = |connection, user| select_credential(&config, connection, user).map(|c| c.name);
+    assert_eq!(name(None, None), Ok("pg_admin".to_string()));
+    assert_eq!(name(Some("lite"), None), Ok("lite_user".to_string()));
+    assert_eq!(name(None, Some("lite_user")), Ok("lite_user".to_string()));
+    assert_eq!(
+        name(Some("pg"), Some("pg_admin")),
+        Ok("pg_admin".to_string())
+    );
+    assert!(name(Some("pg"), Some("lite_user")).is_err());
+    assert!(name(Some("missing"), None).is_err());
+    assert!(name(None, Some("missing")).is_err());
+    assert_eq!(
+        select_credential(&config, None, Some("lite_user"))
+            .unwrap()
+            .default_database,
+        Some("main".to_string())
+    );
+    assert_eq!(
+        select_credential(&config, None, None)
+            .unwrap()
+            .default_database,
+        None
+    );
+}
+
+#[test]
+fn test_status_report() {
+    let script = "create table t (id int);\ninsert into t\nvalues (1);\nselect * from t";
+    let statements = splitter::split_statements(script, crate::shared::Dialect::Generic);
+    let ok = Table {
+        kind: crate::shared::StatementKind::Ddl,
+        statement: statements[0].sql.clone(),
+        rows_affected: Some(0),
+        duration: Duration::from_millis(2),
+        ..Table::default()
+    };
+    let failed = Table {
+        kind: crate::shared::StatementKind::Insert,
+        statement: statements[1].sql.clone(),
+        error: Some("no such table".to_string()),
+        duration: Duration::from_millis(1),
+        ..Table::default()
+    };
+    assert_eq!(
+        status_report(&statements, &[ok, failed], true),
+        "[1/3] OK (2.0ms) create table t (id int): Query OK, 0 row(s) affected\n\
+         [2/3] FAILED (1.0ms) insert into t...: no such table\n\
+         [3/3] SKIPPED select * from t\n\
+         Transaction rolled back\n\
+         3 statement(s), 1 failed, 1 skipped in 3.0ms\n"
+    );
+}
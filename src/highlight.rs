@@ -0,0 +1,213 @@
+// Copyright (c) 2025 mcpeaps_HD
+//
+// This software is released under the MIT License.
+// https://opensource.org/licenses/MIT
+use crate::shared::{Dialect, NordColor};
+use edtui::syntect::easy::HighlightLines;
+use edtui::syntect::highlighting::{
+    Color as SyntectColor, ScopeSelectors, StyleModifier, Theme, ThemeItem, ThemeSettings,
+};
+use edtui::syntect::parsing::{SyntaxDefinition, SyntaxReference, SyntaxSet, SyntaxSetBuilder};
+#[allow(unused_imports)]
+use log::{debug, error, info, trace, warn};
+use ratatui::style::Color;
+use std::str::FromStr;
+use std::sync::OnceLock;
+
+const MYSQL_SYNTAX: &str = include_str!("syntaxes/mysql.sublime-syntax");
+const POSTGRES_SYNTAX: &str = include_str!("syntaxes/postgresql.sublime-syntax");
+
+/// Token colors of the editor, by scope selector; the most specific selector wins.
+const TOKEN_COLORS: &[(&str, NordColor)] = &[
+    ("comment", NordColor::Nord3),
+    ("constant.character.escape", NordColor::Nord13),
+    ("string", NordColor::Nord14),
+    ("constant.numeric", NordColor::Nord15),
+    ("constant.language", NordColor::Nord9),
+    ("variable.other.quoted", NordColor::Nord7),
+    ("variable.parameter, variable.other.user", NordColor::Nord10),
+    ("support.function", NordColor::Nord8),
+    ("storage.type", NordColor::Nord7),
+    ("keyword.operator", NordColor::Nord9),
+    ("keyword", NordColor::Nord9),
+    ("punctuation", NordColor::Nord6),
+];
+
+/// Color of text no rule applies to; it is left to the editor theme.
+const PLAIN: NordColor = NordColor::Nord4;
+
+/// The bundled MySQL and PostgreSQL grammars, built on first use.
+fn bundled_syntaxes() -> &'static SyntaxSet {
+    static SYNTAXES: OnceLock<SyntaxSet> = OnceLock::new();
+    SYNTAXES.get_or_init(|| {
+        let mut builder = SyntaxSetBuilder::new();
+        for (name, source) in [("MySQL", MYSQL_SYNTAX), ("PostgreSQL", POSTGRES_SYNTAX)] {
+            match SyntaxDefinition::load_from_str(source, true, Some(name)) {
+                Ok(syntax) => builder.add(syntax),
+                Err(e) => error!("Cannot load the {} grammar: {}", name, e),
+            }
+        }
+        builder.add_plain_text_syntax();
+        builder.build()
+    })
+}
+
+/// syntect's generic SQL grammar, for SQLite and unknown connection types.
+fn default_syntaxes() -> &'static SyntaxSet {
+    static SYNTAXES: OnceLock<SyntaxSet> = OnceLock::new();
+    SYNTAXES.get_or_init(SyntaxSet::load_defaults_newlines)
+}
+
+/// The grammar for the dialect and the set it belongs to.
+pub fn syntax(dialect: Dialect) -> (&'static SyntaxSet, &'static SyntaxReference) {
+    let bundled = match dialect {
+        Dialect::MySql => Some("MySQL"),
+        Dialect::Postgres => Some("PostgreSQL"),
+        Dialect::Sqlite | Dialect::Generic => None,
+    };
+    if let Some(name) = bundled
+        && let Some(syntax) = bundled_syntaxes().find_syntax_by_name(name)
+    {
+        return (bundled_syntaxes(), syntax);
+    }
+    let set = default_syntaxes();
+    let syntax = set
+        .find_syntax_by_extension("sql")
+        .unwrap_or_else(|| set.find_syntax_plain_text());
+    (set, syntax)
+}
+
+fn syntect_color(color: &NordColor) -> SyntectColor {
+    let [r, g, b, a] = color.value().to_be_bytes();
+    SyntectColor { r, g, b, a }
+}
+
+/// A syntect theme made of the Nord palette.
+pub fn theme() -> &'static Theme {
+    static THEME: OnceLock<Theme> = OnceLock::new();
+    THEME.get_or_init(|| Theme {
+        name: Some("simplesql nord".to_string()),
+        author: None,
+        settings: ThemeSettings {
+            foreground: Some(syntect_color(&PLAIN)),
+            ..ThemeSettings::default()
+        },
+        scopes: TOKEN_COLORS
+            .iter()
+            .filter_map(|(selector, color)| {
+                Some(ThemeItem {
+                    scope: ScopeSelectors::from_str(selector).ok()?,
+                    style: StyleModifier {
+                        foreground: Some(syntect_color(color)),
+                        background: None,
+                        font_style: None,
+                    },
+                })
+            })
+            .collect(),
+    })
+}
+
+/// Token colors of an editor text, one per character; `None` keeps the editor's text color.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Highlighted {
+    pub dialect: Dialect,
+    pub text: String,
+    /// Indexed by line and character, like the editor's cursor.
+    pub colors: Vec<Vec<Option<Color>>>,
+}
+impl Highlighted {
+    /// Highlights the whole text, so strings and comments spanning lines are colored right.
+    pub fn new(text: &str, dialect: Dialect) -> Self {
+        let (set, syntax) = syntax(dialect);
+        let mut highlighter = HighlightLines::new(syntax, theme());
+        let plain = syntect_color(&PLAIN);
+        let colors = text
+            .split('\n')
+            .map(|line| {
+                // the grammars expect the line break
+                let line = format!("{}\n", line.strip_suffix('\r').unwrap_or(line));
+                let ranges = highlighter.highlight_line(&line, set).unwrap_or_else(|e| {
+                    debug!("Cannot highlight line: {}", e);
+                    Vec::new()
+                });
+                let mut colors: Vec<Option<Color>> = ranges
+                    .into_iter()
+                    .flat_map(|(style, token)| {
+                        let color = (style.foreground != plain).then(|| {
+                            let c = style.foreground;
+                            Color::Rgb(c.r, c.g, c.b)
+                        });
+                        std::iter::repeat_n(color, token.chars().count())
+                    })
+                    .collect();
+                colors.pop();
+                colors
+            })
+            .collect();
+        Highlighted {
+            dialect,
+            text: text.to_string(),
+            colors,
+        }
+    }
+
+    /// Whether this is the highlighting of `text` in `dialect`.
+    pub fn is_for(&self, text: &str, dialect: Dialect) -> bool {
+        self.dialect == dialect && self.text == text
+    }
+
+    pub fn color(&self, row: usize, col: usize) -> Option<Color> {
+        self.colors.get(row)?.get(col).copied().flatten()
+    }
+}
+
+// tests
+
+#[allow(dead_code)]
+fn nord(color: NordColor) -> Option<Color> {
+    let [r, g, b, _] = color.value().to_be_bytes();
+    Some(Color::Rgb(r, g, b))
+}
+
+#[test]
+fn test_bundled_grammars_load() {
+    assert_eq!(syntax(Dialect::MySql).1.name, "MySQL");
+    assert_eq!(syntax(Dialect::Postgres).1.name, "PostgreSQL");
+    assert_eq!(syntax(Dialect::Sqlite).1.name, "SQL");
+    assert_eq!(theme().scopes.len(), TOKEN_COLORS.len());
+}
+
+#[test]
+fn test_postgres_highlighting() {
+    let text = "SELECT id::text, $1 FROM \"Users\"\nWHERE body = $fn$ it's\nstill $fn$; -- done";
+    let highlighted = Highlighted::new(text, Dialect::Postgres);
+    assert_eq!(highlighted.color(0, 0), nord(NordColor::Nord9));
+    assert_eq!(highlighted.color(0, 7), None);
+    // the cast and its type
+    assert_eq!(highlighted.color(0, 9), nord(NordColor::Nord9));
+    assert_eq!(highlighted.color(0, 11), nord(NordColor::Nord7));
+    assert_eq!(highlighted.color(0, 17), nord(NordColor::Nord10));
+    assert_eq!(highlighted.color(0, 26), nord(NordColor::Nord7));
+    // the dollar-quoted string goes on in the next line
+    assert_eq!(highlighted.color(1, 20), nord(NordColor::Nord14));
+    assert_eq!(highlighted.color(2, 0), nord(NordColor::Nord14));
+    assert_eq!(highlighted.color(2, 12), nord(NordColor::Nord3));
+    assert_eq!(highlighted.colors[2].len(), 19);
+}
+
+#[test]
+fn test_mysql_highlighting() {
+    let text = "SELECT `order`, 'it\\'s', COUNT(*) FROM t # comment";
+    let highlighted = Highlighted::new(text, Dialect::MySql);
+    assert_eq!(highlighted.color(0, 8), nord(NordColor::Nord7));
+    assert_eq!(highlighted.color(0, 17), nord(NordColor::Nord14));
+    assert_eq!(highlighted.color(0, 19), nord(NordColor::Nord13));
+    assert_eq!(highlighted.color(0, 25), nord(NordColor::Nord8));
+    assert_eq!(highlighted.color(0, 45), nord(NordColor::Nord3));
+    // PostgreSQL has no # comments
+    let highlighted = Highlighted::new(text, Dialect::Postgres);
+    assert_ne!(highlighted.color(0, 45), nord(NordColor::Nord3));
+    assert!(highlighted.is_for(text, Dialect::Postgres));
+    assert!(!highlighted.is_for(text, Dialect::MySql));
+}
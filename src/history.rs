@@ -0,0 +1,365 @@
+// Copyright (c) 2025 mcpeaps_HD
+//
+// This software is released under the MIT License.
+// https://opensource.org/licenses/MIT
+use crate::shared::{self, AppState, ConnectionStatus, SimpleSqlError, Table};
+#[allow(unused_imports)]
+use log::{debug, error, info, trace, warn};
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::time::Duration;
+
+/// Entries kept in the history file; older ones are dropped when it is loaded.
+pub const MAX_ENTRIES: usize = 10_000;
+
+/// An executed query, stored as one line of JSON in `~/.simplesql/history.jsonl`.
+#[derive(Clone, Debug, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct HistoryEntry {
+    /// When the query was started, RFC 3339 in local time.
+    pub timestamp: String,
+    pub connection: String,
+    pub credential: String,
+    pub database: String,
+    pub sql: String,
+    pub duration_ms: u64,
+    /// Rows returned or affected by all statements of the query.
+    pub rows: u64,
+    /// Why the query failed; missing when it succeeded.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+impl HistoryEntry {
+    /// Entry for `sql` about to run with the credential and database selected in `state`.
+    pub fn start(state: &AppState, sql: &str) -> Self {
+        HistoryEntry {
+            timestamp: chrono::Local::now().to_rfc3339_opts(chrono::SecondsFormat::Secs, false),
+            connection: state.user.connection.clone(),
+            credential: state.user.name.clone(),
+            database: state.db.clone(),
+            sql: sql.trim().to_string(),
+            ..HistoryEntry::default()
+        }
+    }
+
+    /// Completes the entry with the outcome of [`shared::run_query`] and the results it left
+    /// in `state`.
+    pub fn finish(
+        mut self,
+        state: &AppState,
+        outcome: &Result<(), SimpleSqlError>,
+        duration: Duration,
+    ) -> Self {
+        // the results of the previous query are still there when this one never ran
+        let ran = match outcome {
+            Ok(()) => true,
+            Err(SimpleSqlError::Database(_)) => {
+                !matches!(state.connection_status(), ConnectionStatus::Failed(_))
+            }
+            Err(_) => false,
+        };
+        let results = state.results.lock().unwrap();
+        let results: &[Table] = if ran { &results } else { &[] };
+        self.duration_ms = duration.as_millis() as u64;
+        self.rows = results
+            .iter()
+            .map(|table| match table.columns.is_empty() {
+                true => table.rows_affected.unwrap_or(0),
+                false => table.rows.len() as u64,
+            })
+            .sum();
+        self.error = match outcome {
+            Err(e @ SimpleSqlError::Database(_)) if ran => results
+                .iter()
+                .find_map(|table| table.error.clone())
+                .or_else(|| Some(e.to_string())),
+            Err(e) => Some(e.to_string()),
+            Ok(()) => results.iter().find_map(|table| table.error.clone()),
+        };
+        self
+    }
+
+    /// Completes the entry of a query that did not finish, e.g. because it was cancelled.
+    pub fn fail(mut self, error: &str, duration: Duration) -> Self {
+        self.duration_ms = duration.as_millis() as u64;
+        self.error = Some(error.to_string());
+        self
+    }
+
+    pub fn succeeded(&self) -> bool {
+        self.error.is_none()
+    }
+
+    /// Start time as shown in the History tab.
+    pub fn time(&self) -> String {
+        chrono::DateTime::parse_from_rfc3339(&self.timestamp)
+            .map(|time| time.format("%Y-%m-%d %H:%M:%S").to_string())
+            .unwrap_or_else(|_| self.timestamp.clone())
+    }
+
+    /// The SQL on a single line, for lists.
+    pub fn one_line(&self) -> String {
+        self.sql.split_whitespace().collect::<Vec<_>>().join(" ")
+    }
+
+    /// What the search of the History tab looks at.
+    fn search_text(&self) -> String {
+        format!(
+            "{} {} {} {}",
+            self.sql, self.database, self.credential, self.connection
+        )
+    }
+}
+
+/// The executed queries, oldest first, and the selection of the History tab.
+#[derive(Clone, Debug, Default)]
+pub struct History {
+    /// Add entries with [`History::push`], which keeps the matches up to date.
+    pub entries: Vec<HistoryEntry>,
+    /// Index into [`History::matches`].
+    pub selected: usize,
+    pub offset: usize,
+    /// The filter `matched` was computed for, `None` when it is out of date.
+    filter: Option<String>,
+    matched: Vec<usize>,
+}
+impl History {
+    /// Matches the entries against `filter` with [`fuzzy_score`] unless they were matched
+    /// against it already and did not change since.
+    pub fn set_filter(&mut self, filter: &str) {
+        if self.filter.as_deref() != Some(filter) {
+            self.matched = if filter.trim().is_empty() {
+                (0..self.entries.len()).rev().collect()
+            } else {
+                let mut scored: Vec<(u32, usize)> = self
+                    .entries
+                    .iter()
+                    .enumerate()
+                    .filter_map(|(i, entry)| Some((fuzzy_score(filter, &entry.search_text())?, i)))
+                    .collect();
+                scored.sort_by(|a, b| b.0.cmp(&a.0).then(b.1.cmp(&a.1)));
+                scored.into_iter().map(|(_, i)| i).collect()
+            };
+            self.filter = Some(filter.to_string());
+        }
+    }
+
+    /// Indices of the entries matching the last [`History::set_filter`], best first and newest
+    /// first among equally good ones. Without a filter all entries match, newest first.
+    pub fn matches(&self) -> &[usize] {
+        &self.matched
+    }
+
+    /// The entry selected among the [`History::matches`].
+    pub fn selected_entry(&self) -> Option<&HistoryEntry> {
+        let idx = *self.matched.get(self.selected)?;
+        self.entries.get(idx)
+    }
+
+    pub fn push(&mut self, entry: HistoryEntry) {
+        self.entries.push(entry);
+        if self.entries.len() > MAX_ENTRIES {
+            self.entries.remove(0);
+        }
+        self.filter = None;
+    }
+}
+
+/// Scores how well `pattern` matches `text`, or `None` when it does not match.
+///
+/// Every word of the pattern has to appear in the text with its characters in order, ignoring
+/// case. Characters following each other or starting a word in the text score higher.
+pub fn fuzzy_score(pattern: &str, text: &str) -> Option<u32> {
+    let text: Vec<char> = text.to_lowercase().chars().collect();
+    pattern.split_whitespace().try_fold(0, |total, word| {
+        let word: Vec<char> = word.to_lowercase().chars().collect();
+        // the first match of the first character is not always the best one
+        let best = (0..text.len())
+            .filter(|start| text[*start] == word[0])
+            .filter_map(|start| word_score(&word, &text, start))
+            .max()?;
+        Some(total + best)
+    })
+}
+
+/// Score of `word` matched from `start` on, taking each next character as early as possible.
+fn word_score(word: &[char], text: &[char], start: usize) -> Option<u32> {
+    let mut score = 0;
+    let mut pos = start;
+    let mut previous = None;
+    for c in word {
+        let found = pos + text.get(pos..)?.iter().position(|t| t == c)?;
+        score += 1;
+        if previous == Some(found.wrapping_sub(1)) {
+            score += 5;
+        }
+        if found == 0 || !text[found - 1].is_alphanumeric() {
+            score += 3;
+        }
+        previous = Some(found);
+        pos = found + 1;
+    }
+    Some(score)
+}
+
+/// Appends `entry` to the history file.
+pub fn record(entry: &HistoryEntry) -> Result<(), SimpleSqlError> {
+    let line =
+        serde_json::to_string(entry).map_err(|e| SimpleSqlError::Io(std::io::Error::other(e)))?;
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(shared::get_history_path()?)?;
+    writeln!(file, "{}", line)?;
+    Ok(())
+}
+
+/// Reads the history file, oldest entry first.
+///
+/// Lines that cannot be read are skipped. When the file holds more than [`MAX_ENTRIES`] it is
+/// rewritten with the newest ones.
+pub fn load() -> History {
+    let Ok(path) = shared::get_history_path() else {
+        return History::default();
+    };
+    let Ok(content) = shared::read_file(&path) else {
+        return History::default();
+    };
+    let mut entries = parse(&content);
+    if entries.len() > MAX_ENTRIES {
+        entries.drain(..entries.len() - MAX_ENTRIES);
+        if let Err(e) = shared::write_file(&path, &serialize(&entries)) {
+            warn!("Cannot shorten the history in {}: {}", path, e);
+        }
+    }
+    History {
+        entries,
+        ..History::default()
+    }
+}
+
+fn parse(content: &str) -> Vec<HistoryEntry> {
+    content
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .filter_map(|line| match serde_json::from_str(line) {
+            Ok(entry) => Some(entry),
+            Err(e) => {
+                debug!("Skipping history line: {}", e);
+                None
+            }
+        })
+        .collect()
+}
+
+fn serialize(entries: &[HistoryEntry]) -> String {
+    entries
+        .iter()
+        .filter_map(|entry| serde_json::to_string(entry).ok())
+        .map(|line| line + "\n")
+        .collect()
+}
+
+// tests
+
+#[allow(dead_code)]
+fn entry(sql: &str, database: &str) -> HistoryEntry {
+    HistoryEntry {
+        timestamp: "2025-03-01T12:30:05+01:00".to_string(),
+        connection: "Local PostgreSQL".to_string(),
+        credential: "postgresql_default".to_string(),
+        database: database.to_string(),
+        sql: sql.to_string(),
+        ..HistoryEntry::default()
+    }
+}
+
+#[test]
+fn test_fuzzy_score() {
+    assert!(fuzzy_score("", "anything").is_some());
+    assert!(fuzzy_score("slct usr", "SELECT * FROM users").is_some());
+    assert!(fuzzy_score("usr slct", "SELECT * FROM users").is_some());
+    assert!(fuzzy_score("sleect", "SELECT * FROM users").is_none());
+    assert!(fuzzy_score("orders", "SELECT * FROM users").is_none());
+    // whole words beat scattered letters
+    assert!(
+        fuzzy_score("user", "SELECT * FROM users").unwrap()
+            > fuzzy_score("user", "UPDATE settings SET value = 'r'").unwrap_or(0)
+    );
+}
+
+#[test]
+fn test_matches_order() {
+    let mut history = History::default();
+    history.push(entry("SELECT * FROM users", "app"));
+    history.push(entry("SELECT * FROM orders", "app"));
+    history.push(entry("DELETE FROM users WHERE id = 1", "shop"));
+    let sql = |history: &mut History, filter: &str| -> Vec<String> {
+        history.set_filter(filter);
+        history
+            .matches()
+            .iter()
+            .map(|&i| history.entries[i].sql.clone())
+            .collect()
+    };
+    assert_eq!(
+        sql(&mut history, ""),
+        vec![
+            "DELETE FROM users WHERE id = 1",
+            "SELECT * FROM orders",
+            "SELECT * FROM users"
+        ]
+    );
+    assert_eq!(
+        sql(&mut history, "users"),
+        vec!["DELETE FROM users WHERE id = 1", "SELECT * FROM users"]
+    );
+    assert_eq!(
+        sql(&mut history, "shop"),
+        vec!["DELETE FROM users WHERE id = 1"]
+    );
+    // the cached matches follow new entries
+    history.push(entry("SELECT 1", "shop"));
+    assert_eq!(sql(&mut history, "shop")[0], "SELECT 1");
+    history.selected = 1;
+    assert_eq!(
+        history.selected_entry().unwrap().sql,
+        "DELETE FROM users WHERE id = 1"
+    );
+}
+
+#[test]
+fn test_history_file_lines() {
+    let mut failed = entry("SELECT\n  1", "app");
+    failed.error = Some("syntax error".to_string());
+    let entries = vec![entry("SELECT * FROM users", "app"), failed];
+    let content = serialize(&entries);
+    assert_eq!(content.lines().count(), 2);
+    assert!(!content.lines().next().unwrap().contains("error"));
+    let with_garbage = format!("{}not json\n\n", content);
+    assert_eq!(parse(&with_garbage), entries);
+    assert_eq!(entries[1].one_line(), "SELECT 1");
+    assert_eq!(entries[0].time(), "2025-03-01 12:30:05");
+    assert!(entries[0].succeeded() && !entries[1].succeeded());
+}
+
+#[test]
+fn test_finish_counts_rows() {
+    let mut state = shared::sqlite_state(":memory:");
+    state.sql_query =
+        "CREATE TABLE t (id INTEGER); INSERT INTO t VALUES (1), (2); SELECT * FROM t".to_string();
+    let outcome = shared::run_query(&mut state);
+    let finished = HistoryEntry::start(&state, &state.sql_query).finish(
+        &state,
+        &outcome,
+        Duration::from_millis(1500),
+    );
+    assert_eq!(finished.rows, 4);
+    assert_eq!(finished.duration_ms, 1500);
+    assert!(finished.succeeded());
+    state.sql_query = "SELECT * FROM missing".to_string();
+    let outcome = shared::run_query(&mut state);
+    let finished =
+        HistoryEntry::start(&state, &state.sql_query).finish(&state, &outcome, Duration::ZERO);
+    assert!(finished.error.unwrap().contains("missing"));
+    state.disconnect();
+}
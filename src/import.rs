@@ -0,0 +1,1236 @@
+// Copyright (c) 2025 mcpeaps_HD
+//
+// This software is released under the MIT License.
+// https://opensource.org/licenses/MIT
+use crate::formatter;
+use crate::headless::{self, ExitCode, Options};
+use crate::shared::{
+    self, AppState, ConnectionStatus, Dialect, QueryCancelHandle, SchemaTable, ScriptOutcome,
+    Settings, SimpleSqlError, Value,
+};
+use crate::splitter::Statement;
+use chrono::{NaiveDate, NaiveDateTime};
+use log::info;
+use std::io::{IsTerminal, Write};
+use std::ops::Range;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+/// Rows per `INSERT` statement unless `--batch-size` says otherwise.
+pub const DEFAULT_BATCH_SIZE: usize = 500;
+
+/// File types that can be imported, chosen by extension.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ImportFormat {
+    Csv,
+    Tsv,
+    /// A JSON array of objects, or one object per line (NDJSON).
+    Json,
+}
+impl ImportFormat {
+    pub fn from_path(path: &str) -> Option<Self> {
+        let extension = Path::new(path).extension()?.to_str()?.to_ascii_lowercase();
+        match extension.as_str() {
+            "csv" => Some(ImportFormat::Csv),
+            "tsv" | "tab" => Some(ImportFormat::Tsv),
+            "json" | "ndjson" | "jsonl" => Some(ImportFormat::Json),
+            _ => None,
+        }
+    }
+}
+
+/// Column type inferred from the values of an imported column.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ColumnKind {
+    Boolean,
+    Integer,
+    Float,
+    Date,
+    Timestamp,
+    Json,
+    Text,
+}
+impl ColumnKind {
+    pub const ALL: [ColumnKind; 7] = [
+        ColumnKind::Boolean,
+        ColumnKind::Integer,
+        ColumnKind::Float,
+        ColumnKind::Date,
+        ColumnKind::Timestamp,
+        ColumnKind::Json,
+        ColumnKind::Text,
+    ];
+
+    /// The kind after this one in [`ColumnKind::ALL`], wrapping around.
+    pub fn next(self) -> Self {
+        let idx = Self::ALL.iter().position(|kind| *kind == self).unwrap_or(0);
+        Self::ALL[(idx + 1) % Self::ALL.len()]
+    }
+
+    /// Kind of a single value, `None` for NULL and empty text which fit every kind.
+    fn of_value(value: &Value) -> Option<Self> {
+        match value {
+            Value::Null => None,
+            Value::Bool(_) => Some(ColumnKind::Boolean),
+            Value::Int(_) => Some(ColumnKind::Integer),
+            Value::Float(_) => Some(ColumnKind::Float),
+            Value::Json(_) => Some(ColumnKind::Json),
+            Value::Text(text) if text.is_empty() => None,
+            Value::Text(text) if parse_int(text).is_some() => Some(ColumnKind::Integer),
+            Value::Text(text) if parse_float(text).is_some() => Some(ColumnKind::Float),
+            Value::Text(text) if parse_bool(text).is_some() => Some(ColumnKind::Boolean),
+            Value::Text(text) if parse_date(text).is_some() => Some(ColumnKind::Date),
+            Value::Text(text) if parse_timestamp(text).is_some() => Some(ColumnKind::Timestamp),
+            _ => Some(ColumnKind::Text),
+        }
+    }
+
+    /// The narrowest kind that holds values of both kinds.
+    fn merge(self, other: Self) -> Self {
+        match (self, other) {
+            (a, b) if a == b => a,
+            (ColumnKind::Integer, ColumnKind::Float) | (ColumnKind::Float, ColumnKind::Integer) => {
+                ColumnKind::Float
+            }
+            (ColumnKind::Date, ColumnKind::Timestamp)
+            | (ColumnKind::Timestamp, ColumnKind::Date) => ColumnKind::Timestamp,
+            _ => ColumnKind::Text,
+        }
+    }
+
+    /// Column type for `CREATE TABLE` in `dialect`.
+    pub fn sql_type(self, dialect: Dialect) -> &'static str {
+        match (self, dialect) {
+            (ColumnKind::Boolean, _) => "BOOLEAN",
+            (ColumnKind::Integer, Dialect::Sqlite) => "INTEGER",
+            (ColumnKind::Integer, _) => "BIGINT",
+            (ColumnKind::Float, Dialect::Sqlite) => "REAL",
+            (ColumnKind::Float, Dialect::MySql) => "DOUBLE",
+            (ColumnKind::Float, _) => "DOUBLE PRECISION",
+            (ColumnKind::Date, _) => "DATE",
+            (ColumnKind::Timestamp, Dialect::MySql) => "DATETIME",
+            (ColumnKind::Timestamp, _) => "TIMESTAMP",
+            (ColumnKind::Json, Dialect::Postgres) => "JSONB",
+            (ColumnKind::Json, Dialect::MySql) => "JSON",
+            (ColumnKind::Json | ColumnKind::Text, _) => "TEXT",
+        }
+    }
+
+    /// Converts a value read from the file to this kind.
+    ///
+    /// Empty text becomes NULL in every column that is not text. Text that does not fit a
+    /// kind picked by hand is kept, see [`ImportData::set_kind`].
+    fn convert(self, value: Value) -> Value {
+        match (self, value) {
+            (_, Value::Null) => Value::Null,
+            (ColumnKind::Text, Value::Text(text)) => Value::Text(text),
+            (ColumnKind::Text, other) => Value::Text(other.to_plain_string()),
+            (_, Value::Text(text)) if text.is_empty() => Value::Null,
+            (ColumnKind::Integer, Value::Text(text)) => {
+                parse_int(&text).map_or(Value::Text(text), Value::Int)
+            }
+            (ColumnKind::Float, Value::Text(text)) => {
+                parse_float(&text).map_or(Value::Text(text), Value::Float)
+            }
+            (ColumnKind::Float, Value::Int(i)) => Value::Float(i as f64),
+            (ColumnKind::Boolean, Value::Text(text)) => {
+                parse_bool(&text).map_or(Value::Text(text), Value::Bool)
+            }
+            (ColumnKind::Date, Value::Text(text)) => {
+                parse_date(&text).map_or(Value::Text(text), Value::Date)
+            }
+            (ColumnKind::Timestamp, Value::Text(text)) => parse_timestamp(&text)
+                .or_else(|| parse_date(&text).and_then(|d| d.and_hms_opt(0, 0, 0)))
+                .map_or(Value::Text(text), Value::Timestamp),
+            (ColumnKind::Json, Value::Text(text)) => {
+                serde_json::from_str(&text).map_or(Value::Text(text), Value::Json)
+            }
+            (_, other) => other,
+        }
+    }
+}
+
+/// Integers without leading zeros, so codes like `007` stay text.
+fn parse_int(text: &str) -> Option<i64> {
+    let digits = text.strip_prefix(['-', '+']).unwrap_or(text);
+    if digits.len() > 1 && digits.starts_with('0') {
+        return None;
+    }
+    text.parse().ok()
+}
+
+/// Plain decimal numbers; `NaN`, `inf` and leading zeros are left as text.
+fn parse_float(text: &str) -> Option<f64> {
+    let digits = text.strip_prefix(['-', '+']).unwrap_or(text);
+    let leading_zero =
+        digits.starts_with('0') && digits[1..].starts_with(|c: char| c.is_ascii_digit());
+    let plain = digits.starts_with(|c: char| c.is_ascii_digit() || c == '.')
+        && digits
+            .chars()
+            .all(|c| c.is_ascii_digit() || matches!(c, '.' | 'e' | 'E' | '-' | '+'));
+    if leading_zero || !plain {
+        return None;
+    }
+    text.parse().ok()
+}
+
+fn parse_bool(text: &str) -> Option<bool> {
+    if text.eq_ignore_ascii_case("true") {
+        Some(true)
+    } else if text.eq_ignore_ascii_case("false") {
+        Some(false)
+    } else {
+        None
+    }
+}
+
+fn parse_date(text: &str) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(text, "%Y-%m-%d").ok()
+}
+
+fn parse_timestamp(text: &str) -> Option<NaiveDateTime> {
+    NaiveDateTime::parse_from_str(text, "%Y-%m-%d %H:%M:%S%.f")
+        .or_else(|_| NaiveDateTime::parse_from_str(text, "%Y-%m-%dT%H:%M:%S%.f"))
+        .ok()
+}
+
+/// Columns and rows read from a file, with the values converted to the inferred kinds.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ImportData {
+    pub columns: Vec<String>,
+    pub kinds: Vec<ColumnKind>,
+    pub rows: Vec<Vec<Value>>,
+}
+impl ImportData {
+    fn new(columns: Vec<String>, rows: Vec<Vec<Value>>) -> Self {
+        let kinds: Vec<ColumnKind> = (0..columns.len())
+            .map(|idx| {
+                rows.iter()
+                    .filter_map(|row| ColumnKind::of_value(&row[idx]))
+                    .reduce(ColumnKind::merge)
+                    .unwrap_or(ColumnKind::Text)
+            })
+            .collect();
+        let rows = rows
+            .into_iter()
+            .map(|row| {
+                row.into_iter()
+                    .zip(&kinds)
+                    .map(|(value, kind)| kind.convert(value))
+                    .collect()
+            })
+            .collect();
+        ImportData {
+            columns,
+            kinds,
+            rows,
+        }
+    }
+
+    /// Converts column `idx` to `kind` instead of the inferred one. Values that do not fit
+    /// are kept as text for the database to convert or reject; their number is returned.
+    pub fn set_kind(&mut self, idx: usize, kind: ColumnKind) -> usize {
+        self.kinds[idx] = kind;
+        let mut misfits = 0;
+        for row in &mut self.rows {
+            let value = match std::mem::replace(&mut row[idx], Value::Null) {
+                Value::Null => Value::Null,
+                Value::Text(text) => Value::Text(text),
+                other => Value::Text(other.to_plain_string()),
+            };
+            row[idx] = kind.convert(value);
+            if kind != ColumnKind::Text && matches!(row[idx], Value::Text(_)) {
+                misfits += 1;
+            }
+        }
+        misfits
+    }
+}
+
+/// Reads and parses the file at `path`, with the format taken from its extension.
+pub fn read_file(path: &str) -> Result<ImportData, SimpleSqlError> {
+    let format = ImportFormat::from_path(path).ok_or_else(|| {
+        SimpleSqlError::Import(format!(
+            "Cannot tell the format of {}, expected a .csv, .tsv or .json file",
+            path
+        ))
+    })?;
+    let text = shared::read_file(path)
+        .map_err(|e| SimpleSqlError::Import(format!("Cannot read {}: {}", path, e)))?;
+    parse(&text, format).map_err(SimpleSqlError::Import)
+}
+
+/// Parses `text`; CSV and TSV files start with a header line naming the columns.
+pub fn parse(text: &str, format: ImportFormat) -> Result<ImportData, String> {
+    let text = text.strip_prefix('\u{feff}').unwrap_or(text);
+    let (columns, rows) = match format {
+        ImportFormat::Csv => with_header(parse_csv(text, ',')?)?,
+        ImportFormat::Tsv => with_header(parse_tsv(text))?,
+        ImportFormat::Json => parse_json(text)?,
+    };
+    Ok(ImportData::new(columns, rows))
+}
+
+/// Splits off the header record and checks that every row has as many fields. Blank lines are
+/// skipped, except in a file with a single column, where they are rows with an empty field.
+fn with_header(mut records: Vec<Vec<Value>>) -> Result<(Vec<String>, Vec<Vec<Value>>), String> {
+    let is_blank = |record: &Vec<Value>| match record.as_slice() {
+        [Value::Null] => true,
+        [Value::Text(text)] => text.is_empty(),
+        _ => false,
+    };
+    let leading = records.iter().take_while(|record| is_blank(record)).count();
+    records.drain(..leading);
+    if records.is_empty() {
+        return Err("The file is empty".to_string());
+    }
+    let columns: Vec<String> = records
+        .remove(0)
+        .into_iter()
+        .enumerate()
+        .map(|(idx, name)| match name {
+            Value::Text(name) if !name.trim().is_empty() => name.trim().to_string(),
+            _ => format!("column_{}", idx + 1),
+        })
+        .collect();
+    if columns.len() > 1 {
+        records.retain(|record| !is_blank(record));
+    }
+    if let Some((idx, record)) = records
+        .iter()
+        .enumerate()
+        .find(|(_, record)| record.len() != columns.len())
+    {
+        return Err(format!(
+            "Row {} has {} field(s), the header has {}",
+            idx + 1,
+            record.len(),
+            columns.len()
+        ));
+    }
+    Ok((columns, records))
+}
+
+/// RFC 4180 records. Empty unquoted fields are NULL, `""` is an empty string.
+fn parse_csv(text: &str, delimiter: char) -> Result<Vec<Vec<Value>>, String> {
+    let mut records = Vec::new();
+    let mut record = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut in_quotes = false;
+    let end_field = |field: &mut String, quoted: &mut bool, record: &mut Vec<Value>| {
+        let value = if field.is_empty() && !*quoted {
+            Value::Null
+        } else {
+            Value::Text(std::mem::take(field))
+        };
+        record.push(value);
+        *quoted = false;
+    };
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        if in_quotes {
+            match c {
+                '"' if chars.peek() == Some(&'"') => {
+                    field.push('"');
+                    chars.next();
+                }
+                '"' => in_quotes = false,
+                c => field.push(c),
+            }
+            continue;
+        }
+        match c {
+            '"' if field.is_empty() && !quoted => {
+                in_quotes = true;
+                quoted = true;
+            }
+            c if c == delimiter => end_field(&mut field, &mut quoted, &mut record),
+            '\r' if chars.peek() == Some(&'\n') => {}
+            '\n' | '\r' => {
+                end_field(&mut field, &mut quoted, &mut record);
+                records.push(std::mem::take(&mut record));
+            }
+            c => field.push(c),
+        }
+    }
+    if in_quotes {
+        return Err(format!(
+            "Unterminated quoted field in row {}",
+            records.len().max(1)
+        ));
+    }
+    if !field.is_empty() || quoted || !record.is_empty() {
+        end_field(&mut field, &mut quoted, &mut record);
+        records.push(record);
+    }
+    Ok(records)
+}
+
+/// Tab separated lines as written by `--format tsv`: `\N` is NULL and `\t`, `\n`, `\r` and
+/// `\\` are escapes.
+fn parse_tsv(text: &str) -> Vec<Vec<Value>> {
+    text.lines()
+        .map(|line| {
+            line.split('\t')
+                .map(|field| match field {
+                    "\\N" => Value::Null,
+                    field => Value::Text(unescape_tsv(field)),
+                })
+                .collect()
+        })
+        .collect()
+}
+
+fn unescape_tsv(field: &str) -> String {
+    let mut text = String::with_capacity(field.len());
+    let mut chars = field.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            text.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('t') => text.push('\t'),
+            Some('n') => text.push('\n'),
+            Some('r') => text.push('\r'),
+            Some(other) => text.push(other),
+            None => text.push('\\'),
+        }
+    }
+    text
+}
+
+/// An array of objects or one object per line. The columns are the keys in order of their
+/// first appearance; missing keys are NULL.
+fn parse_json(text: &str) -> Result<(Vec<String>, Vec<Vec<Value>>), String> {
+    let objects: Vec<serde_json::Value> = if text.trim_start().starts_with('[') {
+        match serde_json::from_str(text).map_err(|e| format!("Invalid JSON: {}", e))? {
+            serde_json::Value::Array(items) => items,
+            _ => unreachable!("text starts with ["),
+        }
+    } else {
+        text.lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty())
+            .map(|(idx, line)| {
+                serde_json::from_str(line)
+                    .map_err(|e| format!("Invalid JSON on line {}: {}", idx + 1, e))
+            })
+            .collect::<Result<_, _>>()?
+    };
+    let mut columns: Vec<String> = Vec::new();
+    let mut rows = Vec::with_capacity(objects.len());
+    for (idx, object) in objects.into_iter().enumerate() {
+        let serde_json::Value::Object(object) = object else {
+            return Err(format!("Row {} is not a JSON object", idx + 1));
+        };
+        let mut row = vec![Value::Null; columns.len()];
+        for (key, value) in object {
+            let idx = match columns.iter().position(|c| *c == key) {
+                Some(idx) => idx,
+                None => {
+                    columns.push(key);
+                    row.push(Value::Null);
+                    columns.len() - 1
+                }
+            };
+            row[idx] = json_value(value);
+        }
+        rows.push(row);
+    }
+    if columns.is_empty() {
+        return Err("The file contains no objects with keys".to_string());
+    }
+    for row in &mut rows {
+        row.resize(columns.len(), Value::Null);
+    }
+    Ok((columns, rows))
+}
+
+fn json_value(value: serde_json::Value) -> Value {
+    match value {
+        serde_json::Value::Null => Value::Null,
+        serde_json::Value::Bool(b) => Value::Bool(b),
+        serde_json::Value::Number(n) => match (n.as_i64(), n.as_f64()) {
+            (Some(i), _) => Value::Int(i),
+            (None, Some(x)) if n.is_f64() => Value::Float(x),
+            _ => Value::Text(n.to_string()),
+        },
+        serde_json::Value::String(s) => Value::Text(s),
+        other => Value::Json(other),
+    }
+}
+
+/// Where the rows of an import go.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Target {
+    /// The table does not exist yet and is created from the column kinds.
+    Create,
+    /// The table exists.
+    Existing(SchemaTable),
+}
+
+/// How a file is imported into a table.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ImportPlan {
+    /// Quoted, possibly qualified name of the target table.
+    pub table: String,
+    pub target: Target,
+    /// The table column for each file column, `None` for file columns that are skipped.
+    pub mapping: Vec<Option<String>>,
+    pub batch_size: usize,
+}
+impl ImportPlan {
+    /// Maps the columns of `data` to `table`, matching names without regard to case, or plans
+    /// to create the table when it is not in `schema`.
+    pub fn new(
+        data: &ImportData,
+        table: &str,
+        schema: &[SchemaTable],
+        dialect: Dialect,
+        batch_size: usize,
+    ) -> Result<Self, String> {
+        let table = table.trim();
+        if table.is_empty() {
+            return Err("Enter the name of the target table".to_string());
+        }
+        let (name, target, mapping) = match shared::find_table(schema, table) {
+            Some(existing) if existing.is_view => {
+                return Err(format!("{} is a view, not a table", table));
+            }
+            Some(existing) => {
+                let mapping: Vec<Option<String>> = data
+                    .columns
+                    .iter()
+                    .map(|column| {
+                        existing
+                            .columns
+                            .iter()
+                            .find(|c| c.name.eq_ignore_ascii_case(column))
+                            .map(|c| c.name.clone())
+                    })
+                    .collect();
+                if mapping.iter().all(Option::is_none) {
+                    return Err(format!(
+                        "None of the columns of the file exist in {}",
+                        existing.name
+                    ));
+                }
+                let name = if existing.schema.is_empty() {
+                    formatter::quote_identifier(&existing.name, dialect)
+                } else {
+                    format!(
+                        "{}.{}",
+                        formatter::quote_identifier(&existing.schema, dialect),
+                        formatter::quote_identifier(&existing.name, dialect)
+                    )
+                };
+                (name, Target::Existing(existing.clone()), mapping)
+            }
+            None => {
+                let name = table
+                    .split('.')
+                    .map(|part| formatter::quote_identifier(part, dialect))
+                    .collect::<Vec<_>>()
+                    .join(".");
+                (
+                    name,
+                    Target::Create,
+                    data.columns.iter().cloned().map(Some).collect(),
+                )
+            }
+        };
+        Ok(ImportPlan {
+            table: name,
+            target,
+            mapping,
+            batch_size: batch_size.max(1),
+        })
+    }
+
+    /// Columns file column `idx` can go to: nowhere, or a column of the table.
+    fn choices(&self, data: &ImportData, idx: usize) -> Vec<Option<String>> {
+        let columns = match &self.target {
+            Target::Create => vec![data.columns[idx].clone()],
+            Target::Existing(table) => table.columns.iter().map(|c| c.name.clone()).collect(),
+        };
+        std::iter::once(None)
+            .chain(columns.into_iter().map(Some))
+            .collect()
+    }
+
+    /// Moves file column `idx` to the next (`step` 1) or previous (`step` -1) table column,
+    /// or skips it.
+    pub fn cycle_mapping(&mut self, data: &ImportData, idx: usize, step: isize) {
+        let choices = self.choices(data, idx);
+        let current = choices
+            .iter()
+            .position(|choice| *choice == self.mapping[idx])
+            .unwrap_or(0);
+        let next = (current as isize + step).rem_euclid(choices.len() as isize);
+        self.mapping[idx] = choices[next as usize].clone();
+    }
+
+    /// File columns that are not imported.
+    pub fn skipped<'a>(&self, data: &'a ImportData) -> Vec<&'a str> {
+        data.columns
+            .iter()
+            .zip(&self.mapping)
+            .filter(|(_, target)| target.is_none())
+            .map(|(column, _)| column.as_str())
+            .collect()
+    }
+
+    /// Table columns no file column goes to; they are left to their defaults.
+    pub fn unfilled(&self) -> Vec<&str> {
+        match &self.target {
+            Target::Create => Vec::new(),
+            Target::Existing(table) => table
+                .columns
+                .iter()
+                .filter(|c| !self.mapping.contains(&Some(c.name.clone())))
+                .map(|c| c.name.as_str())
+                .collect(),
+        }
+    }
+
+    /// The `CREATE TABLE` statement, when the table does not exist yet.
+    pub fn create_table_sql(&self, data: &ImportData, dialect: Dialect) -> Option<String> {
+        if self.target != Target::Create {
+            return None;
+        }
+        let columns: Vec<String> = self
+            .mapping
+            .iter()
+            .zip(&data.kinds)
+            .filter_map(|(name, kind)| {
+                Some(format!(
+                    "  {} {}",
+                    formatter::quote_identifier(name.as_ref()?, dialect),
+                    kind.sql_type(dialect)
+                ))
+            })
+            .collect();
+        Some(format!(
+            "CREATE TABLE {} (\n{}\n)",
+            self.table,
+            columns.join(",\n")
+        ))
+    }
+
+    /// One line per file column, e.g. `name -> full_name` or `age (skipped)`, followed by the
+    /// table columns that are not filled.
+    pub fn describe(&self, data: &ImportData, dialect: Dialect) -> Vec<String> {
+        let mut lines: Vec<String> = match &self.target {
+            Target::Create => self
+                .create_table_sql(data, dialect)
+                .unwrap_or_default()
+                .lines()
+                .map(str::to_string)
+                .collect(),
+            Target::Existing(_) => data
+                .columns
+                .iter()
+                .zip(&self.mapping)
+                .filter_map(|(column, target)| Some(format!("{} -> {}", column, target.as_ref()?)))
+                .collect(),
+        };
+        lines.extend(
+            self.skipped(data)
+                .into_iter()
+                .map(|column| format!("{} (skipped)", column)),
+        );
+        lines.extend(
+            self.unfilled()
+                .into_iter()
+                .map(|column| format!("{} (not in file, left to its default)", column)),
+        );
+        lines
+    }
+
+    /// Why the plan cannot run as it is, e.g. after every column was skipped by hand.
+    pub fn problem(&self) -> Option<String> {
+        self.mapping
+            .iter()
+            .all(Option::is_none)
+            .then(|| "Every column of the file is skipped".to_string())
+    }
+
+    /// `CREATE TABLE` when needed, followed by one multi-row `INSERT` per batch.
+    pub fn statements(&self, data: &ImportData, dialect: Dialect) -> Vec<Statement> {
+        let statement = |sql: String| Statement {
+            end: sql.len(),
+            sql,
+            start: 0,
+        };
+        let (names, indexes): (Vec<String>, Vec<usize>) = self
+            .mapping
+            .iter()
+            .enumerate()
+            .filter_map(|(idx, name)| {
+                name.as_ref()
+                    .map(|name| (formatter::quote_identifier(name, dialect), idx))
+            })
+            .unzip();
+        let mut statements: Vec<Statement> = self
+            .create_table_sql(data, dialect)
+            .into_iter()
+            .map(statement)
+            .collect();
+        for batch in data.rows.chunks(self.batch_size) {
+            let values: Vec<String> = batch
+                .iter()
+                .map(|row| {
+                    let values: Vec<String> = indexes
+                        .iter()
+                        .map(|idx| formatter::sql_literal(&row[*idx], dialect))
+                        .collect();
+                    format!("({})", values.join(", "))
+                })
+                .collect();
+            statements.push(statement(format!(
+                "INSERT INTO {} ({}) VALUES\n{}",
+                self.table,
+                names.join(", "),
+                values.join(",\n")
+            )));
+        }
+        statements
+    }
+
+    /// Number of statements before the first `INSERT`.
+    fn offset(&self) -> usize {
+        usize::from(self.target == Target::Create)
+    }
+
+    /// Rows of the file (0-based) inserted by statement `idx` of [`ImportPlan::statements`].
+    fn batch_rows(&self, idx: usize, total: usize) -> Range<usize> {
+        let batch = idx.saturating_sub(self.offset());
+        let start = (batch * self.batch_size).min(total);
+        start..(start + self.batch_size).min(total)
+    }
+
+    /// Rows inserted once `completed` statements have finished.
+    pub fn rows_done(&self, completed: usize, total: usize) -> usize {
+        (completed.saturating_sub(self.offset()) * self.batch_size).min(total)
+    }
+
+    /// Whether a failed import leaves rows behind that the transaction cannot roll back.
+    /// MySQL and MariaDB commit implicitly on `CREATE TABLE`, so every batch after it is
+    /// committed on its own.
+    pub fn commits_early(&self, dialect: Dialect) -> bool {
+        self.target == Target::Create && dialect == Dialect::MySql
+    }
+
+    /// Summary of a finished import. Failures name the rows of the failing batch, counted
+    /// from 1 without the header, and what became of the rows before it.
+    pub fn report(
+        &self,
+        data: &ImportData,
+        outcome: &ScriptOutcome,
+        undo: &Undo,
+        elapsed: Duration,
+    ) -> String {
+        let total = data.rows.len();
+        let failed = outcome
+            .results
+            .iter()
+            .enumerate()
+            .find_map(|(idx, t)| t.error.as_ref().map(|e| (idx, e)));
+        let (summary, committed) = match (failed, &outcome.error) {
+            (Some((idx, error)), _) if idx < self.offset() => {
+                return format!("Cannot create {}: {}", self.table, error);
+            }
+            (Some((idx, error)), _) => {
+                let rows = self.batch_rows(idx, total);
+                let summary = format!(
+                    "Import into {} failed in rows {}-{}: {}",
+                    self.table,
+                    rows.start + 1,
+                    rows.end,
+                    error
+                );
+                (summary, rows.start)
+            }
+            // the statements went through, committing them did not
+            (None, Some(error)) => (
+                format!("Import into {} failed: {}", self.table, error),
+                total,
+            ),
+            (None, None) => {
+                return format!(
+                    "Imported {} row(s) into {} in {:.1?}",
+                    total, self.table, elapsed
+                );
+            }
+        };
+        let undone = match undo {
+            Undo::Rollback => "The transaction was rolled back, no rows were imported.".to_string(),
+            Undo::DropTable => format!("{} was dropped again, no rows were imported.", self.table),
+            Undo::Failed(error) if committed == 0 => {
+                format!(
+                    "{} is left empty, dropping it failed: {}",
+                    self.table, error
+                )
+            }
+            Undo::Failed(error) => format!(
+                "Rows 1-{} were committed to {}, dropping it failed: {}",
+                committed, self.table, error
+            ),
+        };
+        format!("{}\n{}", summary, undone)
+    }
+}
+
+/// How the rows of a failed import were taken back.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Undo {
+    Rollback,
+    /// The table created by the import was dropped, see [`ImportPlan::commits_early`].
+    DropTable,
+    /// Dropping the created table failed.
+    Failed(String),
+}
+
+/// Runs the statements of `plan` in one transaction. Where that cannot take back the rows
+/// of a failed import, the table created for them is dropped again.
+pub async fn execute(
+    state: &AppState,
+    plan: &ImportPlan,
+    data: &ImportData,
+    progress: &QueryCancelHandle,
+) -> Result<(ScriptOutcome, Undo), SimpleSqlError> {
+    let dialect = state.dialect();
+    let outcome = shared::execute_statements_async(
+        state,
+        &plan.statements(data, dialect),
+        &import_settings(),
+        progress,
+    )
+    .await?;
+    // a failed CREATE TABLE may have hit a table that is not ours
+    let created = outcome.results.first().is_some_and(|t| t.error.is_none());
+    let undo = if outcome.error.is_some() && created && plan.commits_early(dialect) {
+        drop_created_table(state, plan).await
+    } else {
+        Undo::Rollback
+    };
+    Ok((outcome, undo))
+}
+
+/// Drops the table created by `plan`, after an import that was cancelled or failed.
+pub async fn drop_created_table(state: &AppState, plan: &ImportPlan) -> Undo {
+    let sql = format!("DROP TABLE {}", plan.table);
+    let statement = Statement {
+        end: sql.len(),
+        sql,
+        start: 0,
+    };
+    let outcome = shared::execute_statements_async(
+        state,
+        &[statement],
+        &Settings::default(),
+        &QueryCancelHandle::default(),
+    )
+    .await;
+    match outcome {
+        Ok(ScriptOutcome { error: None, .. }) => {
+            info!("Dropped {} after the failed import", plan.table);
+            Undo::DropTable
+        }
+        Ok(ScriptOutcome { error: Some(e), .. }) => Undo::Failed(e.to_string()),
+        Err(e) => Undo::Failed(e.to_string()),
+    }
+}
+
+/// Settings for running the statements of an import: all or nothing.
+pub fn import_settings() -> Settings {
+    Settings {
+        continue_on_error: false,
+        transaction: true,
+        ..Settings::default()
+    }
+}
+
+/// `--import`: imports `path` into `table` without the TUI, with a progress bar on stderr.
+pub fn main_import(options: &Options, path: &str, table: &str, batch_size: usize) -> ExitCode {
+    let state = match headless::load_state(options) {
+        Ok(state) => state,
+        Err(code) => return code,
+    };
+    let data = match read_file(path) {
+        Ok(data) => data,
+        Err(e) => {
+            eprintln!("{}", e);
+            return ExitCode::Usage;
+        }
+    };
+    let schema = match shared::describe_schema(&state) {
+        Ok(schema) => schema,
+        Err(e @ SimpleSqlError::ConnectionNotFound(_)) => {
+            eprintln!("{}", e);
+            return ExitCode::Config;
+        }
+        Err(e) => {
+            eprintln!("Cannot connect to {}: {}", state.user.connection, e);
+            return ExitCode::Connection;
+        }
+    };
+    let dialect = state.dialect();
+    let plan = match ImportPlan::new(&data, table, &schema, dialect, batch_size) {
+        Ok(plan) => plan,
+        Err(message) => {
+            eprintln!("{}", message);
+            return ExitCode::Usage;
+        }
+    };
+    match plan.target {
+        Target::Create => eprintln!("Creating {}:", plan.table),
+        Target::Existing(_) => eprintln!("Importing into {}:", plan.table),
+    }
+    for line in plan.describe(&data, dialect) {
+        eprintln!("  {}", line);
+    }
+
+    let progress = QueryCancelHandle::default();
+    let done = AtomicBool::new(false);
+    let started = Instant::now();
+    let outcome = std::thread::scope(|scope| {
+        if std::io::stderr().is_terminal() {
+            scope.spawn(|| {
+                while !done.load(Ordering::Relaxed) {
+                    let rows = plan.rows_done(progress.completed(), data.rows.len());
+                    eprint!("\r{}", progress_bar(rows, data.rows.len(), 30));
+                    let _ = std::io::stderr().flush();
+                    std::thread::sleep(Duration::from_millis(100));
+                }
+                eprint!("\r{}\r", " ".repeat(50));
+            });
+        }
+        let outcome = shared::runtime().block_on(execute(&state, &plan, &data, &progress));
+        done.store(true, Ordering::Relaxed);
+        outcome
+    });
+    match outcome {
+        Ok((outcome, undo)) => {
+            eprintln!("{}", plan.report(&data, &outcome, &undo, started.elapsed()));
+            if outcome.error.is_some() {
+                ExitCode::QueryFailed
+            } else {
+                ExitCode::Ok
+            }
+        }
+        Err(e) if matches!(state.connection_status(), ConnectionStatus::Failed(_)) => {
+            eprintln!("Cannot connect to {}: {}", state.user.connection, e);
+            ExitCode::Connection
+        }
+        Err(e) => {
+            eprintln!("{}", e);
+            ExitCode::QueryFailed
+        }
+    }
+}
+
+/// `[######------] 120/500 rows` with a bar of `width` characters.
+pub fn progress_bar(rows: usize, total: usize, width: usize) -> String {
+    let filled = (rows * width).checked_div(total).unwrap_or(width);
+    format!(
+        "[{}{}] {}/{} rows",
+        "#".repeat(filled),
+        "-".repeat(width - filled),
+        rows,
+        total
+    )
+}
+
+// tests
+
+#[allow(dead_code)]
+fn users_table() -> SchemaTable {
+    SchemaTable {
+        schema: "public".to_string(),
+        name: "users".to_string(),
+        columns: ["id", "Name", "created"]
+            .iter()
+            .map(|name| shared::ColumnMeta {
+                name: name.to_string(),
+                ..shared::ColumnMeta::default()
+            })
+            .collect(),
+        ..SchemaTable::default()
+    }
+}
+
+#[test]
+fn test_import_format_from_path() {
+    assert_eq!(ImportFormat::from_path("a/b.CSV"), Some(ImportFormat::Csv));
+    assert_eq!(ImportFormat::from_path("b.tsv"), Some(ImportFormat::Tsv));
+    assert_eq!(ImportFormat::from_path("b.jsonl"), Some(ImportFormat::Json));
+    assert_eq!(ImportFormat::from_path("b.xlsx"), None);
+}
+
+#[test]
+fn test_parse_csv() {
+    let text = "\u{feff}id,name,note\r\n1,\"Doe, Jane\",\"said \"\"hi\"\"\"\r\n\r\n2,,\"\"\n3,\"multi\nline\",x";
+    let data = parse(text, ImportFormat::Csv).unwrap();
+    assert_eq!(data.columns, vec!["id", "name", "note"]);
+    assert_eq!(data.kinds[0], ColumnKind::Integer);
+    assert_eq!(
+        data.rows,
+        vec![
+            vec![
+                Value::Int(1),
+                Value::Text("Doe, Jane".to_string()),
+                Value::Text("said \"hi\"".to_string())
+            ],
+            vec![Value::Int(2), Value::Null, Value::Text(String::new())],
+            vec![
+                Value::Int(3),
+                Value::Text("multi\nline".to_string()),
+                Value::Text("x".to_string())
+            ],
+        ]
+    );
+    assert!(parse("a,b\n1\n", ImportFormat::Csv).is_err());
+    assert!(parse("a\n\"open\n", ImportFormat::Csv).is_err());
+}
+
+#[test]
+fn test_parse_tsv() {
+    let data = parse("a\tb\n\\N\tx\\ty\n", ImportFormat::Tsv).unwrap();
+    assert_eq!(
+        data.rows,
+        vec![vec![Value::Null, Value::Text("x\ty".to_string())]]
+    );
+}
+
+#[test]
+fn test_parse_single_column_keeps_empty_rows() {
+    let data = parse("\nname\nann\n\nbob\n", ImportFormat::Csv).unwrap();
+    assert_eq!(data.columns, vec!["name"]);
+    assert_eq!(
+        data.rows,
+        vec![
+            vec![Value::Text("ann".to_string())],
+            vec![Value::Null],
+            vec![Value::Text("bob".to_string())],
+        ]
+    );
+    let data = parse("name\nann\n\nbob\n", ImportFormat::Tsv).unwrap();
+    assert_eq!(data.rows.len(), 3);
+    assert_eq!(data.rows[1], vec![Value::Text(String::new())]);
+}
+
+#[test]
+fn test_parse_json() {
+    let array = r#"[{"id": 1, "tags": ["a"]}, {"id": 2.5, "name": "b"}]"#;
+    let data = parse(array, ImportFormat::Json).unwrap();
+    assert_eq!(data.columns, vec!["id", "tags", "name"]);
+    assert_eq!(
+        data.kinds,
+        vec![ColumnKind::Float, ColumnKind::Json, ColumnKind::Text]
+    );
+    assert_eq!(data.rows[0][0], Value::Float(1.0));
+    assert_eq!(data.rows[0][2], Value::Null);
+    let lines = "{\"b\": true, \"a\": \"2024-01-02\"}\n\n{\"b\": false}\n";
+    let data = parse(lines, ImportFormat::Json).unwrap();
+    assert_eq!(data.columns, vec!["b", "a"]);
+    assert_eq!(data.kinds, vec![ColumnKind::Boolean, ColumnKind::Date]);
+    assert!(parse("[1, 2]", ImportFormat::Json).is_err());
+}
+
+#[test]
+fn test_infer_kinds() {
+    let text = "int,float,zip,flag,day,at,mixed\n\
+                1,1,007,true,2024-01-02,2024-01-02 10:00:00,1\n\
+                -2,2.5,010,FALSE,,2024-01-03,x\n";
+    let data = parse(text, ImportFormat::Csv).unwrap();
+    assert_eq!(
+        data.kinds,
+        vec![
+            ColumnKind::Integer,
+            ColumnKind::Float,
+            ColumnKind::Text,
+            ColumnKind::Boolean,
+            ColumnKind::Date,
+            ColumnKind::Timestamp,
+            ColumnKind::Text,
+        ]
+    );
+    assert_eq!(data.rows[1][1], Value::Float(2.5));
+    assert_eq!(data.rows[1][4], Value::Null);
+    assert_eq!(
+        data.rows[1][5].to_string(),
+        "2024-01-03 00:00:00".to_string()
+    );
+    assert_eq!(parse_float("nan"), None);
+}
+
+#[test]
+fn test_plan_create_table() {
+    let data = parse("id,name\n1,a\n2,b\n3,'c'\n", ImportFormat::Csv).unwrap();
+    let plan = ImportPlan::new(&data, "new_users", &[users_table()], Dialect::Postgres, 2).unwrap();
+    assert_eq!(plan.target, Target::Create);
+    let statements: Vec<String> = plan
+        .statements(&data, Dialect::Postgres)
+        .into_iter()
+        .map(|s| s.sql)
+        .collect();
+    assert_eq!(
+        statements,
+        vec![
+            "CREATE TABLE \"new_users\" (\n  \"id\" BIGINT,\n  \"name\" TEXT\n)".to_string(),
+            "INSERT INTO \"new_users\" (\"id\", \"name\") VALUES\n(1, 'a'),\n(2, 'b')".to_string(),
+            "INSERT INTO \"new_users\" (\"id\", \"name\") VALUES\n(3, '''c''')".to_string(),
+        ]
+    );
+    assert_eq!(plan.rows_done(2, 3), 2);
+    assert_eq!(plan.batch_rows(2, 3), 2..3);
+}
+
+#[test]
+fn test_plan_existing_table() {
+    let data = parse("NAME,id,extra\na,1,x\n", ImportFormat::Csv).unwrap();
+    let plan = ImportPlan::new(&data, "Users", &[users_table()], Dialect::MySql, 10).unwrap();
+    assert_eq!(plan.table, "`public`.`users`");
+    assert_eq!(
+        plan.describe(&data, Dialect::MySql),
+        vec![
+            "NAME -> Name",
+            "id -> id",
+            "extra (skipped)",
+            "created (not in file, left to its default)",
+        ]
+    );
+    assert_eq!(
+        plan.statements(&data, Dialect::MySql)[0].sql,
+        "INSERT INTO `public`.`users` (`Name`, `id`) VALUES\n('a', 1)"
+    );
+    let unrelated = parse("foo\n1\n", ImportFormat::Csv).unwrap();
+    assert!(ImportPlan::new(&unrelated, "users", &[users_table()], Dialect::MySql, 10).is_err());
+}
+
+#[test]
+fn test_report_tells_what_was_undone() {
+    let data = parse("id\n1\n2\n3\n", ImportFormat::Csv).unwrap();
+    let plan = ImportPlan::new(&data, "t", &[], Dialect::MySql, 2).unwrap();
+    assert!(plan.commits_early(Dialect::MySql));
+    assert!(!plan.commits_early(Dialect::Postgres));
+    let outcome = ScriptOutcome {
+        results: vec![
+            shared::Table::default(),
+            shared::Table::default(),
+            shared::Table {
+                error: Some("duplicate key".to_string()),
+                ..shared::Table::default()
+            },
+        ],
+        error: Some(sqlx::Error::RowNotFound),
+    };
+    let report = |undo| plan.report(&data, &outcome, &undo, Duration::ZERO);
+    assert_eq!(
+        report(Undo::DropTable),
+        "Import into `t` failed in rows 3-3: duplicate key\n`t` was dropped again, no rows were imported."
+    );
+    assert_eq!(
+        report(Undo::Failed("gone".to_string())),
+        "Import into `t` failed in rows 3-3: duplicate key\nRows 1-2 were committed to `t`, dropping it failed: gone"
+    );
+}
+
+#[test]
+fn test_plan_mapping_and_types_edited_by_hand() {
+    let mut data = parse("NAME,id,code\na,1,007\nb,2.5,x\n", ImportFormat::Csv).unwrap();
+    let mut plan = ImportPlan::new(&data, "users", &[users_table()], Dialect::MySql, 10).unwrap();
+    assert_eq!(plan.skipped(&data), vec!["code"]);
+    plan.cycle_mapping(&data, 2, -1);
+    assert_eq!(plan.mapping[2], Some("created".to_string()));
+    plan.cycle_mapping(&data, 0, -1);
+    assert_eq!(plan.mapping[0], Some("id".to_string()));
+    plan.cycle_mapping(&data, 0, -1);
+    assert_eq!(plan.mapping[0], None);
+    assert_eq!(plan.skipped(&data), vec!["NAME"]);
+    assert_eq!(plan.unfilled(), vec!["Name"]);
+
+    assert_eq!(data.kinds[1], ColumnKind::Float);
+    assert_eq!(data.set_kind(1, ColumnKind::Integer), 1);
+    assert_eq!(data.rows[0][1], Value::Int(1));
+    assert_eq!(data.rows[1][1], Value::Text("2.5".to_string()));
+    assert_eq!(data.set_kind(1, ColumnKind::Float), 0);
+    assert_eq!(data.rows[1][1], Value::Float(2.5));
+    assert_eq!(
+        plan.statements(&data, Dialect::MySql)[0].sql,
+        "INSERT INTO `public`.`users` (`id`, `created`) VALUES\n(1, '007'),\n(2.5, 'x')"
+    );
+
+    let mut new_table = ImportPlan::new(&data, "copy", &[], Dialect::MySql, 10).unwrap();
+    for idx in 0..3 {
+        new_table.cycle_mapping(&data, idx, 1);
+    }
+    assert!(new_table.problem().is_some());
+    new_table.cycle_mapping(&data, 2, 1);
+    assert_eq!(
+        new_table.create_table_sql(&data, Dialect::MySql).unwrap(),
+        "CREATE TABLE `copy` (\n  `code` TEXT\n)"
+    );
+}
+
+#[test]
+fn test_progress_bar() {
+    assert_eq!(progress_bar(5, 10, 4), "[##--] 5/10 rows");
+    assert_eq!(progress_bar(0, 0, 2), "[##] 0/0 rows");
+}
+
+#[test]
+fn test_import_rolls_back_failing_batch() {
+    let path = std::env::temp_dir().join(format!("simplesql_import_{}.db", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    let mut state = shared::sqlite_state(path.to_str().unwrap());
+    state.sql_query = "create table t (id integer primary key, name text); \
+                       insert into t values (1, 'x')"
+        .to_string();
+    shared::run_query(&mut state).unwrap();
+    let import = |text: &str, table: &str| {
+        let data = parse(text, ImportFormat::Csv).unwrap();
+        let schema = shared::describe_schema(&state).unwrap();
+        let plan = ImportPlan::new(&data, table, &schema, Dialect::Sqlite, 2).unwrap();
+        let (outcome, undo) = shared::runtime()
+            .block_on(execute(&state, &plan, &data, &QueryCancelHandle::default()))
+            .unwrap();
+        assert_eq!(undo, Undo::Rollback);
+        plan.report(&data, &outcome, &undo, Duration::ZERO)
+    };
+    let report = import("id,name\n2,a\n3,b\n1,dup\n", "t");
+    assert!(report.starts_with("Import into \"main\".\"t\" failed in rows 3-3: "));
+    assert_eq!(
+        import("id,name\n2,a\n3,b\n", "copy"),
+        "Imported 2 row(s) into \"copy\" in 0.0ns"
+    );
+    state.sql_query = "select count(*) from t; select count(*) from copy".to_string();
+    shared::run_query(&mut state).unwrap();
+    let counts: Vec<Value> = state
+        .results
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|t| t.rows[0][0].clone())
+        .collect();
+    assert_eq!(counts, vec![Value::Int(1), Value::Int(2)]);
+
+    let data = parse("id\n1\n", ImportFormat::Csv).unwrap();
+    let plan = ImportPlan::new(&data, "copy", &[], Dialect::Sqlite, 2).unwrap();
+    assert_eq!(
+        shared::runtime().block_on(drop_created_table(&state, &plan)),
+        Undo::DropTable
+    );
+    assert!(
+        shared::describe_schema(&state)
+            .unwrap()
+            .iter()
+            .all(|t| t.name != "copy")
+    );
+    state.disconnect();
+    let _ = std::fs::remove_file(&path);
+}
@@ -0,0 +1,399 @@
+// Copyright (c) 2025 mcpeaps_HD
+//
+// This software is released under the MIT License.
+// https://opensource.org/licenses/MIT
+use crate::headless::{self, ExitCode, Options};
+use crate::history;
+use crate::shared::{self, Config, SimpleSqlError};
+#[allow(unused_imports)]
+use log::{debug, error, info, trace, warn};
+use std::fs;
+use std::path::Path;
+
+/// Header keys of a saved query, written as `-- key: value` lines above the SQL.
+const KEYS: [&str; 5] = ["name", "description", "tags", "user", "database"];
+
+/// A query of the library, stored as a `.sql` file below the library directory.
+///
+/// The details live in a comment header, so the files stay plain SQL that can be run and
+/// reviewed anywhere:
+///
+/// ```sql
+/// -- name: Active users
+/// -- description: Users that logged in during the last 30 days
+/// -- tags: reporting, users
+/// -- user: postgresql_default
+/// -- database: app
+///
+/// SELECT * FROM users WHERE last_login > now() - interval '30 days';
+/// ```
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SavedQuery {
+    /// Path below the library directory without `.sql`, e.g. `reports/active_users`.
+    pub id: String,
+    pub name: String,
+    pub description: String,
+    pub tags: Vec<String>,
+    /// Credential to run the query with unless another one is chosen.
+    pub user: Option<String>,
+    /// Database to run the query in unless another one is chosen.
+    pub database: Option<String>,
+    pub sql: String,
+}
+impl SavedQuery {
+    /// Reads a library file; without a `name` header the file name is the name.
+    pub fn parse(id: &str, content: &str) -> Self {
+        let mut query = SavedQuery {
+            id: id.to_string(),
+            ..SavedQuery::default()
+        };
+        let mut rest = content;
+        while let Some((key, value, next)) = header_line(rest) {
+            match key.as_str() {
+                "name" => query.name = value,
+                "description" => query.description = value,
+                "tags" => {
+                    query.tags = value
+                        .split(',')
+                        .map(|tag| tag.trim().to_string())
+                        .filter(|tag| !tag.is_empty())
+                        .collect()
+                }
+                "user" => query.user = Some(value).filter(|v| !v.is_empty()),
+                _ => query.database = Some(value).filter(|v| !v.is_empty()),
+            }
+            rest = next;
+        }
+        if query.name.is_empty() {
+            query.name = id.rsplit('/').next().unwrap_or(id).to_string();
+        }
+        query.sql = rest.trim_start_matches(['\r', '\n']).to_string();
+        query
+    }
+
+    /// The file content: the header followed by the SQL.
+    pub fn to_file_content(&self) -> String {
+        let mut content = format!("-- name: {}\n", self.name);
+        if !self.description.is_empty() {
+            content.push_str(&format!("-- description: {}\n", self.description));
+        }
+        if !self.tags.is_empty() {
+            content.push_str(&format!("-- tags: {}\n", self.tags.join(", ")));
+        }
+        if let Some(user) = &self.user {
+            content.push_str(&format!("-- user: {}\n", user));
+        }
+        if let Some(database) = &self.database {
+            content.push_str(&format!("-- database: {}\n", database));
+        }
+        content.push('\n');
+        content.push_str(self.sql.trim_end());
+        content.push('\n');
+        content
+    }
+
+    /// Folder of the query below the library directory, empty at the top.
+    pub fn folder(&self) -> &str {
+        self.id.rsplit_once('/').map_or("", |(folder, _)| folder)
+    }
+
+    /// Where the query is run unless told otherwise, e.g. `postgresql_default@app`.
+    pub fn target(&self) -> String {
+        match (&self.user, &self.database) {
+            (Some(user), Some(database)) => format!("{}@{}", user, database),
+            (Some(user), None) => user.clone(),
+            (None, Some(database)) => format!("@{}", database),
+            (None, None) => String::new(),
+        }
+    }
+
+    /// What the search of the library looks at.
+    fn search_text(&self) -> String {
+        format!(
+            "{} {} {} {} {}",
+            self.id,
+            self.name,
+            self.tags.join(" "),
+            self.description,
+            self.sql
+        )
+    }
+}
+
+/// Splits a `-- key: value` header line off `content`.
+fn header_line(content: &str) -> Option<(String, String, &str)> {
+    let (line, next) = content.split_once('\n').unwrap_or((content, ""));
+    let (key, value) = line.strip_prefix("--")?.split_once(':')?;
+    let key = key.trim().to_lowercase();
+    if !KEYS.contains(&key.as_str()) {
+        return None;
+    }
+    Some((key, value.trim().to_string(), next))
+}
+
+/// Identifier for a query saved as `name`: folders are kept, the file name is made safe.
+///
+/// `Reports/Active users` becomes `Reports/active_users`.
+pub fn id_for(name: &str) -> String {
+    let parts: Vec<&str> = name
+        .split('/')
+        .map(str::trim)
+        .filter(|part| !part.is_empty() && *part != "." && *part != "..")
+        .collect();
+    let Some((file, folders)) = parts.split_last() else {
+        return String::new();
+    };
+    let mut file_name = String::new();
+    for c in file.to_lowercase().chars() {
+        if c.is_alphanumeric() || c == '-' {
+            file_name.push(c);
+        } else if !file_name.ends_with('_') {
+            file_name.push('_');
+        }
+    }
+    let file_name = file_name.trim_matches('_');
+    folders
+        .iter()
+        .copied()
+        .chain(std::iter::once(file_name))
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// The library directory: `settings.library_dir` of the config, else `~/.simplesql/queries`.
+pub fn library_dir(config: &Config) -> Result<String, SimpleSqlError> {
+    match &config.settings.library_dir {
+        Some(dir) => match dir.strip_prefix("~/") {
+            Some(rest) => {
+                let home = std::env::var("HOME").map_err(|_| SimpleSqlError::NoConfigDir)?;
+                Ok(format!("{}/{}", home, rest))
+            }
+            None => Ok(dir.clone()),
+        },
+        None => Ok(format!("{}/queries", shared::get_config_base_path()?)),
+    }
+}
+
+/// Reads all `.sql` files below `dir`, sorted by folder and file name. A missing directory is an
+/// empty library.
+pub fn load(dir: &str) -> Result<Vec<SavedQuery>, SimpleSqlError> {
+    let mut queries = Vec::new();
+    if Path::new(dir).is_dir() {
+        collect(Path::new(dir), "", &mut queries)?;
+    }
+    queries.sort_by(|a, b| a.id.cmp(&b.id));
+    Ok(queries)
+}
+
+fn collect(dir: &Path, prefix: &str, queries: &mut Vec<SavedQuery>) -> Result<(), SimpleSqlError> {
+    for dir_entry in fs::read_dir(dir)? {
+        let path = dir_entry?.path();
+        let Some(file_name) = path.file_name().and_then(|name| name.to_str()) else {
+            continue;
+        };
+        if file_name.starts_with('.') {
+            continue;
+        }
+        if path.is_dir() {
+            collect(&path, &format!("{}{}/", prefix, file_name), queries)?;
+        } else if let Some(stem) = file_name.strip_suffix(".sql") {
+            let content = fs::read_to_string(&path)?;
+            queries.push(SavedQuery::parse(&format!("{}{}", prefix, stem), &content));
+        }
+    }
+    Ok(())
+}
+
+/// The file of the query `id` in the library at `dir`.
+pub fn path(dir: &str, id: &str) -> String {
+    format!("{}/{}.sql", dir, id)
+}
+
+/// Writes `query` to its file below `dir`, creating its folder, and returns the path.
+pub fn save(dir: &str, query: &SavedQuery) -> Result<String, SimpleSqlError> {
+    let path = path(dir, &query.id);
+    if let Some(parent) = Path::new(&path).parent() {
+        fs::create_dir_all(parent)?;
+    }
+    shared::write_file(&path, &query.to_file_content())?;
+    Ok(path)
+}
+
+/// The query called `name`: by id first, then by name ignoring case.
+pub fn find<'a>(queries: &'a [SavedQuery], name: &str) -> Option<&'a SavedQuery> {
+    let name = name.trim().trim_end_matches(".sql");
+    queries.iter().find(|query| query.id == name).or_else(|| {
+        queries
+            .iter()
+            .find(|query| query.name.eq_ignore_ascii_case(name))
+    })
+}
+
+/// Queries matching `filter` with [`history::fuzzy_score`], best first. Without a filter all
+/// queries match in library order.
+pub fn matches<'a>(queries: &'a [SavedQuery], filter: &str) -> Vec<&'a SavedQuery> {
+    let mut scored: Vec<(u32, &SavedQuery)> = queries
+        .iter()
+        .filter_map(|query| Some((history::fuzzy_score(filter, &query.search_text())?, query)))
+        .collect();
+    scored.sort_by_key(|(score, _)| std::cmp::Reverse(*score));
+    scored.into_iter().map(|(_, query)| query).collect()
+}
+
+/// Fills in the credential and database saved with `query` where the command line left them
+/// open. The saved database only goes with the saved credential.
+pub fn apply_defaults(query: &SavedQuery, options: &mut Options) {
+    if options.user.is_none() && options.connection.is_none() {
+        options.user = query.user.clone();
+    } else if query.user.is_some() && options.user != query.user {
+        return;
+    }
+    if options.db.is_none() {
+        options.db = query.database.clone();
+    }
+}
+
+/// Runs the saved query `name` like `--execute` and returns the exit code for the process.
+pub fn main_saved_query(options: &Options, name: &str) -> ExitCode {
+    let queries = match shared::get_config().and_then(|config| load(&library_dir(&config)?)) {
+        Ok(queries) => queries,
+        Err(e) => {
+            eprintln!("Cannot read the query library: {}", e);
+            return ExitCode::Config;
+        }
+    };
+    let Some(query) = find(&queries, name) else {
+        eprintln!(
+            "No saved query \"{}\" in the library, see --list-queries",
+            name
+        );
+        return ExitCode::Usage;
+    };
+    let mut options = Options {
+        sql: query.sql.clone(),
+        ..options.clone()
+    };
+    apply_defaults(query, &mut options);
+    headless::run(&options)
+}
+
+/// Prints the queries of the library, one per line.
+pub fn main_list_queries() -> ExitCode {
+    let (dir, queries) = match shared::get_config().and_then(|config| {
+        let dir = library_dir(&config)?;
+        let queries = load(&dir)?;
+        Ok((dir, queries))
+    }) {
+        Ok(loaded) => loaded,
+        Err(e) => {
+            eprintln!("Cannot read the query library: {}", e);
+            return ExitCode::Config;
+        }
+    };
+    if queries.is_empty() {
+        eprintln!("No saved queries in {}", dir);
+    }
+    for query in &queries {
+        let mut line = query.id.clone();
+        if query.name != query.id.rsplit('/').next().unwrap_or_default() {
+            line.push_str(&format!(" - {}", query.name));
+        }
+        if !query.tags.is_empty() {
+            line.push_str(&format!(" [{}]", query.tags.join(", ")));
+        }
+        if !query.target().is_empty() {
+            line.push_str(&format!(" ({})", query.target()));
+        }
+        if !query.description.is_empty() {
+            line.push_str(&format!(": {}", query.description));
+        }
+        println!("{}", line);
+    }
+    ExitCode::Ok
+}
+
+// tests
+
+#[test]
+fn test_saved_query_file() {
+    let content = "-- name: Active users\n-- Tags: reporting, users,\n-- user: pg\n\n-- the last month\nSELECT 1;\n";
+    let query = SavedQuery::parse("reports/active_users", content);
+    assert_eq!(query.name, "Active users");
+    assert_eq!(query.tags, vec!["reporting", "users"]);
+    assert_eq!(query.user.as_deref(), Some("pg"));
+    assert_eq!(query.database, None);
+    assert_eq!(query.sql, "-- the last month\nSELECT 1;\n");
+    assert_eq!(query.folder(), "reports");
+    assert_eq!(query.target(), "pg");
+    let saved = query.to_file_content();
+    assert_eq!(SavedQuery::parse(&query.id, &saved), query);
+    // plain SQL files work too
+    let plain = SavedQuery::parse("top", "-- note: not a header\nSELECT 2");
+    assert_eq!(plain.name, "top");
+    assert_eq!(plain.sql, "-- note: not a header\nSELECT 2");
+    assert_eq!(plain.folder(), "");
+}
+
+#[test]
+fn test_id_for() {
+    assert_eq!(id_for("Active users"), "active_users");
+    assert_eq!(
+        id_for(" Reports / Sales: by Month! "),
+        "Reports/sales_by_month"
+    );
+    assert_eq!(id_for("../../etc/passwd"), "etc/passwd");
+    assert_eq!(id_for("/"), "");
+}
+
+#[test]
+fn test_find_and_apply_defaults() {
+    let queries = vec![
+        SavedQuery::parse(
+            "reports/active_users",
+            "-- name: Active users\n-- user: pg\n-- database: app\nSELECT 1",
+        ),
+        SavedQuery::parse("cleanup", "DELETE FROM sessions"),
+    ];
+    assert_eq!(find(&queries, "cleanup.sql").unwrap().id, "cleanup");
+    assert_eq!(
+        find(&queries, "active USERS").unwrap().id,
+        "reports/active_users"
+    );
+    assert!(find(&queries, "missing").is_none());
+    assert_eq!(matches(&queries, "users")[0].id, "reports/active_users");
+    assert_eq!(matches(&queries, "").len(), 2);
+
+    let mut options = Options::default();
+    apply_defaults(&queries[0], &mut options);
+    assert_eq!(options.user.as_deref(), Some("pg"));
+    assert_eq!(options.db.as_deref(), Some("app"));
+    // another credential does not get the saved database
+    let mut options = Options {
+        user: Some("mysql_default".to_string()),
+        ..Options::default()
+    };
+    apply_defaults(&queries[0], &mut options);
+    assert_eq!(options.db, None);
+}
+
+#[test]
+fn test_library_files() {
+    let dir = std::env::temp_dir().join(format!("simplesql_library_{}", std::process::id()));
+    let dir = dir.to_str().unwrap().to_string();
+    let query = SavedQuery {
+        id: id_for("reports/Active users"),
+        name: "Active users".to_string(),
+        sql: "SELECT 1".to_string(),
+        ..SavedQuery::default()
+    };
+    let path = save(&dir, &query).unwrap();
+    assert!(path.ends_with("reports/active_users.sql"));
+    shared::write_file(&format!("{}/notes.txt", dir), "not a query").unwrap();
+    let loaded = load(&dir).unwrap();
+    assert_eq!(loaded.len(), 1);
+    assert_eq!(loaded[0].id, "reports/active_users");
+    assert_eq!(loaded[0].sql, "SELECT 1\n");
+    fs::remove_dir_all(&dir).unwrap();
+    assert!(load(&dir).unwrap().is_empty());
+}
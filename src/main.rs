@@ -39,13 +39,111 @@
 //! | `-t`, `-c` | `--tui`, `--cli`| Launch in terminal mode                      |
 //! | `-h`  | `--help`    | Show help message                                     |
 //! | `-V`  | `--version` | Show version info                                     |
+//! | `-e`  | `--execute SQL` | Run SQL without the TUI, print results to stdout  |
+//! |       | `--connection NAME` | Connection from `config.toml` for `-e`/`-r`/`--repl`       |
+//! | `-u`  | `--user NAME` | Credential from `config.toml` for `-e`/`-r`/`--repl`             |
+//! | `-d`  | `--db NAME` | Database for `-e`/`-r`/`--repl`, defaults to `default_database` of the credential |
+//! | `-f`  | `--format FORMAT` | Output format for `-e`/`-r`: `table` (default), `csv`, `tsv`, `json`, `ndjson`, `markdown`, `html`, `insert` |
+//! | `-r`  | `--run`     | Run the statements of `FILE` (or stdin) without the TUI   |
+//! |       | `--repl`    | Line-oriented prompt for terminals without TUI support |
+//! |       | `--stop-on-error`, `--continue-on-error` | What `-e`/`-r` do when a statement fails |
+//! |       | `--transaction` | Run `-e`/`-r` in one transaction, rolled back on error |
+//! |       | `--import FILE` | Import a CSV, TSV or JSON file into `--table NAME` without the TUI |
+//! |       | `--batch-size ROWS` | Rows per `INSERT` for `--import` (default 500) |
+//! |       | `--format-sql FILE...` | Format SQL files in place, `-` formats stdin to stdout |
+//! |       | `--dialect DIALECT` | Read `--format-sql` files as `mysql`, `postgresql`, `sqlite` or `generic` SQL |
+//! | `-q`  | `--query NAME` | Run a query of the saved query library like `-e` |
+//! |       | `--list-queries` | List the queries of the saved query library |
 //!
 //! ## 🧪 Example
 //!
 //! ```bash
 //! ./simplesql --tui
+//! ./simplesql -e "select * from users" --connection "Local PostgreSQL" --user postgresql_default --db app --format csv > users.csv
+//! ./simplesql --run migrations/001_init.sql --user postgresql_default --db app --transaction
+//! cat migrations/*.sql | ./simplesql --run --user postgresql_default --db app
+//! ./simplesql --repl --user postgresql_default --db app
+//! ./simplesql --import customers.csv --table customers --user postgresql_default --db app
+//! ./simplesql --format-sql queries/*.sql --connection "Local PostgreSQL"
+//! ./simplesql --query reports/active_users --format csv
 //! ```
 //!
+//! With `-e` and `--run` the result sets go to stdout, the status and timing of every statement
+//! to stderr. The exit code is
+//! `0` on success, `1` when a statement failed, `2` for an unknown user, connection or format,
+//! `3` when the config cannot be used and `4` when the database cannot be reached.
+//!
+//! `--import` (or Ctrl+O in the TUI) reads a CSV or TSV file with a header line, or JSON as an
+//! array of objects or one object per line. File columns are matched to the columns of an existing
+//! table by name; when the table does not exist it is created with types inferred from the data.
+//! The rows are inserted in batches inside one transaction, so a failing batch imports nothing and
+//! is reported with its row numbers. MySQL and MariaDB commit a `CREATE TABLE` right away, so there
+//! a table created by a failed import is dropped again. In the Ctrl+O wizard, Tab moves to the column
+//! list: Left/Right picks the table column of a file column or skips it, Space changes its type. The
+//! skipped columns are listed before Enter starts the import.
+//!
+//! In the TUI, F3 lists the databases of the selected credential. The database picked there is
+//! remembered per credential in `~/.simplesql/last_databases.toml`; until one is picked, the
+//! optional `default_database` of the credential in `config.toml` is used.
+//!
+//! The SQL Editor completes keywords and functions of the connection's dialect as well as table
+//! and column names of the selected database while typing, or on Ctrl+Space. After `alias.` it
+//! offers the columns of the aliased table, e.g. `u.` in `SELECT u. FROM users u`. The tables are
+//! read once per connection and database; Ctrl+R in the popup reads them again.
+//!
+//! Highlighting in the SQL Editor follows the connection type: MySQL and PostgreSQL connections
+//! use bundled grammars that know their keywords, quoting, `::` casts and dollar-quoted bodies, all
+//! other connections a generic SQL grammar.
+//!
+//! Ctrl+F in the SQL Editor formats the selection, or the whole script without one: every clause
+//! starts a line, its content is indented below it, and keywords are upper-cased. `--format-sql`
+//! does the same to files, e.g. in a pre-commit hook, reading them in the `--dialect`, or the
+//! dialect of `--connection` or `--user` when given. A file that would not read as the same tokens
+//! after formatting, e.g. a MySQL `#` comment read as generic SQL, is left alone and the exit code
+//! is 2; generic SQL has to read the same in every dialect. The `[format]` section of `config.toml`
+//! sets the spaces per level and the keyword case:
+//!
+//! ```toml
+//! [format]
+//! indent = 4
+//! keyword_case = "lower" # "upper" (default), "lower" or "preserve"
+//! ```
+//!
+//! Every query run in the TUI or the REPL is appended to `~/.simplesql/history.jsonl` with its start
+//! time, connection, credential, database, duration, row count and error. The History tab (F2)
+//! lists them newest first; typing searches them fuzzily, e.g. `slct usr` finds
+//! `SELECT * FROM users`. Enter loads the selected query into the SQL Editor, Ctrl+R switches to
+//! the credential and database it ran on and runs it again. The newest 10000 queries are kept.
+//!
+//! The query library keeps named queries as `.sql` files in `~/.simplesql/queries`, or in the
+//! `library_dir` of the `[settings]` section, e.g. a directory checked into git. Subdirectories are
+//! folders. A comment header holds the details, the rest of the file is the SQL:
+//!
+//! ```sql
+//! -- name: Active users
+//! -- description: Users that logged in during the last 30 days
+//! -- tags: reporting, users
+//! -- user: postgresql_default
+//! -- database: app
+//!
+//! SELECT * FROM users WHERE last_login > now() - interval '30 days';
+//! ```
+//!
+//! Ctrl+S saves the SQL Editor to the library, Ctrl+L searches it: Enter loads the query, Ctrl+R runs
+//! it, both with the saved credential and database if there are any. `--query` runs a saved query by
+//! its path or name without the TUI; `--user`, `--connection` and `--db` override the saved ones.
+//! Saving under the name of another query than the loaded one asks before overwriting it.
+//!
+//! F8 saves the SQL Editor to a file and F9 loads one. The popup lists the `.sql` files and folders
+//! of the typed path and the files used last; Tab completes the path, Ctrl+A shows all files. Saving
+//! over an existing file asks first. The recent files are kept in `~/.simplesql/recent_files`.
+//!
+//! The SQL Editor holds several buffers, shown as tabs: Ctrl+N opens a new one, Ctrl+W closes it,
+//! Alt+Left/Alt+Right or Alt+1..9 switch between them. Each buffer keeps its own file, and a `*` marks
+//! unsaved changes. Ctrl+G binds a buffer to the selected credential and database (shown as
+//! `@database`), so switching to it switches the connection as well. Quitting or closing a buffer with
+//! unsaved changes asks first.
+//!
 //! ## Special Behavior
 // //! The application will automatically detect if it is running in a terminal or not. If it is running in a terminal, it will default to TUI mode unless the `--gui` flag is set. If it is not running in a terminal, it will default to GUI mode.
 // //! The .app/.desktop/.link starts the Programm automatikly in GUI mode.
@@ -66,7 +164,21 @@ use clap::{Arg, Command};
 #[allow(unused_imports)]
 use std::io::Write;
 #[allow(unused_imports)]
+mod completion;
+mod export;
+mod file_picker;
+mod formatter;
+mod headless;
+mod highlight;
+mod history;
+mod import;
+mod library;
+mod pretty;
+mod repl;
+mod schema_browser;
+#[allow(unused_imports)]
 mod shared;
+mod splitter;
 #[allow(unused_imports)]
 mod tui;
 
@@ -76,7 +188,7 @@ fn get_git_hash() -> String {
     use std::process::Command;
 
     let output = Command::new("git")
-        .args(&["rev-parse", "HEAD"])
+        .args(["rev-parse", "HEAD"])
         .output()
         .unwrap();
     let git_hash = String::from_utf8(output.stdout).unwrap();
@@ -84,6 +196,18 @@ fn get_git_hash() -> String {
     git_hash
 }
 
+/// Reads the script for `--run` from `path`, or from stdin when it is missing or `-`.
+fn read_script(path: Option<&str>) -> std::io::Result<String> {
+    match path {
+        Some(path) if path != "-" => shared::read_file(path),
+        _ => {
+            let mut script = String::new();
+            std::io::Read::read_to_string(&mut std::io::stdin(), &mut script)?;
+            Ok(script)
+        }
+    }
+}
+
 fn main() {
     // Set up the CLI application using Clap and congigs
     let version_string = format!(
@@ -119,6 +243,119 @@ fn main() {
           .long_help("The SQL file to load on startup. If not provided, the program will start with an empty state.")
           .value_name("FILE")
           .index(1)  // Positional argument
+    ).arg(
+        Arg::new("execute")
+          .long("execute")
+          .short('e')
+          .value_name("SQL")
+          .help("Run SQL without the TUI and print the results to stdout")
+          .long_help("Runs the given SQL statements without the TUI and prints the result sets to stdout.\nSummaries and errors go to stderr. Exit codes: 0 ok, 1 query failed, 2 unknown user/connection/format, 3 config error, 4 connection failed.")
+    ).arg(
+        Arg::new("connection")
+          .long("connection")
+          .value_name("NAME")
+          .help("Connection from config.toml to use with --execute/--run/--repl")
+    ).arg(
+        Arg::new("user")
+          .long("user")
+          .short('u')
+          .value_name("NAME")
+          .help("Credential from config.toml to use with --execute/--run/--repl")
+    ).arg(
+        Arg::new("db")
+          .long("db")
+          .short('d')
+          .value_name("NAME")
+          .help("Database to use with --execute/--run/--repl")
+    ).arg(
+        Arg::new("format")
+          .long("format")
+          .short('f')
+          .value_name("FORMAT")
+          .default_value("table")
+          .help("Output format for --execute/--run: table, csv, tsv, json, ndjson, markdown, html or insert")
+    ).arg(
+        Arg::new("run")
+          .long("run")
+          .short('r')
+          .action(clap::ArgAction::SetTrue)
+          .conflicts_with("execute")
+          .help("Run the statements of FILE (or stdin) without the TUI")
+          .long_help("Runs the statements of FILE one after another without the TUI and reports the status and timing of each on stderr.\nReads the script from stdin when FILE is missing or \"-\", e.g. `simplesql --run < migration.sql`.")
+    ).arg(
+        Arg::new("repl")
+          .long("repl")
+          .action(clap::ArgAction::SetTrue)
+          .conflicts_with_all(["execute", "run"])
+          .help("Start a line-oriented prompt instead of the full-screen TUI")
+          .long_help("Starts a line-oriented prompt for dumb terminals and serial consoles.\nStatements end with ';', type \\? for the backslash commands. The history is kept in ~/.simplesql/repl_history.")
+    ).arg(
+        Arg::new("stop-on-error")
+          .long("stop-on-error")
+          .action(clap::ArgAction::SetTrue)
+          .conflicts_with("continue-on-error")
+          .help("Stop at the first failing statement [default, see settings.continue_on_error]")
+    ).arg(
+        Arg::new("continue-on-error")
+          .long("continue-on-error")
+          .action(clap::ArgAction::SetTrue)
+          .help("Keep running the remaining statements after one fails")
+    ).arg(
+        Arg::new("transaction")
+          .long("transaction")
+          .action(clap::ArgAction::SetTrue)
+          .help("Run all statements in one transaction, rolled back when one fails")
+    ).arg(
+        Arg::new("import")
+          .long("import")
+          .value_name("FILE")
+          .requires("table")
+          .conflicts_with_all(["execute", "run", "repl"])
+          .help("Import a CSV, TSV or JSON file into --table without the TUI")
+          .long_help("Imports the rows of a .csv, .tsv or .json (array of objects or NDJSON) file into --table.\nColumns are matched to the existing table by name; a missing table is created with types inferred from the data.\nAll rows are inserted in one transaction, so nothing is imported when a batch fails; on MySQL/MariaDB a table created by the import is dropped again.")
+    ).arg(
+        Arg::new("table")
+          .long("table")
+          .value_name("NAME")
+          .requires("import")
+          .help("Target table for --import")
+    ).arg(
+        Arg::new("batch-size")
+          .long("batch-size")
+          .value_name("ROWS")
+          .requires("import")
+          .value_parser(clap::value_parser!(usize))
+          .default_value(&*Box::leak(import::DEFAULT_BATCH_SIZE.to_string().into_boxed_str()))
+          .help("Rows per INSERT statement for --import")
+    ).arg(
+        Arg::new("format-sql")
+          .long("format-sql")
+          .value_name("FILE")
+          .num_args(1..)
+          .conflicts_with_all(["execute", "run", "repl", "import", "file"])
+          .help("Format SQL files in place without the TUI, \"-\" formats stdin to stdout")
+          .long_help("Rewrites the given SQL files with one clause per line, indented content and keywords cased as set in the [format] section of config.toml, e.g. as a pre-commit hook.\nScripts are read in the --dialect, or the dialect of --connection/--user when given, else as generic SQL. A file that would read differently after formatting is left alone and the exit code is 2. \"-\" formats stdin to stdout.")
+    ).arg(
+        Arg::new("dialect")
+          .long("dialect")
+          .value_name("DIALECT")
+          .requires("format-sql")
+          .value_parser(["mysql", "mariadb", "postgresql", "postgres", "sqlite", "generic"])
+          .help("SQL dialect of the files for --format-sql")
+    ).arg(
+        Arg::new("query")
+          .long("query")
+          .short('q')
+          .value_name("NAME")
+          .conflicts_with_all(["execute", "run", "repl", "import", "format-sql", "file"])
+          .help("Run a query of the saved query library without the TUI, like --execute")
+          .long_help("Runs a query of the saved query library (Ctrl+L in the TUI) like --execute.\nNAME is the path below the library directory without .sql, e.g. reports/active_users, or the name of the query.\nThe credential and database saved with the query are used unless --user, --connection or --db is given.")
+    ).arg(
+        Arg::new("list-queries")
+          .long("list-queries")
+          .action(clap::ArgAction::SetTrue)
+          .conflicts_with_all(["execute", "run", "repl", "import", "format-sql", "query"])
+          .help("List the queries of the saved query library")
     )
     .get_matches();
     if let Err(e) = shared::check_and_gen_config() {
@@ -126,16 +363,114 @@ fn main() {
         std::process::exit(1);
     }
     let is_terminal = atty::is(atty::Stream::Stdout) || atty::is(atty::Stream::Stderr);
-    shared::setup_logger(matches.get_flag("tui") || is_terminal).unwrap();
-    let mut file_content = String::new();
-    if let Some(file_path) = matches.get_one::<String>("file").map(|s| s.as_str()) {
-        file_content = shared::read_file(file_path).unwrap();
+    let headless = matches.contains_id("execute")
+        || matches.get_flag("run")
+        || matches.get_flag("repl")
+        || matches.contains_id("import")
+        || matches.contains_id("format-sql")
+        || matches.contains_id("query")
+        || matches.get_flag("list-queries");
+    if let Err(e) = shared::setup_logger(!headless && (matches.get_flag("tui") || is_terminal)) {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    }
+
+    let file_path = matches.get_one::<String>("file").map(|s| s.as_str());
+    if matches.get_flag("repl") {
+        let options = headless::Options {
+            connection: matches.get_one::<String>("connection").cloned(),
+            user: matches.get_one::<String>("user").cloned(),
+            db: matches.get_one::<String>("db").cloned(),
+            ..headless::Options::default()
+        };
+        std::process::exit(repl::main_repl(&options) as i32);
+    }
+    if let Some(path) = matches.get_one::<String>("import") {
+        let options = headless::Options {
+            connection: matches.get_one::<String>("connection").cloned(),
+            user: matches.get_one::<String>("user").cloned(),
+            db: matches.get_one::<String>("db").cloned(),
+            ..headless::Options::default()
+        };
+        let table = matches.get_one::<String>("table").unwrap();
+        let batch_size = *matches.get_one::<usize>("batch-size").unwrap();
+        std::process::exit(import::main_import(&options, path, table, batch_size) as i32);
+    }
+    if let Some(paths) = matches.get_many::<String>("format-sql") {
+        let options = headless::Options {
+            connection: matches.get_one::<String>("connection").cloned(),
+            user: matches.get_one::<String>("user").cloned(),
+            ..headless::Options::default()
+        };
+        let dialect = matches
+            .get_one::<String>("dialect")
+            .and_then(|name| shared::Dialect::from_name(name));
+        let paths: Vec<String> = paths.cloned().collect();
+        std::process::exit(pretty::main_format_sql(&options, dialect, &paths) as i32);
+    }
+    if matches.get_flag("list-queries") {
+        std::process::exit(library::main_list_queries() as i32);
+    }
+    if headless {
+        let sql = match matches.get_one::<String>("execute") {
+            Some(sql) => sql.clone(),
+            None if matches.contains_id("query") => String::new(),
+            None => match read_script(file_path) {
+                Ok(sql) => sql,
+                Err(e) => {
+                    eprintln!("Cannot read {}: {}", file_path.unwrap_or("stdin"), e);
+                    std::process::exit(headless::ExitCode::Usage as i32);
+                }
+            },
+        };
+        let format = matches
+            .get_one::<String>("format")
+            .map(|s| s.as_str())
+            .unwrap_or("table");
+        let Some(format) = formatter::Format::parse(format) else {
+            eprintln!(
+                "Unknown output format \"{}\" (expected one of {})",
+                format,
+                formatter::Format::NAMES
+            );
+            std::process::exit(headless::ExitCode::Usage as i32);
+        };
+        let continue_on_error = if matches.get_flag("continue-on-error") {
+            Some(true)
+        } else if matches.get_flag("stop-on-error") {
+            Some(false)
+        } else {
+            None
+        };
+        let options = headless::Options {
+            sql,
+            connection: matches.get_one::<String>("connection").cloned(),
+            user: matches.get_one::<String>("user").cloned(),
+            db: matches.get_one::<String>("db").cloned(),
+            format,
+            continue_on_error,
+            transaction: matches.get_flag("transaction"),
+        };
+        if let Some(name) = matches.get_one::<String>("query") {
+            std::process::exit(library::main_saved_query(&options, name) as i32);
+        }
+        std::process::exit(headless::run(&options) as i32);
+    }
+    let mut file = None;
+    if let Some(file_path) = file_path {
+        match shared::read_file(file_path) {
+            Ok(content) => file = Some((file_path, content)),
+            Err(e) => {
+                eprintln!("Cannot read {}: {}", file_path, e);
+                std::process::exit(1);
+            }
+        }
     }
 
     if matches.get_flag("tui") || is_terminal {
         // TUI mode
         info!("TUI Mode activated");
-        if let Err(e) = tui::main_tui(file_content) {
+        if let Err(e) = tui::main_tui(file) {
             error!("{e}");
             std::process::exit(1);
         }
@@ -0,0 +1,1060 @@
+// Copyright (c) 2025 mcpeaps_HD
+//
+// This software is released under the MIT License.
+// https://opensource.org/licenses/MIT
+use crate::completion;
+use crate::headless::{self, ExitCode, Options};
+use crate::shared::{self, Dialect, FormatSettings, KeywordCase};
+use crate::splitter;
+use std::io::{self, Read, Write};
+
+/// Clauses on a line of their own, with their content indented on the lines below.
+const BLOCK_CLAUSES: &[&[&str]] = &[
+    &["SELECT", "DISTINCT"],
+    &["SELECT", "ALL"],
+    &["SELECT"],
+    &["FROM"],
+    &["WHERE"],
+    &["GROUP", "BY"],
+    &["HAVING"],
+    &["WINDOW"],
+    &["ORDER", "BY"],
+    &["SET"],
+    &["VALUES"],
+    &["RETURNING"],
+];
+
+/// Clauses that start a new line and keep their content on it.
+const LINE_CLAUSES: &[&[&str]] = &[
+    &["LIMIT"],
+    &["OFFSET"],
+    &["FETCH"],
+    &["ON", "CONFLICT"],
+    &["ON", "DUPLICATE", "KEY", "UPDATE"],
+    &["FOR", "UPDATE"],
+    &["FOR", "SHARE"],
+];
+
+/// Clauses that only begin a statement, a subquery or the statement after `WITH`.
+const STATEMENT_CLAUSES: &[&[&str]] = &[
+    &["WITH", "RECURSIVE"],
+    &["WITH"],
+    &["INSERT", "INTO"],
+    &["INSERT", "IGNORE", "INTO"],
+    &["INSERT", "OR", "REPLACE", "INTO"],
+    &["INSERT", "OR", "IGNORE", "INTO"],
+    &["REPLACE", "INTO"],
+    &["UPDATE"],
+    &["DELETE", "FROM"],
+    &["DELETE"],
+];
+
+const SET_OPERATIONS: &[&[&str]] = &[
+    &["UNION", "ALL"],
+    &["UNION", "DISTINCT"],
+    &["UNION"],
+    &["INTERSECT"],
+    &["EXCEPT"],
+];
+
+const JOINS: &[&[&str]] = &[
+    &["JOIN"],
+    &["INNER", "JOIN"],
+    &["CROSS", "JOIN"],
+    &["NATURAL", "JOIN"],
+    &["STRAIGHT_JOIN"],
+    &["LEFT", "JOIN"],
+    &["LEFT", "OUTER", "JOIN"],
+    &["RIGHT", "JOIN"],
+    &["RIGHT", "OUTER", "JOIN"],
+    &["FULL", "JOIN"],
+    &["FULL", "OUTER", "JOIN"],
+];
+
+/// Clauses whose `AND` and `OR` start a new line.
+const CONDITION_CLAUSES: &[&str] = &["WHERE", "HAVING", "FROM"];
+
+/// Words that may come before the statement without making it something else than a query.
+const QUERY_PREFIXES: &[&str] = &["EXPLAIN", "ANALYZE", "VERBOSE"];
+
+/// Keywords the completion does not offer, but that are cased like the others.
+const EXTRA_KEYWORDS: &[&str] = &[
+    "ANY",
+    "COLLATE",
+    "CURRENT",
+    "ESCAPE",
+    "FETCH",
+    "FIRST",
+    "FOLLOWING",
+    "FOR",
+    "IF",
+    "INTERVAL",
+    "LAST",
+    "NATURAL",
+    "NEXT",
+    "NULLS",
+    "ONLY",
+    "OVER",
+    "PARTITION",
+    "PRECEDING",
+    "RECURSIVE",
+    "ROW",
+    "ROWS",
+    "SOME",
+    "TEMPORARY",
+    "UNBOUNDED",
+    "WINDOW",
+];
+
+/// Type names, cased like keywords; `numeric(10, 2)` keeps its parentheses close like a call.
+const TYPE_NAMES: &[&str] = &[
+    "BIGINT",
+    "BOOLEAN",
+    "CHAR",
+    "DATE",
+    "DECIMAL",
+    "DOUBLE",
+    "FLOAT",
+    "INT",
+    "INTEGER",
+    "NUMERIC",
+    "PRECISION",
+    "REAL",
+    "SMALLINT",
+    "TEXT",
+    "TIMESTAMP",
+    "VARCHAR",
+];
+
+/// Words after which a name is expected, which stays as typed even if it looks like a keyword.
+const NAME_KEYWORDS: &[&str] = &["FROM", "JOIN", "UPDATE", "INTO", "TABLE"];
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum Kind {
+    /// Keywords and unquoted names.
+    Word,
+    /// Strings, quoted names, numbers and parameters, written as they are.
+    Literal,
+    Comment,
+    /// `(` or `[`.
+    Open,
+    /// `)` or `]`.
+    Close,
+    Comma,
+    /// `;` or the delimiter set with `DELIMITER`.
+    Delimiter,
+    /// `;` in the body of a routine while `DELIMITER` has set another delimiter.
+    Separator,
+    Dot,
+    /// PostgreSQL's `::`.
+    Cast,
+    Operator,
+    /// A `DELIMITER` command, kept on a line of its own.
+    Command,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Token<'a> {
+    kind: Kind,
+    text: &'a str,
+    /// Whitespace before the token in the script.
+    space: bool,
+    /// A line break before the token in the script.
+    newline: bool,
+}
+
+fn tokenize(script: &str, dialect: Dialect) -> Vec<Token<'_>> {
+    let mut tokens: Vec<Token> = Vec::new();
+    let mut delimiter = String::from(";");
+    let (mut space, mut newline) = (false, false);
+    let mut i = 0;
+    while i < script.len() {
+        let rest = &script[i..];
+        let c = rest.chars().next().unwrap_or_default();
+        if c.is_whitespace() {
+            space = true;
+            newline |= c == '\n';
+            i += c.len_utf8();
+            continue;
+        }
+        let statement_start = tokens
+            .iter()
+            .rfind(|t| t.kind != Kind::Comment)
+            .is_none_or(|t| matches!(t.kind, Kind::Delimiter | Kind::Command));
+        let (kind, end) = if statement_start
+            && splitter::supports_delimiter_command(dialect)
+            && splitter::at_line_start(script, i)
+            && let Some((new_delimiter, line_end)) = splitter::parse_delimiter_command(rest)
+        {
+            delimiter = new_delimiter;
+            (Kind::Command, i + rest[..line_end].trim_end().len())
+        } else if rest.starts_with(delimiter.as_str()) {
+            (Kind::Delimiter, i + delimiter.len())
+        } else if c == ';' {
+            (Kind::Separator, i + 1)
+        } else {
+            // `.5` is a number, `t.5` or `).5` are not
+            let after_operand = !space
+                && tokens
+                    .last()
+                    .is_some_and(|t| matches!(t.kind, Kind::Word | Kind::Literal | Kind::Close));
+            scan(script, i, c, dialect, after_operand)
+        };
+        tokens.push(Token {
+            kind,
+            text: &script[i..end],
+            space,
+            newline,
+        });
+        (space, newline) = (false, false);
+        i = end;
+    }
+    tokens
+}
+
+/// Kind and end of the token starting with `c` at byte `start`.
+fn scan(
+    script: &str,
+    start: usize,
+    c: char,
+    dialect: Dialect,
+    after_operand: bool,
+) -> (Kind, usize) {
+    let rest = &script[start..];
+    let next = rest[c.len_utf8()..].chars().next();
+    let word_end = |from: usize| {
+        script[from..]
+            .find(|c: char| !(c.is_alphanumeric() || c == '_' || c == '$'))
+            .map_or(script.len(), |n| from + n)
+    };
+    match c {
+        '\'' => (
+            Kind::Literal,
+            splitter::skip_quoted(script, start, '\'', dialect == Dialect::MySql),
+        ),
+        '"' => (
+            Kind::Literal,
+            splitter::skip_quoted(script, start, '"', dialect == Dialect::MySql),
+        ),
+        '`' if dialect != Dialect::Postgres => (
+            Kind::Literal,
+            splitter::skip_quoted(script, start, '`', false),
+        ),
+        '[' if dialect == Dialect::Sqlite => (
+            Kind::Literal,
+            rest.find(']').map_or(script.len(), |n| start + n + 1),
+        ),
+        '-' if rest.starts_with("--") => (Kind::Comment, line_end(script, start)),
+        '#' if dialect == Dialect::MySql => (Kind::Comment, line_end(script, start)),
+        '/' if rest.starts_with("/*") => (
+            Kind::Comment,
+            splitter::skip_block_comment(script, start, dialect == Dialect::Postgres),
+        ),
+        '$' if splitter::supports_dollar_quotes(dialect)
+            && let Some(tag) = splitter::dollar_tag(script, start) =>
+        {
+            (
+                Kind::Literal,
+                splitter::skip_dollar_quoted(script, start, tag),
+            )
+        }
+        '$' | '?' => (Kind::Literal, word_end(start + 1)),
+        '@' if dialect != Dialect::Postgres
+            && next.is_some_and(|c| c.is_alphanumeric() || c == '_' || c == '@') =>
+        {
+            let name = if rest.starts_with("@@") { 2 } else { 1 };
+            (Kind::Literal, word_end(start + name))
+        }
+        ':' if rest.starts_with("::") => (Kind::Cast, start + 2),
+        ':' if rest.starts_with(":=") => (Kind::Operator, start + 2),
+        ':' if next.is_some_and(|c| c.is_alphabetic() || c == '_') => {
+            (Kind::Literal, word_end(start + 1))
+        }
+        '0'..='9' => (Kind::Literal, number_end(script, start)),
+        '.' if !after_operand && next.is_some_and(|c| c.is_ascii_digit()) => {
+            (Kind::Literal, number_end(script, start))
+        }
+        '(' | '[' => (Kind::Open, start + 1),
+        ')' | ']' => (Kind::Close, start + 1),
+        ',' => (Kind::Comma, start + 1),
+        '.' => (Kind::Dot, start + 1),
+        c if c.is_alphabetic() || c == '_' => {
+            let end = word_end(start);
+            let word = &script[start..end];
+            // E'...', X'...', N'...' and MySQL's _utf8mb4'...' belong to their string
+            let prefix = matches!(
+                word.to_ascii_lowercase().as_str(),
+                "e" | "b" | "x" | "n" | "u"
+            ) || (dialect == Dialect::MySql && word.starts_with('_'));
+            let quote = if word.eq_ignore_ascii_case("u") && script[end..].starts_with("&'") {
+                Some(end + 1)
+            } else {
+                script[end..].starts_with('\'').then_some(end)
+            };
+            match quote {
+                Some(quote) if prefix => {
+                    let backslash = dialect == Dialect::MySql || word.eq_ignore_ascii_case("e");
+                    (
+                        Kind::Literal,
+                        splitter::skip_quoted(script, quote, '\'', backslash),
+                    )
+                }
+                _ => (Kind::Word, end),
+            }
+        }
+        _ if is_operator_char(c, dialect) => {
+            let mut end = start;
+            for (offset, c) in rest.char_indices() {
+                let at = &rest[offset..];
+                if !is_operator_char(c, dialect)
+                    || (offset > 0 && (at.starts_with("--") || at.starts_with("/*")))
+                {
+                    break;
+                }
+                end = start + offset + c.len_utf8();
+            }
+            (Kind::Operator, end)
+        }
+        _ => (Kind::Operator, start + c.len_utf8()),
+    }
+}
+
+fn is_operator_char(c: char, dialect: Dialect) -> bool {
+    "+-*/%<>=!|&^~@".contains(c) || (c == '#' && dialect != Dialect::MySql)
+}
+
+/// End of a line comment, before the line break.
+fn line_end(script: &str, start: usize) -> usize {
+    let end = script[start..]
+        .find('\n')
+        .map_or(script.len(), |n| start + n);
+    start + script[start..end].trim_end().len()
+}
+
+fn number_end(script: &str, start: usize) -> usize {
+    let mut end = start;
+    let mut chars = script[start..].char_indices().peekable();
+    while let Some((offset, c)) = chars.next() {
+        if !(c.is_alphanumeric() || c == '.' || c == '_') {
+            break;
+        }
+        end = start + offset + 1;
+        // the sign of an exponent
+        if matches!(c, 'e' | 'E')
+            && let Some((_, sign @ ('+' | '-'))) = chars.peek().copied()
+        {
+            chars.next();
+            end += sign.len_utf8();
+        }
+    }
+    end
+}
+
+/// Collects the formatted text and keeps track of where the next token goes.
+struct Printer {
+    out: String,
+    /// Spaces per indentation level.
+    width: usize,
+    /// Line breaks to put before the next token.
+    breaks: usize,
+    /// Indentation of the line of the next token when `breaks` is set, else of the current one.
+    indent: usize,
+}
+impl Printer {
+    /// Puts the next token on a new line.
+    fn line(&mut self, indent: usize) {
+        self.breaks = self.breaks.max(1);
+        self.indent = indent;
+    }
+
+    fn push(&mut self, text: &str, space: bool) {
+        if self.breaks > 0 {
+            if !self.out.is_empty() {
+                self.out.truncate(self.out.trim_end_matches(' ').len());
+                self.out.push_str(&"\n".repeat(self.breaks));
+            }
+            self.out.push_str(&" ".repeat(self.indent * self.width));
+        } else if space && !self.out.is_empty() {
+            self.out.push(' ');
+        }
+        self.breaks = 0;
+        self.out.push_str(text);
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum LevelKind {
+    Statement,
+    /// `(SELECT ...)`
+    Subquery,
+    Parens,
+    /// `CASE ... END`
+    Case,
+}
+
+/// A statement, or a part of it in parentheses or `CASE`.
+#[derive(Clone, Debug)]
+struct Level {
+    kind: LevelKind,
+    /// Indentation of its clauses, or of the `CASE`.
+    base: usize,
+    /// Indentation of the line the level was opened on.
+    outer: usize,
+    /// First word of the current clause.
+    clause: Option<&'static str>,
+    /// The clause content goes on the lines below the clause.
+    block: bool,
+    /// Laid out clause by clause; only queries are.
+    query: bool,
+    /// Something else than `EXPLAIN` was written in it.
+    started: bool,
+    /// The next `AND` belongs to a `BETWEEN`.
+    between: bool,
+}
+impl Level {
+    fn new(kind: LevelKind, base: usize, outer: usize) -> Self {
+        Level {
+            kind,
+            base,
+            outer,
+            clause: None,
+            block: false,
+            query: false,
+            started: false,
+            between: false,
+        }
+    }
+
+    fn has_clauses(&self) -> bool {
+        matches!(self.kind, LevelKind::Statement | LevelKind::Subquery)
+    }
+}
+
+struct Formatter<'a> {
+    tokens: Vec<Token<'a>>,
+    settings: &'a FormatSettings,
+    keywords: Vec<&'static str>,
+    functions: Vec<&'static str>,
+    printer: Printer,
+    levels: Vec<Level>,
+    /// The next token is written without a space, e.g. after `(`.
+    glue: bool,
+    /// Kind of the last token written, and whether it was a keyword.
+    last: Option<(Kind, bool)>,
+}
+
+/// Rewrites a script with one clause per line, indented content and keywords in
+/// `settings.keyword_case`. Strings, quoted names and comments are kept as they are.
+pub fn format_sql(script: &str, dialect: Dialect, settings: &FormatSettings) -> String {
+    let tokens = tokenize(script, dialect);
+    if tokens.is_empty() {
+        return script.to_string();
+    }
+    let mut formatter = Formatter {
+        tokens,
+        settings,
+        keywords: keywords(dialect),
+        functions: completion::functions(dialect),
+        printer: Printer {
+            out: String::new(),
+            width: settings.indent,
+            breaks: 0,
+            indent: 0,
+        },
+        levels: vec![Level::new(LevelKind::Statement, 0, 0)],
+        glue: false,
+        last: None,
+    };
+    let mut i = 0;
+    while i < formatter.tokens.len() {
+        i += formatter.token(i);
+    }
+    let mut out = formatter.printer.out;
+    if script.ends_with('\n') {
+        out.push('\n');
+    }
+    out
+}
+
+/// Words the formatter writes in the keyword case, sorted.
+fn keywords(dialect: Dialect) -> Vec<&'static str> {
+    let mut keywords = completion::keywords(dialect);
+    keywords.extend(EXTRA_KEYWORDS);
+    keywords.extend(TYPE_NAMES);
+    keywords.sort_unstable();
+    keywords.dedup();
+    keywords
+}
+
+/// Whether `formatted` reads as the same tokens as `script`, apart from whitespace and the
+/// case of keywords. Generic SQL may be any dialect, so it has to read the same in all of them:
+/// whitespace matters to MySQL's `#` comments and backslash escapes.
+pub fn same_tokens(script: &str, formatted: &str, dialect: Dialect) -> bool {
+    let keywords = keywords(dialect);
+    let dialects = match dialect {
+        Dialect::Generic => vec![
+            Dialect::Generic,
+            Dialect::MySql,
+            Dialect::Postgres,
+            Dialect::Sqlite,
+        ],
+        dialect => vec![dialect],
+    };
+    dialects.into_iter().all(|dialect| {
+        let before = tokenize(script, dialect);
+        let after = tokenize(formatted, dialect);
+        before.len() == after.len()
+            && before.iter().zip(&after).all(|(a, b)| {
+                a.kind == b.kind
+                    && (a.text == b.text
+                        || (a.kind == Kind::Word
+                            && a.text.eq_ignore_ascii_case(b.text)
+                            && keywords
+                                .binary_search(&a.text.to_ascii_uppercase().as_str())
+                                .is_ok()))
+            })
+    })
+}
+
+impl Formatter<'_> {
+    fn level(&mut self) -> &mut Level {
+        self.levels
+            .last_mut()
+            .expect("the statement level is never closed")
+    }
+
+    /// Writes the token at `i` and returns how many tokens were used.
+    fn token(&mut self, i: usize) -> usize {
+        let token = self.tokens[i];
+        match token.kind {
+            Kind::Word => return self.word(i),
+            Kind::Comment => self.comment(i),
+            Kind::Command => {
+                self.printer.line(0);
+                self.write(token, token.text, false);
+                self.printer.line(0);
+            }
+            Kind::Delimiter => {
+                self.write(token, token.text, false);
+                self.levels = vec![Level::new(LevelKind::Statement, 0, 0)];
+                self.printer.breaks = 2;
+                self.printer.indent = 0;
+            }
+            Kind::Separator => {
+                self.write(token, token.text, false);
+                self.levels = vec![Level::new(LevelKind::Statement, 0, 0)];
+                self.printer.line(0);
+            }
+            Kind::Open => {
+                let subquery = token.text == "("
+                    && self.tokens[i + 1..]
+                        .iter()
+                        .find(|t| t.kind != Kind::Comment)
+                        .is_some_and(|t| {
+                            t.kind == Kind::Word
+                                && ["SELECT", "WITH"]
+                                    .contains(&t.text.to_ascii_uppercase().as_str())
+                        });
+                self.write(token, token.text, true);
+                self.glue = true;
+                let outer = self.printer.indent;
+                if subquery {
+                    self.levels
+                        .push(Level::new(LevelKind::Subquery, outer + 1, outer));
+                    self.printer.line(outer + 1);
+                } else {
+                    self.levels
+                        .push(Level::new(LevelKind::Parens, outer, outer));
+                }
+            }
+            Kind::Close => {
+                while self.levels.len() > 1 && self.level().kind == LevelKind::Case {
+                    self.levels.pop();
+                }
+                if self.levels.len() > 1 {
+                    let level = self.levels.pop().unwrap_or_else(|| unreachable!());
+                    if level.kind == LevelKind::Subquery {
+                        self.printer.line(level.outer);
+                    }
+                }
+                self.write(token, token.text, false);
+            }
+            Kind::Comma => {
+                self.write(token, token.text, false);
+                let level = self.level();
+                if level.has_clauses() && level.query && level.block {
+                    let indent = level.base + 1;
+                    self.printer.line(indent);
+                }
+            }
+            Kind::Dot | Kind::Cast => {
+                self.write(token, token.text, false);
+                self.glue = true;
+            }
+            Kind::Operator => {
+                let operand_before = matches!(
+                    self.last,
+                    Some((Kind::Word, false)) | Some((Kind::Literal, _)) | Some((Kind::Close, _))
+                );
+                let unary = matches!(token.text, "-" | "+" | "~")
+                    && !operand_before
+                    && self
+                        .tokens
+                        .get(i + 1)
+                        .is_some_and(|t| t.kind != Kind::Operator && t.kind != Kind::Comment);
+                self.write(token, token.text, true);
+                self.glue = unary;
+            }
+            Kind::Literal => self.write(token, token.text, true),
+        }
+        1
+    }
+
+    fn word(&mut self, i: usize) -> usize {
+        let token = self.tokens[i];
+        let upper = token.text.to_ascii_uppercase();
+        let previous = self.previous_word(i);
+        let level = self.level().clone();
+
+        if level.has_clauses() && !self.near_dot(i) {
+            let after_as = previous.as_deref() == Some("AS");
+            let statement_start = !level.started || level.clause == Some("WITH") || after_as;
+            if level.query
+                && let Some(phrase) = self.phrase(i, SET_OPERATIONS)
+            {
+                let base = level.base;
+                self.printer.line(base);
+                self.write_phrase(i, phrase);
+                let level = self.level();
+                level.clause = None;
+                level.started = false;
+                self.printer.line(base);
+                return phrase.len();
+            }
+            if statement_start && let Some(phrase) = self.phrase(i, STATEMENT_CLAUSES) {
+                return self.clause(i, phrase, false);
+            }
+            let starts_query =
+                (!level.started || after_as) && ["SELECT", "VALUES"].contains(&upper.as_str());
+            let is_clause = match upper.as_str() {
+                "FROM" => previous.as_deref() != Some("DISTINCT"),
+                "VALUES" => level.clause != Some("ON"),
+                _ => true,
+            };
+            if (level.query || starts_query)
+                && is_clause
+                && let Some(phrase) = self.phrase(i, BLOCK_CLAUSES)
+            {
+                return self.clause(i, phrase, true);
+            }
+            if level.query {
+                if let Some(phrase) = self.phrase(i, LINE_CLAUSES) {
+                    return self.clause(i, phrase, false);
+                }
+                if let Some(phrase) = self.phrase(i, JOINS) {
+                    self.printer.line(level.base + 1);
+                    self.write_phrase(i, phrase);
+                    return phrase.len();
+                }
+                let condition = level
+                    .clause
+                    .is_some_and(|clause| CONDITION_CLAUSES.contains(&clause));
+                match upper.as_str() {
+                    "AND" | "OR" if condition && !(upper == "AND" && level.between) => {
+                        self.printer.line(level.base + 1);
+                    }
+                    "AND" => self.level().between = false,
+                    "BETWEEN" => self.level().between = true,
+                    _ => {}
+                }
+            }
+        }
+
+        let in_case = level.kind == LevelKind::Case;
+        match upper.as_str() {
+            "WHEN" | "ELSE" if in_case => self.printer.line(level.base + 1),
+            "END" if in_case => {
+                self.printer.line(level.base);
+                self.levels.pop();
+            }
+            _ => {}
+        }
+        let text = self.cased(i);
+        self.write(token, &text, true);
+        if upper == "CASE" {
+            let indent = self.printer.indent;
+            self.levels
+                .push(Level::new(LevelKind::Case, indent, indent));
+        }
+        if !QUERY_PREFIXES.contains(&upper.as_str()) {
+            self.level().started = true;
+        }
+        1
+    }
+
+    /// Starts a clause with the words of `phrase` at `i`.
+    fn clause(&mut self, i: usize, phrase: &'static [&'static str], block: bool) -> usize {
+        let level = self.level();
+        level.clause = Some(phrase[0]);
+        level.block = block;
+        level.query = true;
+        level.started = true;
+        level.between = false;
+        let base = level.base;
+        self.printer.line(base);
+        self.write_phrase(i, phrase);
+        if block {
+            self.printer.line(base + 1);
+        }
+        phrase.len()
+    }
+
+    fn write_phrase(&mut self, i: usize, phrase: &[&str]) {
+        for (n, word) in phrase.iter().enumerate() {
+            let text = self.keyword_case(self.tokens[i + n].text, word);
+            self.write(self.tokens[i + n], &text, true);
+        }
+    }
+
+    /// The longest of `phrases` made of the words at `i`.
+    fn phrase(
+        &self,
+        i: usize,
+        phrases: &[&'static [&'static str]],
+    ) -> Option<&'static [&'static str]> {
+        phrases
+            .iter()
+            .filter(|phrase| {
+                phrase.iter().enumerate().all(|(n, word)| {
+                    self.tokens
+                        .get(i + n)
+                        .is_some_and(|t| t.kind == Kind::Word && t.text.eq_ignore_ascii_case(word))
+                })
+            })
+            .max_by_key(|phrase| phrase.len())
+            .copied()
+    }
+
+    /// The word before `i` in upper case, skipping comments.
+    fn previous_word(&self, i: usize) -> Option<String> {
+        self.tokens[..i]
+            .iter()
+            .rfind(|t| t.kind != Kind::Comment)
+            .filter(|t| t.kind == Kind::Word)
+            .map(|t| t.text.to_ascii_uppercase())
+    }
+
+    /// Whether the word at `i` is part of a qualified name like `t.name`.
+    fn near_dot(&self, i: usize) -> bool {
+        let dot = |t: Option<&Token>| t.is_some_and(|t| t.kind == Kind::Dot);
+        dot(i.checked_sub(1).and_then(|p| self.tokens.get(p))) || dot(self.tokens.get(i + 1))
+    }
+
+    fn is_keyword(&self, i: usize) -> bool {
+        let upper = self.tokens[i].text.to_ascii_uppercase();
+        self.keywords.binary_search(&upper.as_str()).is_ok()
+            && !self.near_dot(i)
+            && !self
+                .previous_word(i)
+                .is_some_and(|previous| NAME_KEYWORDS.contains(&previous.as_str()))
+    }
+
+    /// The word at `i` in keyword case if it is a keyword.
+    fn cased(&self, i: usize) -> String {
+        let text = self.tokens[i].text;
+        if self.is_keyword(i) {
+            self.keyword_case(text, &text.to_ascii_uppercase())
+        } else {
+            text.to_string()
+        }
+    }
+
+    fn keyword_case(&self, text: &str, upper: &str) -> String {
+        match self.settings.keyword_case {
+            KeywordCase::Upper => upper.to_string(),
+            KeywordCase::Lower => upper.to_ascii_lowercase(),
+            KeywordCase::Preserve => text.to_string(),
+        }
+    }
+
+    fn comment(&mut self, i: usize) {
+        let token = self.tokens[i];
+        if token.newline || self.printer.out.is_empty() {
+            if self.printer.breaks == 0 {
+                self.printer.line(self.printer.indent);
+            }
+            self.write(token, token.text, true);
+        } else {
+            // behind the code it was written after, even if that ended a line
+            let breaks = std::mem::take(&mut self.printer.breaks);
+            self.glue = false;
+            self.write(token, token.text, true);
+            self.printer.breaks = breaks;
+        }
+        let line_comment = !token.text.starts_with("/*");
+        if line_comment || self.tokens.get(i + 1).is_some_and(|t| t.newline) {
+            self.printer.line(self.printer.indent);
+        }
+    }
+
+    fn write(&mut self, token: Token, text: &str, spaced: bool) {
+        let space = spaced
+            && !self.glue
+            && match token.kind {
+                // `count(*)` stays a call, `IN (...)` gets its space
+                Kind::Open => match self.last {
+                    Some((Kind::Word | Kind::Literal | Kind::Close, keyword)) => {
+                        keyword || token.space
+                    }
+                    _ => true,
+                },
+                _ => true,
+            };
+        self.printer.push(text, space);
+        self.glue = false;
+        let upper = token.text.to_ascii_uppercase();
+        let keyword = token.kind == Kind::Word
+            && self.keywords.binary_search(&upper.as_str()).is_ok()
+            && self.functions.binary_search(&upper.as_str()).is_err()
+            && !TYPE_NAMES.contains(&upper.as_str());
+        self.last = Some((token.kind, keyword));
+    }
+}
+
+/// Formats the SQL files at `paths` in place for `--format-sql`; `-` formats stdin to stdout.
+///
+/// Scripts are read in `dialect`, else in the dialect of `--connection`/`--user` when one is
+/// given, else as generic SQL. The layout comes from the `[format]` section of the config. A
+/// script the formatter would change beyond its layout (see [`same_tokens`]) is left alone.
+pub fn main_format_sql(options: &Options, dialect: Option<Dialect>, paths: &[String]) -> ExitCode {
+    let (connection_dialect, settings) = if options.connection.is_some() || options.user.is_some() {
+        match headless::load_state(options) {
+            Ok(state) => {
+                let settings = state.config.lock().unwrap().format.clone();
+                (state.dialect(), settings)
+            }
+            Err(code) => return code,
+        }
+    } else {
+        match shared::get_config() {
+            Ok(config) => (Dialect::Generic, config.format),
+            Err(e) => {
+                eprintln!("{}", e);
+                return ExitCode::Config;
+            }
+        }
+    };
+    let dialect = dialect.unwrap_or(connection_dialect);
+    let refuse = |name: &str| {
+        eprintln!(
+            "Cannot format {}: the result would not read the same as {:?} SQL, left as it is. \
+             Pass --dialect to read it as MySQL, PostgreSQL or SQLite.",
+            name, dialect
+        );
+    };
+    let mut code = ExitCode::Ok;
+    for path in paths {
+        if path == "-" {
+            let mut script = String::new();
+            let written = io::stdin().read_to_string(&mut script).and_then(|_| {
+                let formatted = format_sql(&script, dialect, &settings);
+                if same_tokens(&script, &formatted, dialect) {
+                    io::stdout().write_all(formatted.as_bytes())
+                } else {
+                    refuse("stdin");
+                    code = ExitCode::Usage;
+                    io::stdout().write_all(script.as_bytes())
+                }
+            });
+            if let Err(e) = written {
+                eprintln!("Cannot format stdin: {}", e);
+                code = ExitCode::Usage;
+            }
+            continue;
+        }
+        let script = match shared::read_file(path) {
+            Ok(script) => script,
+            Err(e) => {
+                eprintln!("Cannot read {}: {}", path, e);
+                code = ExitCode::Usage;
+                continue;
+            }
+        };
+        let formatted = format_sql(&script, dialect, &settings);
+        if formatted == script {
+            continue;
+        }
+        if !same_tokens(&script, &formatted, dialect) {
+            refuse(path);
+            code = ExitCode::Usage;
+            continue;
+        }
+        match shared::write_file(path, &formatted) {
+            Ok(()) => eprintln!("Formatted {}", path),
+            Err(e) => {
+                eprintln!("Cannot write {}: {}", path, e);
+                code = ExitCode::Usage;
+            }
+        }
+    }
+    code
+}
+
+// tests
+
+#[allow(dead_code)]
+fn format(script: &str, dialect: Dialect) -> String {
+    format_sql(script, dialect, &FormatSettings::default())
+}
+
+#[test]
+fn test_format_query() {
+    let script = "select u.id, count(*) as n from users u left join orders o on o.user_id = u.id and o.total > -1 where u.active = true and u.created between '2024-01-01' and now() group by u.id order by n desc limit 10";
+    let expected = "\
+SELECT
+  u.id,
+  count(*) AS n
+FROM
+  users u
+  LEFT JOIN orders o ON o.user_id = u.id
+  AND o.total > -1
+WHERE
+  u.active = TRUE
+  AND u.created BETWEEN '2024-01-01' AND now()
+GROUP BY
+  u.id
+ORDER BY
+  n DESC
+LIMIT 10";
+    assert_eq!(format(script, Dialect::Generic), expected);
+    assert_eq!(format(expected, Dialect::Generic), expected);
+}
+
+#[test]
+fn test_format_subqueries_and_case() {
+    let script = "with recent as (select * from orders where created > now() - interval '1 day') select c.name, case when c.vip then 'vip' else 'regular' end as kind from customers c where c.id in (select customer_id from recent) union all select 'x', null";
+    let expected = "\
+WITH recent AS (
+  SELECT
+    *
+  FROM
+    orders
+  WHERE
+    created > now() - INTERVAL '1 day'
+)
+SELECT
+  c.name,
+  CASE
+    WHEN c.vip THEN 'vip'
+    ELSE 'regular'
+  END AS kind
+FROM
+  customers c
+WHERE
+  c.id IN (
+    SELECT
+      customer_id
+    FROM
+      recent
+  )
+UNION ALL
+SELECT
+  'x',
+  NULL";
+    assert_eq!(format(script, Dialect::Postgres), expected);
+    assert_eq!(format(expected, Dialect::Postgres), expected);
+}
+
+#[test]
+fn test_format_keeps_literals_and_comments() {
+    let script = "select x::text, 'it''s -- no comment', $fn$ select  1 $fn$ from t; -- done\n-- next\nupdate t set a = 1, b = E'\\'' where id = $1 returning *\n";
+    let expected = "\
+SELECT
+  x::TEXT,
+  'it''s -- no comment',
+  $fn$ select  1 $fn$
+FROM
+  t; -- done
+
+-- next
+UPDATE t
+SET
+  a = 1,
+  b = E'\\''
+WHERE
+  id = $1
+RETURNING
+  *
+";
+    assert_eq!(format(script, Dialect::Postgres), expected);
+    assert_eq!(format(expected, Dialect::Postgres), expected);
+}
+
+#[test]
+fn test_format_mysql_script() {
+    let script = "DELIMITER //\nCREATE PROCEDURE p() BEGIN SELECT 1; END//\nDELIMITER ;\nselect `order`, @x := -1 from `user` # note\n where a is distinct from b and c between 1 and 2 for update";
+    let expected = "\
+DELIMITER //
+CREATE PROCEDURE p() BEGIN SELECT 1;
+END//
+
+DELIMITER ;
+SELECT
+  `order`,
+  @x := -1
+FROM
+  `user` # note
+WHERE
+  a IS DISTINCT FROM b
+  AND c BETWEEN 1 AND 2
+FOR UPDATE";
+    assert_eq!(format(script, Dialect::MySql), expected);
+    assert_eq!(format(expected, Dialect::MySql), expected);
+}
+
+#[test]
+fn test_format_refuses_generic_output_that_reads_differently() {
+    for script in [
+        "select a # note\n, b from t",
+        "select 'it\\'s from here' from t",
+    ] {
+        let formatted = format(script, Dialect::Generic);
+        assert!(
+            !same_tokens(script, &formatted, Dialect::Generic),
+            "{}",
+            formatted
+        );
+        let formatted = format(script, Dialect::MySql);
+        assert!(
+            same_tokens(script, &formatted, Dialect::MySql),
+            "{}",
+            formatted
+        );
+    }
+    let script = "select a, b from t where x = 'y' -- z\n";
+    let formatted = format(script, Dialect::Generic);
+    assert_ne!(formatted, script);
+    assert!(same_tokens(script, &formatted, Dialect::Generic));
+}
+
+#[test]
+fn test_format_settings() {
+    let script = "SELECT id, Name FROM user WHERE id IN(1,2)";
+    let settings = FormatSettings {
+        indent: 4,
+        keyword_case: KeywordCase::Lower,
+    };
+    assert_eq!(
+        format_sql(script, Dialect::Sqlite, &settings),
+        "select\n    id,\n    Name\nfrom\n    user\nwhere\n    id in (1, 2)"
+    );
+    let settings = FormatSettings {
+        indent: 2,
+        keyword_case: KeywordCase::Preserve,
+    };
+    assert_eq!(
+        format_sql("select a From t", Dialect::Generic, &settings),
+        "select\n  a\nFrom\n  t"
+    );
+    assert_eq!(format("  \n", Dialect::Generic), "  \n");
+}
@@ -0,0 +1,350 @@
+// Copyright (c) 2025 mcpeaps_HD
+//
+// This software is released under the MIT License.
+// https://opensource.org/licenses/MIT
+use crate::formatter;
+use crate::headless::{self, ExitCode, Options};
+use crate::history::{self, HistoryEntry};
+use crate::shared::{self, AppState, ColumnMeta, SchemaTable, SimpleSqlError, Table, Value};
+use crate::splitter;
+use log::{info, warn};
+use rustyline::DefaultEditor;
+use rustyline::error::ReadlineError;
+use std::io::{self, Write};
+use std::time::Instant;
+
+const HELP: &str = "\
+Statements are sent when a line ends with ';'.
+  \\c [DB [USER]]  show or change the database and credential
+  \\dt             list tables and views
+  \\d TABLE        describe a table
+  \\x              toggle expanded output
+  \\?              show this help
+  \\q              quit (or Ctrl+D)
+Ctrl+C discards the statement being typed.";
+
+/// A backslash command typed at the prompt.
+#[derive(Clone, Debug, PartialEq)]
+enum MetaCommand {
+    Connect {
+        db: Option<String>,
+        user: Option<String>,
+    },
+    ListTables,
+    Describe(String),
+    ToggleExpanded,
+    Help,
+    Quit,
+    Unknown(String),
+}
+impl MetaCommand {
+    fn parse(line: &str) -> Self {
+        let mut words = line.split_whitespace();
+        let command = words.next().unwrap_or_default();
+        let mut arg = || words.next().map(str::to_string);
+        match command {
+            "\\c" | "\\connect" => MetaCommand::Connect {
+                db: arg(),
+                user: arg(),
+            },
+            "\\dt" => MetaCommand::ListTables,
+            "\\d" => match arg() {
+                Some(table) => MetaCommand::Describe(table),
+                None => MetaCommand::ListTables,
+            },
+            "\\x" => MetaCommand::ToggleExpanded,
+            "\\?" | "\\h" | "\\help" => MetaCommand::Help,
+            "\\q" | "\\quit" => MetaCommand::Quit,
+            other => MetaCommand::Unknown(other.to_string()),
+        }
+    }
+}
+
+struct Repl {
+    state: AppState,
+    /// One record per block instead of a table, toggled with `\x`.
+    expanded: bool,
+}
+
+/// Line-oriented prompt for terminals that cannot show the TUI.
+pub fn main_repl(options: &Options) -> ExitCode {
+    let state = match headless::load_state(options) {
+        Ok(state) => state,
+        Err(code) => return code,
+    };
+    let mut editor = match DefaultEditor::new() {
+        Ok(editor) => editor,
+        Err(e) => {
+            eprintln!("Cannot start the line editor: {}", e);
+            return ExitCode::Usage;
+        }
+    };
+    let history_path = shared::get_repl_history_path().ok();
+    if let Some(path) = &history_path
+        && editor.load_history(path).is_err()
+    {
+        info!("No REPL history at {}", path);
+    }
+
+    let mut repl = Repl {
+        state,
+        expanded: false,
+    };
+    println!(
+        "simplesql {} - connected as {} to {}. Type \\? for help.",
+        env!("CARGO_PKG_VERSION"),
+        repl.state.user.name,
+        repl.location()
+    );
+    let mut buffer = String::new();
+    loop {
+        let prompt = if buffer.is_empty() {
+            format!("{}=> ", repl.location())
+        } else {
+            format!("{}-> ", repl.location())
+        };
+        let line = match editor.readline(&prompt) {
+            Ok(line) => line,
+            Err(ReadlineError::Interrupted) => {
+                buffer.clear();
+                continue;
+            }
+            Err(ReadlineError::Eof) => break,
+            Err(e) => {
+                eprintln!("{}", e);
+                break;
+            }
+        };
+        if buffer.is_empty() && line.trim_start().starts_with('\\') {
+            let _ = editor.add_history_entry(line.trim());
+            match MetaCommand::parse(line.trim()) {
+                MetaCommand::Quit => break,
+                command => repl.meta_command(command),
+            }
+            continue;
+        }
+        if buffer.is_empty() && line.trim().is_empty() {
+            continue;
+        }
+        if !buffer.is_empty() {
+            buffer.push('\n');
+        }
+        buffer.push_str(&line);
+        if splitter::is_complete(&buffer, repl.state.dialect()) {
+            let _ = editor.add_history_entry(buffer.as_str());
+            repl.execute(std::mem::take(&mut buffer));
+        }
+    }
+    if let Some(path) = &history_path
+        && let Err(e) = editor.save_history(path)
+    {
+        warn!("Cannot save the REPL history to {}: {}", path, e);
+    }
+    ExitCode::Ok
+}
+
+impl Repl {
+    /// Database name for the prompt, or the connection when no database is selected.
+    fn location(&self) -> &str {
+        if self.state.db.is_empty() {
+            &self.state.user.connection
+        } else {
+            &self.state.db
+        }
+    }
+
+    fn execute(&mut self, sql: String) {
+        let entry = HistoryEntry::start(&self.state, &sql);
+        self.state.sql_query = sql;
+        let started = Instant::now();
+        let outcome = shared::run_query(&mut self.state);
+        let entry = entry.finish(&self.state, &outcome, started.elapsed());
+        if let Err(e) = history::record(&entry) {
+            warn!("Cannot save the query history: {}", e);
+        }
+        let results = self.state.results.lock().unwrap().clone();
+        for table in &results {
+            self.print_result(table);
+        }
+        match outcome {
+            Err(SimpleSqlError::Database(_)) if !results.is_empty() => {}
+            Err(e) => eprintln!("ERROR:  {}", e),
+            Ok(()) => {}
+        }
+    }
+
+    fn print_result(&self, table: &Table) {
+        if let Some(error) = &table.error {
+            eprintln!("ERROR:  {}", error);
+            return;
+        }
+        if table.columns.is_empty() {
+            println!("{}", table.summary());
+            return;
+        }
+        let stdout = io::stdout();
+        let mut out = stdout.lock();
+        let written = if self.expanded {
+            formatter::write_expanded(&mut out, table)
+        } else {
+            formatter::write_table(&mut out, table)
+        };
+        let count = table.rows.len();
+        let written = written.and_then(|()| {
+            writeln!(
+                out,
+                "({} row{})\n",
+                count,
+                if count == 1 { "" } else { "s" }
+            )
+        });
+        if let Err(e) = written {
+            eprintln!("Cannot write results: {}", e);
+        }
+    }
+
+    fn meta_command(&mut self, command: MetaCommand) {
+        match command {
+            MetaCommand::Connect { db: None, .. } => println!(
+                "Connected to {} as {} ({}).",
+                self.location(),
+                self.state.user.name,
+                self.state.user.connection
+            ),
+            MetaCommand::Connect { db: Some(db), user } => self.connect(db, user),
+            MetaCommand::ListTables => match shared::describe_schema(&self.state) {
+                Ok(tables) => self.print_result(&tables_overview(&tables)),
+                Err(e) => eprintln!("ERROR:  {}", e),
+            },
+            MetaCommand::Describe(name) => match shared::describe_schema(&self.state) {
+                Ok(tables) => match shared::find_table(&tables, &name) {
+                    Some(table) => {
+                        println!("{} \"{}.{}\"", kind(table), table.schema, table.name);
+                        self.print_result(&columns_overview(&table.columns));
+                    }
+                    None => eprintln!("Did not find any relation named \"{}\".", name),
+                },
+                Err(e) => eprintln!("ERROR:  {}", e),
+            },
+            MetaCommand::ToggleExpanded => {
+                self.expanded = !self.expanded;
+                println!(
+                    "Expanded display is {}.",
+                    if self.expanded { "on" } else { "off" }
+                );
+            }
+            MetaCommand::Help => println!("{}", HELP),
+            MetaCommand::Quit => {}
+            MetaCommand::Unknown(command) => {
+                eprintln!("Invalid command {}. Try \\? for help.", command)
+            }
+        }
+    }
+
+    /// Switches to `db` ("-" keeps the current one) and optionally another credential.
+    fn connect(&mut self, db: String, user: Option<String>) {
+        if let Some(user) = user {
+            let credential = {
+                let config = self.state.config.lock().unwrap();
+                headless::select_credential(&config, None, Some(&user))
+            };
+            match credential {
+                Ok(credential) => {
+                    self.state.disconnect();
+                    self.state.user = credential;
+                }
+                Err(message) => {
+                    eprintln!("{}", message);
+                    return;
+                }
+            }
+        }
+        if db != "-" {
+            self.state.set_db(db);
+        }
+        println!(
+            "You are now connected to {} as {}.",
+            self.location(),
+            self.state.user.name
+        );
+    }
+}
+
+fn kind(table: &SchemaTable) -> &'static str {
+    if table.is_view { "view" } else { "table" }
+}
+
+fn text_table(headers: &[&str], rows: Vec<Vec<String>>) -> Table {
+    Table {
+        columns: headers
+            .iter()
+            .map(|name| ColumnMeta {
+                name: name.to_string(),
+                ..ColumnMeta::default()
+            })
+            .collect(),
+        rows: rows
+            .into_iter()
+            .map(|row| row.into_iter().map(Value::Text).collect())
+            .collect(),
+        ..Table::default()
+    }
+}
+
+fn tables_overview(tables: &[SchemaTable]) -> Table {
+    text_table(
+        &["Schema", "Name", "Type"],
+        tables
+            .iter()
+            .map(|t| vec![t.schema.clone(), t.name.clone(), kind(t).to_string()])
+            .collect(),
+    )
+}
+
+fn columns_overview(columns: &[ColumnMeta]) -> Table {
+    text_table(
+        &["Column", "Type", "Nullable"],
+        columns
+            .iter()
+            .map(|c| {
+                let nullable = match c.nullable {
+                    Some(true) => "yes",
+                    Some(false) => "no",
+                    None => "",
+                };
+                vec![c.name.clone(), c.type_name.clone(), nullable.to_string()]
+            })
+            .collect(),
+    )
+}
+
+// tests
+
+#[test]
+fn test_parse_meta_commands() {
+    assert_eq!(MetaCommand::parse("\\dt"), MetaCommand::ListTables);
+    assert_eq!(
+        MetaCommand::parse("\\d  users"),
+        MetaCommand::Describe("users".to_string())
+    );
+    assert_eq!(
+        MetaCommand::parse("\\c app postgresql_default"),
+        MetaCommand::Connect {
+            db: Some("app".to_string()),
+            user: Some("postgresql_default".to_string())
+        }
+    );
+    assert_eq!(
+        MetaCommand::parse("\\c"),
+        MetaCommand::Connect {
+            db: None,
+            user: None
+        }
+    );
+    assert_eq!(MetaCommand::parse("\\x"), MetaCommand::ToggleExpanded);
+    assert_eq!(MetaCommand::parse("\\q"), MetaCommand::Quit);
+    assert_eq!(
+        MetaCommand::parse("\\z"),
+        MetaCommand::Unknown("\\z".to_string())
+    );
+}
@@ -0,0 +1,785 @@
+// Copyright (c) 2025 mcpeaps_HD
+//
+// This software is released under the MIT License.
+// https://opensource.org/licenses/MIT
+use crate::formatter;
+use crate::shared::{Dialect, SchemaTable};
+use std::collections::HashSet;
+
+/// Rows a "SELECT top 100" action fetches.
+pub const SELECT_LIMIT: usize = 100;
+
+/// Words reserved in MySQL, PostgreSQL or SQLite, sorted; names like these need quotes.
+const RESERVED_WORDS: &[&str] = &[
+    "accessible",
+    "add",
+    "all",
+    "alter",
+    "analyse",
+    "analyze",
+    "and",
+    "any",
+    "array",
+    "as",
+    "asc",
+    "asymmetric",
+    "authorization",
+    "before",
+    "between",
+    "bigint",
+    "binary",
+    "blob",
+    "both",
+    "by",
+    "call",
+    "cascade",
+    "case",
+    "cast",
+    "change",
+    "char",
+    "character",
+    "check",
+    "collate",
+    "collation",
+    "column",
+    "concurrently",
+    "condition",
+    "constraint",
+    "continue",
+    "convert",
+    "create",
+    "cross",
+    "current_catalog",
+    "current_date",
+    "current_role",
+    "current_schema",
+    "current_time",
+    "current_timestamp",
+    "current_user",
+    "cursor",
+    "database",
+    "databases",
+    "dec",
+    "decimal",
+    "declare",
+    "default",
+    "deferrable",
+    "delayed",
+    "delete",
+    "desc",
+    "describe",
+    "deterministic",
+    "distinct",
+    "distinctrow",
+    "div",
+    "do",
+    "double",
+    "drop",
+    "dual",
+    "each",
+    "else",
+    "elseif",
+    "enclosed",
+    "end",
+    "escape",
+    "escaped",
+    "except",
+    "exists",
+    "exit",
+    "explain",
+    "false",
+    "fetch",
+    "float",
+    "for",
+    "force",
+    "foreign",
+    "freeze",
+    "from",
+    "full",
+    "fulltext",
+    "function",
+    "generated",
+    "glob",
+    "grant",
+    "group",
+    "groups",
+    "having",
+    "high_priority",
+    "if",
+    "ignore",
+    "ilike",
+    "in",
+    "index",
+    "infile",
+    "initially",
+    "inner",
+    "inout",
+    "insert",
+    "int",
+    "integer",
+    "intersect",
+    "interval",
+    "into",
+    "is",
+    "isnull",
+    "iterate",
+    "join",
+    "key",
+    "keys",
+    "kill",
+    "lateral",
+    "leading",
+    "leave",
+    "left",
+    "like",
+    "limit",
+    "linear",
+    "lines",
+    "load",
+    "localtime",
+    "localtimestamp",
+    "lock",
+    "long",
+    "loop",
+    "low_priority",
+    "match",
+    "mod",
+    "modifies",
+    "natural",
+    "not",
+    "notnull",
+    "null",
+    "numeric",
+    "offset",
+    "on",
+    "only",
+    "optimize",
+    "option",
+    "optionally",
+    "or",
+    "order",
+    "out",
+    "outer",
+    "outfile",
+    "over",
+    "overlaps",
+    "partition",
+    "placing",
+    "precision",
+    "primary",
+    "procedure",
+    "purge",
+    "range",
+    "rank",
+    "read",
+    "reads",
+    "real",
+    "recursive",
+    "references",
+    "regexp",
+    "release",
+    "rename",
+    "repeat",
+    "replace",
+    "require",
+    "resignal",
+    "restrict",
+    "return",
+    "returning",
+    "revoke",
+    "right",
+    "rlike",
+    "row",
+    "rows",
+    "schema",
+    "schemas",
+    "select",
+    "sensitive",
+    "separator",
+    "session_user",
+    "set",
+    "show",
+    "signal",
+    "similar",
+    "smallint",
+    "some",
+    "spatial",
+    "specific",
+    "sql",
+    "sqlexception",
+    "sqlstate",
+    "sqlwarning",
+    "starting",
+    "stored",
+    "straight_join",
+    "symmetric",
+    "system_user",
+    "table",
+    "tablesample",
+    "terminated",
+    "then",
+    "tinyint",
+    "to",
+    "trailing",
+    "trigger",
+    "true",
+    "undo",
+    "union",
+    "unique",
+    "unlock",
+    "unsigned",
+    "update",
+    "usage",
+    "use",
+    "user",
+    "using",
+    "values",
+    "varchar",
+    "variadic",
+    "varying",
+    "verbose",
+    "virtual",
+    "when",
+    "where",
+    "while",
+    "window",
+    "with",
+    "write",
+    "xor",
+    "zerofill",
+];
+
+/// The part of a table shown below it in the tree.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Group {
+    Columns,
+    Indexes,
+    ForeignKeys,
+}
+
+/// What a row of the tree stands for; tables are indexes into [`SchemaBrowser::tables`].
+#[derive(Clone, Debug, PartialEq)]
+pub enum Node {
+    Database(String),
+    Schema(String),
+    Table(usize),
+    Group(usize, Group),
+    Column(usize, usize),
+    Index(usize, usize),
+    ForeignKey(usize, usize),
+}
+
+/// A visible row of the tree.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TreeItem {
+    pub depth: usize,
+    pub node: Node,
+    pub label: String,
+    /// Type, kind or target shown dimmed after the label.
+    pub detail: String,
+    pub expandable: bool,
+    pub expanded: bool,
+}
+
+/// The databases, schemas and tables of the active credential as a collapsible tree.
+#[derive(Clone, Debug, Default)]
+pub struct SchemaBrowser {
+    pub databases: Vec<String>,
+    /// Database the tables belong to; only this one can be expanded.
+    pub current_db: String,
+    pub tables: Vec<SchemaTable>,
+    /// Why the tree could not be loaded.
+    pub error: Option<String>,
+    pub selected: usize,
+    pub offset: usize,
+    expanded: HashSet<String>,
+}
+impl SchemaBrowser {
+    /// Replaces the content, keeping expanded nodes and the selection where possible.
+    ///
+    /// On first load the current database and its schemas are expanded.
+    pub fn load(&mut self, databases: Vec<String>, current_db: String, tables: Vec<SchemaTable>) {
+        let first_load = self.expanded.is_empty();
+        self.databases = databases;
+        self.current_db = current_db;
+        self.tables = tables;
+        self.error = None;
+        if first_load {
+            self.expanded.insert(database_key(&self.current_db));
+            for schema in self.schemas() {
+                self.expanded.insert(schema_key(&self.current_db, &schema));
+            }
+        }
+        self.selected = self.selected.min(self.items().len().saturating_sub(1));
+    }
+
+    /// Schemas of the tables in order of appearance.
+    fn schemas(&self) -> Vec<String> {
+        let mut schemas: Vec<String> = Vec::new();
+        for table in &self.tables {
+            if !schemas.contains(&table.schema) {
+                schemas.push(table.schema.clone());
+            }
+        }
+        schemas
+    }
+
+    /// Stable key of `node` for remembering whether it is expanded.
+    fn key(&self, node: &Node) -> String {
+        let table_key = |idx: usize| {
+            let table = &self.tables[idx];
+            format!(
+                "{}/{}",
+                schema_key(&self.current_db, &table.schema),
+                table.name
+            )
+        };
+        match node {
+            Node::Database(name) => database_key(name),
+            Node::Schema(schema) => schema_key(&self.current_db, schema),
+            Node::Table(idx) => table_key(*idx),
+            Node::Group(idx, group) => format!("{}/{:?}", table_key(*idx), group),
+            Node::Column(idx, item) | Node::Index(idx, item) | Node::ForeignKey(idx, item) => {
+                format!("{}/{}", table_key(*idx), item)
+            }
+        }
+    }
+
+    fn is_expanded(&self, node: &Node) -> bool {
+        self.expanded.contains(&self.key(node))
+    }
+
+    /// The rows of the tree that are not hidden in a collapsed node.
+    pub fn items(&self) -> Vec<TreeItem> {
+        let mut items = Vec::new();
+        let mut databases = self.databases.clone();
+        if !databases.contains(&self.current_db) {
+            databases.insert(0, self.current_db.clone());
+        }
+        for database in databases {
+            let node = Node::Database(database.clone());
+            let current = database == self.current_db;
+            let expanded = current && self.is_expanded(&node);
+            items.push(TreeItem {
+                depth: 0,
+                label: if database.is_empty() {
+                    "(default database)".to_string()
+                } else {
+                    database
+                },
+                detail: if current { "current" } else { "" }.to_string(),
+                expandable: current,
+                expanded,
+                node,
+            });
+            if expanded {
+                for schema in self.schemas() {
+                    self.push_schema(&mut items, schema);
+                }
+            }
+        }
+        items
+    }
+
+    fn push_schema(&self, items: &mut Vec<TreeItem>, schema: String) {
+        let node = Node::Schema(schema.clone());
+        let expanded = self.is_expanded(&node);
+        let count = self.tables.iter().filter(|t| t.schema == schema).count();
+        items.push(TreeItem {
+            depth: 1,
+            label: schema.clone(),
+            detail: format!("{} object(s)", count),
+            expandable: true,
+            expanded,
+            node,
+        });
+        if !expanded {
+            return;
+        }
+        for (idx, table) in self.tables.iter().enumerate() {
+            if table.schema != schema {
+                continue;
+            }
+            let node = Node::Table(idx);
+            let expanded = self.is_expanded(&node);
+            items.push(TreeItem {
+                depth: 2,
+                label: table.name.clone(),
+                detail: if table.is_view { "view" } else { "table" }.to_string(),
+                expandable: true,
+                expanded,
+                node,
+            });
+            if expanded {
+                self.push_groups(items, idx);
+            }
+        }
+    }
+
+    fn push_groups(&self, items: &mut Vec<TreeItem>, idx: usize) {
+        let table = &self.tables[idx];
+        let groups = [
+            (Group::Columns, "Columns", table.columns.len()),
+            (Group::Indexes, "Indexes", table.indexes.len()),
+            (Group::ForeignKeys, "Foreign Keys", table.foreign_keys.len()),
+        ];
+        for (group, label, count) in groups {
+            if count == 0 && group != Group::Columns {
+                continue;
+            }
+            let node = Node::Group(idx, group);
+            let expanded = self.is_expanded(&node);
+            items.push(TreeItem {
+                depth: 3,
+                label: label.to_string(),
+                detail: count.to_string(),
+                expandable: count > 0,
+                expanded,
+                node,
+            });
+            if !expanded {
+                continue;
+            }
+            let children: Vec<(Node, String, String)> = match group {
+                Group::Columns => table
+                    .columns
+                    .iter()
+                    .enumerate()
+                    .map(|(item, c)| {
+                        let not_null = if c.nullable == Some(false) {
+                            " NOT NULL"
+                        } else {
+                            ""
+                        };
+                        (
+                            Node::Column(idx, item),
+                            c.name.clone(),
+                            format!("{}{}", c.type_name, not_null),
+                        )
+                    })
+                    .collect(),
+                Group::Indexes => table
+                    .indexes
+                    .iter()
+                    .enumerate()
+                    .map(|(item, index)| {
+                        let unique = if index.unique { "UNIQUE " } else { "" };
+                        (
+                            Node::Index(idx, item),
+                            index.name.clone(),
+                            format!("{}({})", unique, index.columns.join(", ")),
+                        )
+                    })
+                    .collect(),
+                Group::ForeignKeys => table
+                    .foreign_keys
+                    .iter()
+                    .enumerate()
+                    .map(|(item, key)| {
+                        (
+                            Node::ForeignKey(idx, item),
+                            key.name.clone(),
+                            format!(
+                                "({}) -> {}({})",
+                                key.columns.join(", "),
+                                key.referenced_table,
+                                key.referenced_columns.join(", ")
+                            ),
+                        )
+                    })
+                    .collect(),
+            };
+            items.extend(children.into_iter().map(|(node, label, detail)| TreeItem {
+                depth: 4,
+                node,
+                label,
+                detail,
+                expandable: false,
+                expanded: false,
+            }));
+        }
+    }
+
+    pub fn selected_item(&self) -> Option<TreeItem> {
+        self.items().into_iter().nth(self.selected)
+    }
+
+    /// Moves the selection `step` rows, stopping at the ends.
+    pub fn move_selection(&mut self, step: isize) {
+        let count = self.items().len();
+        self.selected = self
+            .selected
+            .saturating_add_signed(step)
+            .min(count.saturating_sub(1));
+    }
+
+    /// Expands or collapses the selected node.
+    pub fn toggle(&mut self) {
+        if let Some(item) = self.selected_item()
+            && item.expandable
+        {
+            self.set_expanded(&item.node, !item.expanded);
+        }
+    }
+
+    /// Expands the selected node, or moves into it when it is expanded already.
+    pub fn expand(&mut self) {
+        match self.selected_item() {
+            Some(item) if item.expandable && !item.expanded => self.set_expanded(&item.node, true),
+            Some(item) if item.expanded => self.move_selection(1),
+            _ => {}
+        }
+    }
+
+    /// Collapses the selected node, or moves to its parent when it is collapsed.
+    pub fn collapse(&mut self) {
+        let items = self.items();
+        let Some(item) = items.get(self.selected) else {
+            return;
+        };
+        if item.expanded {
+            self.set_expanded(&item.node.clone(), false);
+        } else if let Some(parent) = items[..self.selected]
+            .iter()
+            .rposition(|parent| parent.depth < item.depth)
+        {
+            self.selected = parent;
+        }
+    }
+
+    fn set_expanded(&mut self, node: &Node, expanded: bool) {
+        let key = self.key(node);
+        if expanded {
+            self.expanded.insert(key);
+        } else {
+            self.expanded.remove(&key);
+        }
+    }
+
+    /// Name of the selected object, as typed into a query.
+    pub fn insert_text(&self, node: &Node, dialect: Dialect) -> String {
+        match node {
+            Node::Database(name) | Node::Schema(name) => identifier(name, dialect),
+            Node::Table(idx) | Node::Group(idx, _) => qualified_name(&self.tables[*idx], dialect),
+            Node::Column(idx, item) => identifier(&self.tables[*idx].columns[*item].name, dialect),
+            Node::Index(idx, item) => identifier(&self.tables[*idx].indexes[*item].name, dialect),
+            Node::ForeignKey(idx, item) => {
+                identifier(&self.tables[*idx].foreign_keys[*item].name, dialect)
+            }
+        }
+    }
+
+    /// `SELECT * FROM table LIMIT 100` for the table the selected node belongs to.
+    pub fn select_sql(&self, node: &Node, dialect: Dialect) -> Option<String> {
+        let idx = match node {
+            Node::Table(idx)
+            | Node::Group(idx, _)
+            | Node::Column(idx, _)
+            | Node::Index(idx, _)
+            | Node::ForeignKey(idx, _) => *idx,
+            Node::Database(_) | Node::Schema(_) => return None,
+        };
+        Some(format!(
+            "SELECT * FROM {} LIMIT {}",
+            qualified_name(&self.tables[idx], dialect),
+            SELECT_LIMIT
+        ))
+    }
+}
+
+fn database_key(name: &str) -> String {
+    format!("db:{}", name)
+}
+
+fn schema_key(db: &str, schema: &str) -> String {
+    format!("db:{}/{}", db, schema)
+}
+
+/// `name` as is when it needs no quotes, quoted otherwise: reserved words and names that are
+/// not plain lower case.
+pub fn identifier(name: &str, dialect: Dialect) -> String {
+    let plain = name.starts_with(|c: char| c.is_ascii_lowercase() || c == '_')
+        && name
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_')
+        && RESERVED_WORDS.binary_search(&name).is_err();
+    if plain {
+        name.to_string()
+    } else {
+        formatter::quote_identifier(name, dialect)
+    }
+}
+
+/// The table name, qualified unless the schema is the one queries use by default.
+pub fn qualified_name(table: &SchemaTable, dialect: Dialect) -> String {
+    let default_schema = match dialect {
+        Dialect::Postgres => table.schema == "public",
+        Dialect::Sqlite => table.schema == "main",
+        // only tables of the selected database are listed
+        Dialect::MySql => true,
+        Dialect::Generic => table.schema.is_empty(),
+    };
+    if default_schema {
+        identifier(&table.name, dialect)
+    } else {
+        format!(
+            "{}.{}",
+            identifier(&table.schema, dialect),
+            identifier(&table.name, dialect)
+        )
+    }
+}
+
+// tests
+
+#[allow(dead_code)]
+fn test_browser() -> SchemaBrowser {
+    use crate::shared::{ColumnMeta, ForeignKey, SchemaIndex};
+    let column = |name: &str, type_name: &str, nullable| ColumnMeta {
+        name: name.to_string(),
+        type_name: type_name.to_string(),
+        nullable: Some(nullable),
+    };
+    let mut browser = SchemaBrowser::default();
+    browser.load(
+        vec!["app".to_string(), "postgres".to_string()],
+        "app".to_string(),
+        vec![
+            SchemaTable {
+                schema: "public".to_string(),
+                name: "pets".to_string(),
+                columns: vec![
+                    column("id", "int4", false),
+                    column("owner_id", "int4", true),
+                ],
+                indexes: vec![SchemaIndex {
+                    name: "pets_pkey".to_string(),
+                    columns: vec!["id".to_string()],
+                    unique: true,
+                }],
+                foreign_keys: vec![ForeignKey {
+                    name: "pets_owner_fk".to_string(),
+                    columns: vec!["owner_id".to_string()],
+                    referenced_schema: "audit".to_string(),
+                    referenced_table: "Owners".to_string(),
+                    referenced_columns: vec!["id".to_string()],
+                }],
+                ..SchemaTable::default()
+            },
+            SchemaTable {
+                schema: "audit".to_string(),
+                name: "Owners".to_string(),
+                is_view: true,
+                columns: vec![column("id", "int4", false)],
+                ..SchemaTable::default()
+            },
+        ],
+    );
+    browser
+}
+
+#[allow(dead_code)]
+fn labels(browser: &SchemaBrowser) -> Vec<String> {
+    browser
+        .items()
+        .iter()
+        .map(|item| format!("{}{}", "  ".repeat(item.depth), item.label))
+        .collect()
+}
+
+#[test]
+fn test_tree_expands_current_database_and_schemas() {
+    let browser = test_browser();
+    assert_eq!(
+        labels(&browser),
+        vec![
+            "app",
+            "  public",
+            "    pets",
+            "  audit",
+            "    Owners",
+            "postgres"
+        ]
+    );
+    assert_eq!(browser.items()[0].detail, "current");
+    assert!(!browser.items()[5].expandable);
+}
+
+#[test]
+fn test_tree_expand_and_collapse() {
+    let mut browser = test_browser();
+    browser.selected = 2;
+    browser.expand();
+    assert_eq!(
+        labels(&browser)[2..6],
+        [
+            "    pets",
+            "      Columns",
+            "      Indexes",
+            "      Foreign Keys"
+        ]
+    );
+    browser.move_selection(1);
+    browser.toggle();
+    assert_eq!(browser.items()[4].label, "id");
+    assert_eq!(browser.items()[4].detail, "int4 NOT NULL");
+    browser.collapse();
+    assert_eq!(browser.selected, 3);
+    browser.collapse();
+    assert_eq!(browser.items()[4].label, "Indexes");
+    browser.collapse();
+    assert_eq!(browser.selected, 2);
+    browser.move_selection(-10);
+    assert_eq!(browser.selected, 0);
+    browser.move_selection(100);
+    assert_eq!(browser.selected, browser.items().len() - 1);
+}
+
+#[test]
+fn test_tree_keeps_expanded_nodes_on_reload() {
+    let mut browser = test_browser();
+    browser.selected = 1;
+    browser.toggle();
+    let tables = browser.tables.clone();
+    browser.load(browser.databases.clone(), "app".to_string(), tables);
+    assert_eq!(
+        labels(&browser),
+        vec!["app", "  public", "  audit", "    Owners", "postgres"]
+    );
+}
+
+#[test]
+fn test_insert_text_and_select_sql() {
+    let browser = test_browser();
+    let pets = Node::Table(0);
+    assert_eq!(browser.insert_text(&pets, Dialect::Postgres), "pets");
+    assert_eq!(
+        browser.insert_text(&Node::Table(1), Dialect::Postgres),
+        "audit.\"Owners\""
+    );
+    assert_eq!(
+        browser.insert_text(&Node::Column(0, 1), Dialect::Postgres),
+        "owner_id"
+    );
+    assert_eq!(
+        browser.select_sql(&Node::Column(0, 1), Dialect::Postgres),
+        Some("SELECT * FROM pets LIMIT 100".to_string())
+    );
+    assert_eq!(
+        browser.select_sql(&Node::Table(1), Dialect::MySql),
+        Some("SELECT * FROM `Owners` LIMIT 100".to_string())
+    );
+    assert_eq!(
+        browser.select_sql(&Node::Schema("public".to_string()), Dialect::Postgres),
+        None
+    );
+}
+
+#[test]
+fn test_identifier_quotes_reserved_words() {
+    assert!(RESERVED_WORDS.windows(2).all(|pair| pair[0] < pair[1]));
+    assert_eq!(identifier("order", Dialect::MySql), "`order`");
+    assert_eq!(identifier("user", Dialect::Postgres), "\"user\"");
+    assert_eq!(identifier("group", Dialect::Sqlite), "\"group\"");
+    assert_eq!(identifier("orders", Dialect::Postgres), "orders");
+}
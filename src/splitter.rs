@@ -0,0 +1,349 @@
+// Copyright (c) 2025 mcpeaps_HD
+//
+// This software is released under the MIT License.
+// https://opensource.org/licenses/MIT
+use crate::shared::Dialect;
+
+/// One statement of a script, without its delimiter.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Statement {
+    pub sql: String,
+    /// Byte offset of the statement in the script.
+    pub start: usize,
+    /// Byte offset just past the statement, before the delimiter.
+    pub end: usize,
+}
+
+/// Splits a script into statements.
+///
+/// Delimiters inside strings, quoted identifiers, comments and dollar-quoted bodies are ignored.
+/// For MySQL and generic scripts a `DELIMITER xyz` line switches the delimiter like the
+/// `mysql` command line client does. Chunks that only contain comments are dropped.
+pub fn split_statements(script: &str, dialect: Dialect) -> Vec<Statement> {
+    let mut statements = Vec::new();
+    let mut delimiter = String::from(";");
+    let mut stmt_start = 0;
+    let mut has_content = false;
+    let mut i = 0;
+
+    while i < script.len() {
+        let rest = &script[i..];
+
+        if !has_content
+            && supports_delimiter_command(dialect)
+            && at_line_start(script, i)
+            && let Some((new_delimiter, line_end)) = parse_delimiter_command(rest)
+        {
+            delimiter = new_delimiter;
+            i += line_end;
+            stmt_start = i;
+            continue;
+        }
+
+        if rest.starts_with(delimiter.as_str()) {
+            push_statement(&mut statements, script, stmt_start, i, has_content);
+            i += delimiter.len();
+            stmt_start = i;
+            has_content = false;
+            continue;
+        }
+
+        let c = rest.chars().next().unwrap_or_default();
+        let next = match c {
+            '\'' => {
+                let backslash = dialect == Dialect::MySql || is_escape_string(script, i);
+                skip_quoted(script, i, '\'', backslash)
+            }
+            '"' => skip_quoted(script, i, '"', dialect == Dialect::MySql),
+            '`' if dialect != Dialect::Postgres => skip_quoted(script, i, '`', false),
+            '-' if rest.starts_with("--") => {
+                i = skip_line(script, i);
+                continue;
+            }
+            '#' if dialect == Dialect::MySql => {
+                i = skip_line(script, i);
+                continue;
+            }
+            '/' if rest.starts_with("/*") => {
+                i = skip_block_comment(script, i, dialect == Dialect::Postgres);
+                continue;
+            }
+            '$' if supports_dollar_quotes(dialect) => match dollar_tag(script, i) {
+                Some(tag) => skip_dollar_quoted(script, i, tag),
+                None => i + 1,
+            },
+            _ => i + c.len_utf8(),
+        };
+        if !c.is_whitespace() {
+            has_content = true;
+        }
+        i = next;
+    }
+    push_statement(
+        &mut statements,
+        script,
+        stmt_start,
+        script.len(),
+        has_content,
+    );
+    statements
+}
+
+/// Returns the statement at byte `offset` of the script, e.g. the one under the editor cursor.
+///
+/// When the offset lies between two statements the previous one is used if only its delimiter
+/// and whitespace on the same line separate it from the offset, otherwise the next one.
+pub fn statement_at(script: &str, offset: usize, dialect: Dialect) -> Option<Statement> {
+    let statements = split_statements(script, dialect);
+    if let Some(statement) = statements
+        .iter()
+        .find(|s| s.start <= offset && offset <= s.end)
+    {
+        return Some(statement.clone());
+    }
+    let previous = statements.iter().rev().find(|s| s.end < offset);
+    let next = statements.iter().find(|s| s.start > offset);
+    match (previous, next) {
+        (Some(previous), _) if !script[previous.end..offset].contains('\n') => {
+            Some(previous.clone())
+        }
+        (_, Some(next)) => Some(next.clone()),
+        (previous, None) => previous.cloned(),
+    }
+}
+
+/// Tells whether the script ends with a terminated statement, e.g. to know when a line-based
+/// prompt has read a whole statement. Unclosed strings and comments keep it incomplete.
+pub fn is_complete(script: &str, dialect: Dialect) -> bool {
+    // whatever follows the last statement starts with its delimiter
+    split_statements(script, dialect)
+        .last()
+        .is_some_and(|last| !script[last.end..].trim().is_empty())
+}
+
+fn push_statement(
+    statements: &mut Vec<Statement>,
+    script: &str,
+    start: usize,
+    end: usize,
+    has_content: bool,
+) {
+    if !has_content {
+        return;
+    }
+    let chunk = &script[start..end];
+    let leading = chunk.len() - chunk.trim_start().len();
+    let trailing = chunk.len() - chunk.trim_end().len();
+    statements.push(Statement {
+        sql: chunk.trim().to_string(),
+        start: start + leading,
+        end: end - trailing,
+    });
+}
+
+pub fn supports_delimiter_command(dialect: Dialect) -> bool {
+    matches!(dialect, Dialect::MySql | Dialect::Generic)
+}
+
+pub fn supports_dollar_quotes(dialect: Dialect) -> bool {
+    matches!(dialect, Dialect::Postgres | Dialect::Generic)
+}
+
+pub fn at_line_start(script: &str, pos: usize) -> bool {
+    script[..pos]
+        .chars()
+        .rev()
+        .take_while(|c| *c != '\n')
+        .all(char::is_whitespace)
+}
+
+/// Parses `DELIMITER xyz` and returns the new delimiter and the length of the line.
+pub fn parse_delimiter_command(rest: &str) -> Option<(String, usize)> {
+    let line_end = rest.find('\n').map(|n| n + 1).unwrap_or(rest.len());
+    let line = rest[..line_end].trim();
+    let (keyword, value) = line.split_once(char::is_whitespace)?;
+    if !keyword.eq_ignore_ascii_case("delimiter") {
+        return None;
+    }
+    let value = value.trim();
+    if value.is_empty() {
+        return None;
+    }
+    Some((value.to_string(), line_end))
+}
+
+/// `E'...'` strings use backslash escapes in PostgreSQL.
+fn is_escape_string(script: &str, quote_pos: usize) -> bool {
+    let before = &script[..quote_pos];
+    before.ends_with(['e', 'E'])
+        && !before[..before.len() - 1]
+            .chars()
+            .next_back()
+            .is_some_and(|c| c.is_alphanumeric() || c == '_')
+}
+
+pub fn skip_quoted(script: &str, start: usize, quote: char, backslash_escapes: bool) -> usize {
+    let mut chars = script[start + 1..].char_indices().peekable();
+    while let Some((offset, c)) = chars.next() {
+        if backslash_escapes && c == '\\' {
+            chars.next();
+        } else if c == quote {
+            if chars.peek().is_some_and(|(_, next)| *next == quote) {
+                chars.next();
+            } else {
+                return start + 1 + offset + c.len_utf8();
+            }
+        }
+    }
+    script.len()
+}
+
+fn skip_line(script: &str, start: usize) -> usize {
+    script[start..]
+        .find('\n')
+        .map(|n| start + n + 1)
+        .unwrap_or(script.len())
+}
+
+pub fn skip_block_comment(script: &str, start: usize, nested: bool) -> usize {
+    let mut depth = 0;
+    let mut i = start;
+    while i < script.len() {
+        let rest = &script[i..];
+        if rest.starts_with("/*") && (nested || depth == 0) {
+            depth += 1;
+            i += 2;
+        } else if rest.starts_with("*/") {
+            depth -= 1;
+            i += 2;
+            if depth == 0 {
+                return i;
+            }
+        } else {
+            i += rest.chars().next().map(char::len_utf8).unwrap_or(1);
+        }
+    }
+    script.len()
+}
+
+/// Returns the `$tag$` opening a dollar-quoted string at `start`, if any.
+pub fn dollar_tag(script: &str, start: usize) -> Option<&str> {
+    let preceded_by_identifier = script[..start]
+        .chars()
+        .next_back()
+        .is_some_and(|c| c.is_alphanumeric() || c == '_');
+    if preceded_by_identifier {
+        return None;
+    }
+    let rest = &script[start + 1..];
+    let tag_len = rest.find('$')?;
+    let tag = &rest[..tag_len];
+    let valid = tag.chars().all(|c| c.is_alphanumeric() || c == '_')
+        && !tag.starts_with(|c: char| c.is_ascii_digit());
+    valid.then(|| &script[start..start + tag_len + 2])
+}
+
+pub fn skip_dollar_quoted(script: &str, start: usize, tag: &str) -> usize {
+    let body = start + tag.len();
+    script[body..]
+        .find(tag)
+        .map(|n| body + n + tag.len())
+        .unwrap_or(script.len())
+}
+
+// tests
+
+#[allow(dead_code)]
+fn sql_of(script: &str, dialect: Dialect) -> Vec<String> {
+    split_statements(script, dialect)
+        .into_iter()
+        .map(|s| s.sql)
+        .collect()
+}
+
+#[test]
+fn test_split_simple_script() {
+    let script = "CREATE TABLE t (id int);\nINSERT INTO t VALUES (1);\n\nSELECT * FROM t";
+    assert_eq!(
+        sql_of(script, Dialect::Generic),
+        vec![
+            "CREATE TABLE t (id int)",
+            "INSERT INTO t VALUES (1)",
+            "SELECT * FROM t"
+        ]
+    );
+}
+
+#[test]
+fn test_split_ignores_delimiters_in_strings_and_comments() {
+    let script = "SELECT 'a;b', \"c;d\" -- e;f\n; /* g; */ SELECT `h;i`; # j;k\n";
+    assert_eq!(
+        sql_of(script, Dialect::MySql),
+        vec!["SELECT 'a;b', \"c;d\" -- e;f", "/* g; */ SELECT `h;i`"]
+    );
+    assert_eq!(
+        sql_of("SELECT 'it''s;'; SELECT 'x\\';'", Dialect::MySql),
+        vec!["SELECT 'it''s;'", "SELECT 'x\\';'"]
+    );
+}
+
+#[test]
+fn test_split_dollar_quoting() {
+    let script = "CREATE FUNCTION f() RETURNS int AS $body$ BEGIN RETURN 1; END; $body$ LANGUAGE plpgsql;\nSELECT $$;$$, $1";
+    assert_eq!(
+        sql_of(script, Dialect::Postgres),
+        vec![
+            "CREATE FUNCTION f() RETURNS int AS $body$ BEGIN RETURN 1; END; $body$ LANGUAGE plpgsql",
+            "SELECT $$;$$, $1"
+        ]
+    );
+}
+
+#[test]
+fn test_split_delimiter_command() {
+    let script = "DELIMITER //\nCREATE PROCEDURE p() BEGIN SELECT 1; END//\nDELIMITER ;\nCALL p();";
+    assert_eq!(
+        sql_of(script, Dialect::MySql),
+        vec!["CREATE PROCEDURE p() BEGIN SELECT 1; END", "CALL p()"]
+    );
+}
+
+#[test]
+fn test_split_offsets() {
+    let script = "select 1;\n  select 2;\n";
+    let statements = split_statements(script, Dialect::Generic);
+    assert_eq!(&script[statements[1].start..statements[1].end], "select 2");
+}
+
+#[test]
+fn test_split_skips_comment_only_chunks() {
+    assert!(split_statements("-- nothing here\n;  ;", Dialect::Generic).is_empty());
+}
+
+#[test]
+fn test_statement_at_cursor() {
+    let script = "select 1;\nselect 2; \n\nselect 3";
+    let at = |offset| statement_at(script, offset, Dialect::Generic).map(|s| s.sql);
+    assert_eq!(at(0).as_deref(), Some("select 1"));
+    assert_eq!(at(14).as_deref(), Some("select 2"));
+    // right after the delimiter on the same line
+    assert_eq!(at(20).as_deref(), Some("select 2"));
+    // empty line between statements
+    assert_eq!(at(21).as_deref(), Some("select 3"));
+    assert_eq!(at(script.len()).as_deref(), Some("select 3"));
+    assert_eq!(statement_at("  ", 1, Dialect::Generic), None);
+}
+
+#[test]
+fn test_is_complete() {
+    let complete = |script| is_complete(script, Dialect::Generic);
+    assert!(complete("select 1;"));
+    assert!(complete("select 1\n  from t; -- done"));
+    assert!(!complete("select 1"));
+    assert!(!complete("select ';'"));
+    assert!(!complete("select 1 /* ; */"));
+    assert!(!complete("select $$;"));
+    assert!(!complete(""));
+    assert!(complete("DELIMITER //\nselect 1; select 2//"));
+    assert!(!complete("DELIMITER //\nselect 1;"));
+}
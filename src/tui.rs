@@ -2,13 +2,25 @@
 //
 // This software is released under the MIT License.
 // https://opensource.org/licenses/MIT
+use crate::completion;
 #[allow(unused_imports)]
+use crate::export;
+use crate::file_picker::{self, FilePicker};
+use crate::formatter;
+use crate::highlight;
+use crate::history;
+use crate::import;
+use crate::library;
+use crate::pretty;
+use crate::schema_browser::{self, SchemaBrowser};
 use crate::shared;
 use crate::shared::Tab;
+use crate::splitter;
 #[allow(unused_imports)]
 use edtui::{
-    EditorEventHandler, EditorState, EditorStatusLine, EditorTheme, EditorView, Lines,
-    SyntaxHighlighter,
+    EditorEventHandler, EditorMode, EditorState, EditorStatusLine, EditorTheme, EditorView, Index2,
+    Lines, SyntaxHighlighter,
+    actions::{Execute, SwitchMode},
     syntect::parsing::{Scope, SyntaxReference},
 };
 #[allow(unused_imports)]
@@ -20,10 +32,11 @@ use std::cmp::PartialEq;
 use std::error::Error;
 use std::fmt::format;
 use std::ops::Deref;
-use std::time::SystemTime;
+use std::time::{Duration, Instant, SystemTime};
 use tui_logger::{TuiLoggerLevelOutput, TuiLoggerSmartWidget, TuiLoggerWidget};
 use tui_popup::Popup;
 use tui_textarea::{CursorMove, TextArea};
+use unicode_width::UnicodeWidthChar;
 
 use crossterm::event::{
     Event, KeyCode, KeyEvent, KeyEventKind, KeyEventState, KeyModifiers, ModifierKeyCode,
@@ -35,8 +48,8 @@ use ratatui::{
     style::{Color, Modifier, Style, Stylize},
     text::{Line, Span, Text},
     widgets::{
-        Block, BorderType, Borders, Cell, Paragraph, Row, Scrollbar, ScrollbarOrientation,
-        ScrollbarState, Table, TableState, Tabs, Wrap,
+        Block, BorderType, Borders, Cell, Clear, Gauge, Paragraph, Row, Scrollbar,
+        ScrollbarOrientation, ScrollbarState, Table, TableState, Tabs, Wrap,
     },
 };
 
@@ -68,7 +81,7 @@ const SHORTCUTS: &[Shortcut<'_>] = &[
     Shortcut {
         key: KeyCode::F(3),
         modifiers: None,
-        description: "Input the Databasename for the Connection to the Database",
+        description: "Pick the Database for the Connection (type to search)",
         alternative_shortcut: None,
     },
     Shortcut {
@@ -83,16 +96,112 @@ const SHORTCUTS: &[Shortcut<'_>] = &[
         description: "Query the Database",
         alternative_shortcut: None,
     },
+    Shortcut {
+        key: KeyCode::F(6),
+        modifiers: None,
+        description: "Run the Statement under the Cursor",
+        alternative_shortcut: None,
+    },
+    Shortcut {
+        key: KeyCode::F(7),
+        modifiers: None,
+        description: "Run the selected Text",
+        alternative_shortcut: None,
+    },
+    Shortcut {
+        key: KeyCode::Char('k'),
+        modifiers: Some(KeyModifiers::CONTROL),
+        description: "Cancel the running Query",
+        alternative_shortcut: None,
+    },
+    Shortcut {
+        key: KeyCode::Char('n'),
+        modifiers: Some(KeyModifiers::CONTROL),
+        description: "Open a new Editor Buffer (Ctrl+W: close the Buffer)",
+        alternative_shortcut: None,
+    },
+    Shortcut {
+        key: KeyCode::Right,
+        modifiers: Some(KeyModifiers::ALT),
+        description: "Show the next Editor Buffer (Alt+Left: previous, Alt+1..9: Buffer 1 to 9)",
+        alternative_shortcut: None,
+    },
+    Shortcut {
+        key: KeyCode::Char('g'),
+        modifiers: Some(KeyModifiers::CONTROL),
+        description: "Bind the Editor Buffer to the selected User and Database, or release it",
+        alternative_shortcut: None,
+    },
+    Shortcut {
+        key: KeyCode::Tab,
+        modifiers: None,
+        description: "Show the next Result Set in the Table View",
+        alternative_shortcut: None,
+    },
+    Shortcut {
+        key: KeyCode::BackTab,
+        modifiers: None,
+        description: "Show the previous Result Set in the Table View",
+        alternative_shortcut: None,
+    },
     Shortcut {
         key: KeyCode::F(8),
         modifiers: None,
-        description: "Export the SQL Statement to a File",
+        description: "Save the SQL Editor to a File (Tab: complete the Path, Ctrl+A: all Files or only .sql)",
         alternative_shortcut: None,
     },
     Shortcut {
         key: KeyCode::F(9),
         modifiers: None,
-        description: "Import a SQL Statement from a File",
+        description: "Load the SQL Editor from a File or a recent File",
+        alternative_shortcut: None,
+    },
+    Shortcut {
+        key: KeyCode::F(10),
+        modifiers: None,
+        description: "Export the Result Set to a File (csv, tsv, json, ndjson, md, html, sql, txt, xlsx, parquet, arrow)",
+        alternative_shortcut: None,
+    },
+    Shortcut {
+        key: KeyCode::Char('o'),
+        modifiers: Some(KeyModifiers::CONTROL),
+        description: "Import a CSV, TSV or JSON File into a Table",
+        alternative_shortcut: None,
+    },
+    Shortcut {
+        key: KeyCode::Char('b'),
+        modifiers: Some(KeyModifiers::CONTROL),
+        description: "Show, focus or hide the Schema Browser (Enter: expand, s: SELECT top 100, i: insert Name, r: refresh)",
+        alternative_shortcut: None,
+    },
+    Shortcut {
+        key: KeyCode::Char(' '),
+        modifiers: Some(KeyModifiers::CONTROL),
+        description: "Complete Keywords, Functions, Tables and Columns (Tab: accept, Ctrl+R: reload the Schema)",
+        alternative_shortcut: None,
+    },
+    Shortcut {
+        key: KeyCode::Char('f'),
+        modifiers: Some(KeyModifiers::CONTROL),
+        description: "Format the SQL of the Selection or the whole Editor",
+        alternative_shortcut: None,
+    },
+    Shortcut {
+        key: KeyCode::Char('l'),
+        modifiers: Some(KeyModifiers::CONTROL),
+        description: "Open the saved Query Library (type to search, Enter: load into the Editor, Ctrl+R: run)",
+        alternative_shortcut: None,
+    },
+    Shortcut {
+        key: KeyCode::Char('s'),
+        modifiers: Some(KeyModifiers::CONTROL),
+        description: "Save the SQL Editor to the Query Library with Name, Tags and Description",
+        alternative_shortcut: None,
+    },
+    Shortcut {
+        key: KeyCode::Char('r'),
+        modifiers: Some(KeyModifiers::CONTROL),
+        description: "Run the selected Query again on its Credential and Database in the History Tab (type to search, Enter: load into the Editor)",
         alternative_shortcut: None,
     },
     Shortcut {
@@ -114,7 +223,28 @@ const SHORTCUTS: &[Shortcut<'_>] = &[
 
 impl AlternativeShortcut {
     #[allow(dead_code)]
-    pub fn to_string(&self) -> String {
+    pub fn as_str(&self) -> &'static str {
+        Box::leak(self.to_string().into_boxed_str())
+    }
+}
+
+impl std::fmt::Display for AlternativeShortcut {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let key = match self.key {
+            KeyCode::F(n) => format!("F{}", n),
+            KeyCode::Char(' ') => "Space".to_string(),
+            KeyCode::Char(c) => c.to_string(),
+            KeyCode::Enter => "Enter".to_string(),
+            KeyCode::Esc => "Esc".to_string(),
+            KeyCode::Tab => "Tab".to_string(),
+            KeyCode::BackTab => "Shift+Tab".to_string(),
+            KeyCode::Backspace => "Backspace".to_string(),
+            KeyCode::Left => "Left".to_string(),
+            KeyCode::Right => "Right".to_string(),
+            KeyCode::Up => "Up".to_string(),
+            KeyCode::Down => "Down".to_string(),
+            _ => "Other".to_string(),
+        };
         if let Some(modifiers) = self.modifiers {
             let mut parts = Vec::new();
             if modifiers.contains(KeyModifiers::CONTROL) {
@@ -126,46 +256,23 @@ impl AlternativeShortcut {
             if modifiers.contains(KeyModifiers::SHIFT) {
                 parts.push("Shift".to_string());
             }
-            parts.push(match self.key {
-                KeyCode::F(n) => format!("F{}", n),
-                KeyCode::Char(c) => c.to_string(),
-                KeyCode::Enter => "Enter".to_string(),
-                KeyCode::Esc => "Esc".to_string(),
-                KeyCode::Tab => "Tab".to_string(),
-                KeyCode::Backspace => "Backspace".to_string(),
-                KeyCode::Left => "Left".to_string(),
-                KeyCode::Right => "Right".to_string(),
-                KeyCode::Up => "Up".to_string(),
-                KeyCode::Down => "Down".to_string(),
-                _ => "Other".to_string(),
-            });
-            parts.join("+")
+            parts.push(key);
+            f.write_str(&parts.join("+"))
         } else {
-            match self.key {
-                KeyCode::F(n) => format!("F{}", n),
-                KeyCode::Char(c) => c.to_string(),
-                KeyCode::Enter => "Enter".to_string(),
-                KeyCode::Esc => "Esc".to_string(),
-                KeyCode::Tab => "Tab".to_string(),
-                KeyCode::Backspace => "Backspace".to_string(),
-                KeyCode::Left => "Left".to_string(),
-                KeyCode::Right => "Right".to_string(),
-                KeyCode::Up => "Up".to_string(),
-                KeyCode::Down => "Down".to_string(),
-                _ => "Other".to_string(),
-            }
+            f.write_str(&key)
         }
     }
+}
 
+impl Shortcut<'_> {
     #[allow(dead_code)]
     pub fn as_str(&self) -> &'static str {
         Box::leak(self.to_string().into_boxed_str())
     }
 }
 
-impl Shortcut<'_> {
-    #[allow(dead_code)]
-    pub fn to_string(&self) -> String {
+impl std::fmt::Display for Shortcut<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let mut parts = Vec::new();
         if let Some(modifiers) = self.modifiers {
             if modifiers.contains(KeyModifiers::CONTROL) {
@@ -180,10 +287,12 @@ impl Shortcut<'_> {
         }
         parts.push(match self.key {
             KeyCode::F(n) => format!("F{}", n),
+            KeyCode::Char(' ') => "Space".to_string(),
             KeyCode::Char(c) => c.to_string(),
             KeyCode::Enter => "Enter".to_string(),
             KeyCode::Esc => "Esc".to_string(),
             KeyCode::Tab => "Tab".to_string(),
+            KeyCode::BackTab => "Shift+Tab".to_string(),
             KeyCode::Backspace => "Backspace".to_string(),
             KeyCode::Left => "Left".to_string(),
             KeyCode::Right => "Right".to_string(),
@@ -191,56 +300,165 @@ impl Shortcut<'_> {
             KeyCode::Down => "Down".to_string(),
             _ => "Other".to_string(),
         });
-        #[allow(unused_assignments)]
-        let mut shortcut: String = String::new();
         let main_shortcut = parts.join("+");
-        if let Some(alternatives) = self.alternative_shortcut {
+        let shortcut = if let Some(alternatives) = self.alternative_shortcut {
             let alt_strings: Vec<String> = alternatives.iter().map(|alt| alt.to_string()).collect();
-            shortcut = format!("{} ({})", main_shortcut, alt_strings.join(" / "));
+            format!("{} ({})", main_shortcut, alt_strings.join(" / "))
         } else {
-            shortcut = main_shortcut;
-        }
-
-        format!("{} - {}", shortcut, self.description)
-    }
+            main_shortcut
+        };
 
-    #[allow(dead_code)]
-    pub fn as_str(&self) -> &'static str {
-        Box::leak(self.to_string().into_boxed_str())
+        write!(f, "{} - {}", shortcut, self.description)
     }
 }
 
-#[derive(Clone)]
+#[derive(Clone, PartialEq)]
 pub enum FileAction {
     Save,
     Load,
+    /// Write the shown result set, in the format given by the file extension.
+    Export,
+    /// Open the import wizard for a CSV, TSV or JSON file.
+    Import,
+}
+
+/// A query executing on the background runtime.
+pub struct RunningQuery {
+    pub handle: tokio::task::JoinHandle<Result<(), shared::SimpleSqlError>>,
+    pub cancel: shared::QueryCancelHandle,
+    pub started: Instant,
+    /// Recorded in the history once the query is done.
+    pub entry: history::HistoryEntry,
+}
+
+pub type SchemaLoad =
+    tokio::task::JoinHandle<Result<Vec<shared::SchemaTable>, shared::SimpleSqlError>>;
+
+/// Databases and tables loading for the schema browser, with the pool they come from.
+pub type BrowserLoad = tokio::task::JoinHandle<
+    Result<(shared::PoolKey, Vec<String>, Vec<shared::SchemaTable>), shared::SimpleSqlError>,
+>;
+
+pub type DatabaseLoad = tokio::task::JoinHandle<Result<Vec<String>, shared::SimpleSqlError>>;
+
+/// The import wizard opened with Ctrl+O, from the preview to the final report.
+pub struct ImportWizard {
+    pub path: String,
+    pub data: import::ImportData,
+    /// Tables of the database, to map the file columns to.
+    pub schema: Vec<shared::SchemaTable>,
+    /// Load of `schema` running on the background runtime; nothing is planned until it is done.
+    pub schema_loading: Option<SchemaLoad>,
+    pub table_textarea: TextArea<'static>,
+    /// The plan for the table name being typed, or why it cannot be imported.
+    pub plan: Result<import::ImportPlan, String>,
+    /// File column selected in the column list, `None` while the table name is typed.
+    pub column: Option<usize>,
+    /// Values per file column that do not fit the type picked by hand.
+    pub misfits: Vec<usize>,
+    pub running: Option<RunningImport>,
+    /// Summary of the finished import, shown until the wizard is closed.
+    pub report: Option<String>,
+}
+
+/// An import executing on the background runtime.
+pub struct RunningImport {
+    pub handle: tokio::task::JoinHandle<
+        Result<(shared::ScriptOutcome, import::Undo), shared::SimpleSqlError>,
+    >,
+    pub progress: shared::QueryCancelHandle,
+    pub started: Instant,
+}
+
+/// The completion popup of the SQL Editor.
+pub struct CompletionPopup {
+    pub completion: completion::Completion,
+    pub selected: usize,
+    /// Enter only accepts a suggestion after the user picked one, otherwise it breaks the line.
+    pub navigated: bool,
 }
 
+const SPINNER_FRAMES: [&str; 10] = ["⠋", "⠙", "⠹", "⠸", "⠼", "⠴", "⠦", "⠧", "⠇", "⠏"];
+
 #[allow(dead_code)]
-#[derive(Clone)]
 pub struct ExtendedAppState {
     pub shared: shared::AppState,
     pub editor_state: EditorState,
-    pub db_input: bool,
-    pub db_textarea: TextArea<'static>,
+    /// The F3 popup, while it is open.
+    pub db_picker: Option<DatabasePicker>,
+    /// Databases being listed for the F3 popup.
+    pub db_picker_loading: Option<DatabaseLoad>,
     pub file_textarea: TextArea<'static>,
     pub show_help: bool,
+    /// Error shown in a popup until it is dismissed.
+    pub error_message: Option<String>,
+    /// The path popup of [`FileAction::Export`] and [`FileAction::Import`]; F8/F9 use the
+    /// [`FilePickerPopup`].
     pub show_file_popup: bool,
     pub file_save: Option<FileAction>,
     pub file_popup_is_active: bool,
     pub table_selected: usize,
     pub table_offset: usize,
     pub table_col_offset: usize,
+    /// Result set shown in the Table View.
+    pub result_index: usize,
+    /// Part of the editor that was sent to the database by the last F6/F7.
+    pub executed_range: Option<(Index2, Index2)>,
+    pub runtime: Option<tokio::runtime::Handle>,
+    pub running_query: Option<RunningQuery>,
+    pub import: Option<ImportWizard>,
+    pub show_schema_browser: bool,
+    /// Keys go to the schema browser instead of the editor.
+    pub schema_browser_focused: bool,
+    pub schema_browser: SchemaBrowser,
+    /// Load of the schema browser running on the background runtime.
+    pub schema_browser_loading: Option<BrowserLoad>,
+    pub completion: Option<CompletionPopup>,
+    /// Tables the completion suggests, loaded once per pool and reloaded on demand.
+    pub completion_schema: Option<(shared::PoolKey, Vec<shared::SchemaTable>)>,
+    /// Schema load for the completion running on the background runtime.
+    pub completion_loading: Option<(shared::PoolKey, SchemaLoad)>,
+    /// Token colors of the SQL Editor text, computed again when the text or dialect changes.
+    pub highlighted: highlight::Highlighted,
+    pub editor_viewport: EditorViewport,
+    /// Executed queries listed in the History tab.
+    pub history: history::History,
+    pub history_filter: TextArea<'static>,
+    /// The Ctrl+L popup, while it is open.
+    pub library: Option<LibraryBrowser>,
+    /// The Ctrl+S form, while it is open.
+    pub library_save: Option<LibrarySaveForm>,
+    /// Saved query loaded into the editor last, offered again when saving.
+    pub library_query: Option<library::SavedQuery>,
+    /// The F8/F9 popup, while it is open.
+    pub file_picker: Option<FilePickerPopup>,
+    /// The editor buffers; the active one is edited through `editor_state` and
+    /// `shared.sql_query`, see [`EditorBuffer`].
+    pub buffers: Vec<EditorBuffer>,
+    pub buffer_index: usize,
+    /// Question waiting for y/n before unsaved changes are dropped.
+    pub confirm: Option<Confirm>,
+    /// The user agreed to quit with unsaved changes.
+    pub quit_confirmed: bool,
 }
 impl Default for ExtendedAppState {
     fn default() -> Self {
-        let shared = shared::AppState::default();
-        let lines = vec![shared.db.clone()];
+        let (shared, error_message) = match shared::AppState::load() {
+            Ok(shared) => (shared, None),
+            Err(e) => {
+                error!("{}", e);
+                (
+                    shared::AppState::unconfigured(),
+                    Some(format!("{}\n\nStarting without a connection.", e)),
+                )
+            }
+        };
+        let initial_sql = shared.sql_query.clone();
         ExtendedAppState {
             shared,
             editor_state: EditorState::default(),
-            db_input: false,
-            db_textarea: TextArea::new(lines),
+            db_picker: None,
+            db_picker_loading: None,
             file_textarea: TextArea::new(vec![
                 std::env::current_dir()
                     .unwrap()
@@ -250,46 +468,37 @@ impl Default for ExtendedAppState {
                     .to_string(),
             ]),
             show_help: false,
+            error_message,
             show_file_popup: false,
             file_save: None,
             file_popup_is_active: false,
             table_selected: 0,
             table_offset: 0,
             table_col_offset: 0,
-        }
-    }
-}
-
-fn inactivate(textarea: &mut TextArea<'_>) {
-    textarea.set_cursor_line_style(Style::default());
-    textarea.set_cursor_style(Style::default());
-    textarea.set_block(
-        Block::default()
-            .borders(Borders::ALL)
-            .border_type(BorderType::Thick)
-            .style(Style::default().fg(Color::White))
-            .title("DB"),
-    );
-}
-
-fn activate(textarea: &mut TextArea<'_>) {
-    textarea.set_cursor_line_style(Style::default().add_modifier(Modifier::UNDERLINED));
-    textarea.set_cursor_style(Style::default().add_modifier(Modifier::REVERSED));
-    textarea.set_block(
-        Block::default()
-            .borders(Borders::ALL)
-            .border_type(BorderType::Thick)
-            .style(Style::default())
-            .title("DB"),
-    );
-}
-
-impl PartialEq for FileAction {
-    fn eq(&self, other: &Self) -> bool {
-        match (self, other) {
-            (FileAction::Save, FileAction::Save) => true,
-            (FileAction::Load, FileAction::Load) => true,
-            _ => false,
+            result_index: 0,
+            executed_range: None,
+            runtime: None,
+            running_query: None,
+            import: None,
+            show_schema_browser: false,
+            schema_browser_focused: false,
+            schema_browser: SchemaBrowser::default(),
+            schema_browser_loading: None,
+            completion: None,
+            completion_schema: None,
+            completion_loading: None,
+            highlighted: highlight::Highlighted::default(),
+            editor_viewport: EditorViewport::default(),
+            history: history::load(),
+            history_filter: history_filter(),
+            library: None,
+            library_save: None,
+            library_query: None,
+            file_picker: None,
+            buffers: vec![EditorBuffer::new("Query 1".to_string(), initial_sql)],
+            buffer_index: 0,
+            confirm: None,
+            quit_confirmed: false,
         }
     }
 }
@@ -330,6 +539,7 @@ fn ui(frame: &mut ratatui::Frame, state: &mut ExtendedAppState) {
                 ),
             terminal_size,
         );
+        draw_confirm(frame, state);
         return;
     }
 
@@ -339,15 +549,23 @@ fn ui(frame: &mut ratatui::Frame, state: &mut ExtendedAppState) {
         .constraints([Constraint::Length(3), Constraint::Fill(1)])
         .split(frame.area());
 
-    let tab_string = vec![
-        Tab::SqlEditor.to_string(),
+    // the editor buffers take the place of the SQL Editor tab
+    let mut tab_string: Vec<String> = (0..state.buffers.len())
+        .map(|idx| buffer_label(state, idx))
+        .collect();
+    tab_string.extend([
         Tab::TableView.to_string(),
+        Tab::History.to_string(),
         Tab::LogViewer.to_string(),
-    ];
+    ]);
+    let selected_tab = match state.shared.current_tab {
+        shared::Tab::SqlEditor => state.buffer_index,
+        tab => state.buffers.len() + tab.to_index() - 1,
+    };
 
     // Create and render tabs
     let tabs = Tabs::new(tab_string)
-        .select(state.shared.current_tab.to_index())
+        .select(selected_tab)
         .style(Style::default().fg(Color::White))
         .highlight_style(Style::default().bold().fg(Color::Black).bg(Color::White))
         .divider("|")
@@ -361,24 +579,25 @@ fn ui(frame: &mut ratatui::Frame, state: &mut ExtendedAppState) {
     let h0chunks = Layout::default()
         .direction(Direction::Horizontal)
         .constraints([
-            Constraint::Percentage(60),
-            Constraint::Percentage(20),
+            Constraint::Percentage(50),
             Constraint::Percentage(20),
+            Constraint::Percentage(15),
+            Constraint::Percentage(15),
         ])
         .split(chunks[0]);
 
-    match state.shared.current_tab {
-        shared::Tab::SqlEditor => {
-            state.editor_state.lines = Lines::from(state.shared.sql_query.clone());
-        }
-        _ => {}
+    if state.shared.current_tab == shared::Tab::SqlEditor {
+        state.editor_state.lines = Lines::from(state.shared.sql_query.clone());
     }
-    // Create SQL SyntaxHighlighter
-    let sql_syntax_highlighter: SyntaxHighlighter = SyntaxHighlighter::new("nord", "sql");
     frame.render_widget(tabs.clone(), h0chunks[0]);
+    let (user_text, user_color) = if state.shared.has_connection() {
+        (state.shared.user.name.clone(), Color::White)
+    } else {
+        ("no connection configured".to_string(), Color::Red)
+    };
     frame.render_widget(
-        Paragraph::new(state.shared.user.name.clone())
-            .style(Style::default().fg(Color::White))
+        Paragraph::new(user_text)
+            .style(Style::default().fg(user_color))
             .block(
                 Block::default()
                     .title("Selected User")
@@ -387,44 +606,141 @@ fn ui(frame: &mut ratatui::Frame, state: &mut ExtendedAppState) {
             ),
         h0chunks[2],
     );
-    if state.db_input {
-        activate(&mut state.db_textarea);
+    let (status_text, status_color) = if let Some(running) = &state.running_query {
+        let elapsed = running.started.elapsed();
+        let frame_idx = (elapsed.as_millis() / 100) as usize % SPINNER_FRAMES.len();
+        (
+            format!(
+                "{} running {:.1}s",
+                SPINNER_FRAMES[frame_idx],
+                elapsed.as_secs_f64()
+            ),
+            Color::Yellow,
+        )
     } else {
-        inactivate(&mut state.db_textarea);
-    }
-    frame.render_widget(&state.db_textarea, h0chunks[1]);
+        match state.shared.connection_status() {
+            shared::ConnectionStatus::Connected => ("● connected".to_string(), Color::Green),
+            shared::ConnectionStatus::Disconnected => ("○ disconnected".to_string(), Color::Gray),
+            shared::ConnectionStatus::Failed(_) => ("✖ failed".to_string(), Color::Red),
+        }
+    };
+    frame.render_widget(
+        Paragraph::new(status_text)
+            .style(Style::default().fg(status_color))
+            .block(
+                Block::default()
+                    .title("Connection")
+                    .borders(Borders::ALL)
+                    .border_type(BorderType::Thick),
+            ),
+        h0chunks[3],
+    );
+    frame.render_widget(
+        Paragraph::new(state.shared.db.as_str()).block(
+            Block::default()
+                .title("DB")
+                .borders(Borders::ALL)
+                .border_type(BorderType::Thick),
+        ),
+        h0chunks[1],
+    );
+    let content_area = if state.show_schema_browser {
+        let columns = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Percentage(25), Constraint::Fill(1)])
+            .split(chunks[1]);
+        draw_schema_browser(frame, columns[0], state);
+        columns[1]
+    } else {
+        chunks[1]
+    };
     // Render main content based on selected tab
     match state.shared.current_tab {
-        shared::Tab::SqlEditor => frame.render_widget(
-            EditorView::new(&mut state.editor_state)
-                .wrap(true)
-                .theme(Theme::new().editor)
-                .syntax_highlighter(Some(sql_syntax_highlighter)),
-            chunks[1],
-        ),
+        shared::Tab::SqlEditor => {
+            // Show the executed statement through the selection, unless the user is selecting.
+            let mut theme = Theme::new().editor;
+            let highlight_executed =
+                state.editor_state.selection.is_none() && state.executed_range.is_some();
+            if let Some((start, end)) = state.executed_range
+                && highlight_executed
+            {
+                select_range(&mut state.editor_state, start, end);
+                theme = theme.selection_style(
+                    Style::default()
+                        .bg(Color::from_u32(shared::NordColor::Nord3.value() >> 8))
+                        .fg(Color::White),
+                );
+            }
+            frame.render_widget(
+                EditorView::new(&mut state.editor_state)
+                    .wrap(true)
+                    .theme(theme),
+                content_area,
+            );
+            if highlight_executed {
+                state.editor_state.selection = None;
+            }
+            let text_area = editor_text_area(content_area);
+            state.editor_viewport.update(
+                &state.editor_state,
+                text_area.width as usize,
+                text_area.height as usize,
+            );
+            let dialect = state.shared.dialect();
+            if !state.highlighted.is_for(&state.shared.sql_query, dialect) {
+                state.highlighted = highlight::Highlighted::new(&state.shared.sql_query, dialect);
+            }
+            paint_highlighting(frame.buffer_mut(), text_area, state);
+            draw_completion(frame, content_area, state);
+        }
         shared::Tab::TableView => {
-            let table_arc = state.shared.table.clone();
-            let table = table_arc.lock().unwrap();
-            if table.headers.is_empty() && table.rows.is_empty() {
+            let results_arc = state.shared.results.clone();
+            let results = results_arc.lock().unwrap();
+            let empty = shared::Table::default();
+            let table = results.get(state.result_index).unwrap_or(&empty);
+            let title = match results.len() {
+                0 => "Table View".to_string(),
+                1 if table.rows_affected.is_none() && table.error.is_none() => {
+                    "Table View".to_string()
+                }
+                n => format!(
+                    "Table View [{}/{}] - {}",
+                    state.result_index + 1,
+                    n,
+                    table.summary()
+                ),
+            };
+            if table.columns.is_empty() && table.rows.is_empty() {
+                let (message, color) = if table.error.is_some() {
+                    (
+                        format!("{}\n\n{}", table.summary(), table.statement),
+                        Color::Red,
+                    )
+                } else if table.rows_affected.is_some() {
+                    (table.summary(), Color::White)
+                } else {
+                    ("No data available".to_string(), Color::White)
+                };
                 frame.render_widget(
-                    Paragraph::new("No data available")
-                        .style(Style::default().fg(Color::White))
+                    Paragraph::new(message)
+                        .wrap(Wrap { trim: false })
+                        .style(Style::default().fg(color))
                         .block(
                             Block::default()
-                                .title("Table View")
+                                .title(title)
                                 .borders(Borders::ALL)
                                 .border_type(BorderType::Thick),
                         ),
-                    chunks[1],
+                    content_area,
                 );
             } else {
                 // Header row
                 let block = Block::default()
-                    .title("Table View")
+                    .title(title)
                     .borders(Borders::ALL)
                     .border_type(BorderType::Thick);
-                frame.render_widget(block, chunks[1]);
-                let inner_area = chunks[1].inner(ratatui::layout::Margin::new(1, 1));
+                frame.render_widget(block, content_area);
+                let inner_area = content_area.inner(ratatui::layout::Margin::new(1, 1));
 
                 let max_rows_visible = inner_area.height.saturating_sub(1) as usize;
                 let has_vertical_scroll = table.rows.len() > max_rows_visible;
@@ -433,12 +749,12 @@ fn ui(frame: &mut ratatui::Frame, state: &mut ExtendedAppState) {
                 let available_width = inner_area.width.saturating_sub(v_scroll_width);
 
                 let mut max_widths: Vec<u16> =
-                    table.headers.iter().map(|h| h.len() as u16).collect();
+                    table.columns.iter().map(|c| c.name.len() as u16).collect();
 
                 for row in table.rows.iter().take(100) {
                     for (i, cell) in row.iter().enumerate() {
                         if i < max_widths.len() {
-                            max_widths[i] = max_widths[i].max(cell.len() as u16);
+                            max_widths[i] = max_widths[i].max(cell.to_string().len() as u16);
                         }
                     }
                 }
@@ -462,7 +778,7 @@ fn ui(frame: &mut ratatui::Frame, state: &mut ExtendedAppState) {
                 }
 
                 let has_horizontal_scroll =
-                    visible_cols_end_idx < table.headers.len() || state.table_col_offset > 0;
+                    visible_cols_end_idx < table.columns.len() || state.table_col_offset > 0;
                 let h_scroll_height = if has_horizontal_scroll { 1 } else { 0 };
 
                 let table_area = Rect {
@@ -486,8 +802,8 @@ fn ui(frame: &mut ratatui::Frame, state: &mut ExtendedAppState) {
                 let start_index = state.table_offset;
                 let end_index = (start_index + content_height).min(table.rows.len());
 
-                let visible_headers = &table.headers[state.table_col_offset..visible_cols_end_idx];
-                let header = Row::new(visible_headers.iter().map(|s| s.as_str()));
+                let visible_columns = &table.columns[state.table_col_offset..visible_cols_end_idx];
+                let header = Row::new(visible_columns.iter().map(|c| c.name.as_str()));
 
                 let rows =
                     table.rows[start_index..end_index]
@@ -500,20 +816,18 @@ fn ui(frame: &mut ratatui::Frame, state: &mut ExtendedAppState) {
                                 .enumerate()
                                 .skip(state.table_col_offset)
                                 .take(visible_cols_end_idx - state.table_col_offset)
-                                .map(|(col_idx, s)| {
+                                .map(|(col_idx, value)| {
                                     let width = all_col_widths[col_idx] as usize;
-                                    if s.chars().count() > width {
-                                        let mut truncated: String =
-                                            s.chars().take(width.saturating_sub(1)).collect();
-                                        truncated.push('…');
-                                        Cell::from(truncated)
-                                    } else {
-                                        Cell::from(s.clone())
+                                    let mut text = value.to_string();
+                                    if text.chars().count() > width {
+                                        text = text.chars().take(width.saturating_sub(1)).collect();
+                                        text.push('…');
                                     }
+                                    value_cell(value, text)
                                 });
                             let style = if actual_idx == state.table_selected {
                                 style::Style::default().bg(Color::White).fg(Color::Black)
-                            } else if actual_idx % 2 == 0 {
+                            } else if actual_idx.is_multiple_of(2) {
                                 style::Style::default().bg(Color::Black)
                             } else {
                                 style::Style::default().bg(Color::DarkGray)
@@ -561,7 +875,7 @@ fn ui(frame: &mut ratatui::Frame, state: &mut ExtendedAppState) {
                             width: table_area.width,
                             height: 1,
                         },
-                        table.headers.len(),
+                        table.columns.len(),
                         visible_cols_end_idx.saturating_sub(state.table_col_offset),
                         state.table_col_offset,
                         ScrollbarOrientation::HorizontalBottom,
@@ -570,6 +884,8 @@ fn ui(frame: &mut ratatui::Frame, state: &mut ExtendedAppState) {
             }
         }
 
+        shared::Tab::History => draw_history(frame, content_area, state),
+
         shared::Tab::LogViewer => frame.render_widget(
             TuiLoggerWidget::default()
                 .output_separator('-')
@@ -584,30 +900,31 @@ fn ui(frame: &mut ratatui::Frame, state: &mut ExtendedAppState) {
                         .borders(Borders::ALL),
                 )
                 .style(Style::default().fg(Color::Gray)),
-            chunks[1],
+            content_area,
         ),
     }
 
     if state.show_file_popup {
-        if state.file_save == Some(FileAction::Save) && !state.file_popup_is_active {
+        if state.file_save == Some(FileAction::Export) && !state.file_popup_is_active {
             state.file_popup_is_active = true;
-            #[allow(unused_assignments, unused_mut)]
-            let mut curret_path: String = state.file_textarea.lines().first().unwrap().to_string();
-            #[allow(unused_assignments, unused_mut)]
-            let mut save_path: String = String::new();
-            if !curret_path.contains(".sql") {
-                let suggested_filename = format!(
-                    "{}_{}.sql",
-                    "query",
+            let current_path = state.file_textarea.lines().join("");
+            // keep a path typed for an earlier export, but do not overwrite a saved query
+            let keep = matches!(
+                formatter::Format::from_path(&current_path),
+                Some(format) if format != formatter::Format::Insert
+            );
+            if !keep {
+                let path = std::path::Path::new(&current_path);
+                let dir = match path.extension() {
+                    Some(_) => path.parent().unwrap_or(path),
+                    None => path,
+                };
+                let suggested = dir.join(format!(
+                    "results_{}.csv",
                     humantime::format_rfc3339_seconds(SystemTime::now())
-                );
-                if std::env::consts::OS == "windows" {
-                    save_path = format!("\\{}", suggested_filename);
-                } else {
-                    save_path = format!("/{}", suggested_filename);
-                }
+                ));
+                state.file_textarea = TextArea::new(vec![suggested.to_string_lossy().into_owned()]);
                 state.file_textarea.move_cursor(CursorMove::End);
-                state.file_textarea.insert_str(&save_path.as_str());
             }
         }
         state
@@ -617,9 +934,8 @@ fn ui(frame: &mut ratatui::Frame, state: &mut ExtendedAppState) {
             .file_textarea
             .set_cursor_style(Style::default().add_modifier(Modifier::REVERSED));
         let title = match state.file_save {
-            Some(FileAction::Save) => "Save SQL to File",
-            Some(FileAction::Load) => "Load SQL from File",
-            None => "File Action",
+            Some(FileAction::Import) => "Import CSV, TSV or JSON File into a Table",
+            _ => "Export Result Set to File",
         };
         state.file_textarea.set_block(
             Block::default()
@@ -630,11 +946,27 @@ fn ui(frame: &mut ratatui::Frame, state: &mut ExtendedAppState) {
         frame.render_widget(&state.file_textarea, popup_size);
     }
 
+    if let Some(wizard) = &state.import {
+        draw_import(frame, wizard, state.shared.dialect());
+    }
+    if let Some(picker) = &state.db_picker {
+        draw_db_picker(frame, picker, &state.shared.db);
+    }
+    if let Some(browser) = &state.library {
+        draw_library(frame, browser);
+    }
+    if let Some(form) = &state.library_save {
+        draw_library_save(frame, form);
+    }
+    if let Some(popup) = &state.file_picker {
+        draw_file_picker(frame, popup);
+    }
+
     // Render help popup
     if state.show_help {
         let mut helplinetext = String::new();
         for shortcut in SHORTCUTS {
-            helplinetext.push_str(&format!("{}\n", shortcut.to_string()));
+            helplinetext.push_str(&format!("{}\n", shortcut));
         }
         let frame_size = frame.area();
         let help_paragraoh = Text::from(helplinetext);
@@ -645,6 +977,46 @@ fn ui(frame: &mut ratatui::Frame, state: &mut ExtendedAppState) {
             .border_set(border::THICK);
         frame.render_widget(&help_popup, frame_size);
     }
+
+    draw_confirm(frame, state);
+
+    if let Some(message) = &state.error_message {
+        let error_popup = Popup::new(Text::from(format!("{}\n\nPress Esc to close", message)))
+            .style(Style::default().fg(Color::White).bg(Color::Red))
+            .title("Error")
+            .borders(Borders::ALL)
+            .border_set(border::THICK);
+        frame.render_widget(&error_popup, frame.area());
+    }
+}
+
+/// Asks whether unsaved changes may be dropped.
+fn draw_confirm(frame: &mut ratatui::Frame, state: &ExtendedAppState) {
+    let Some(confirm) = &state.confirm else {
+        return;
+    };
+    let question = match confirm {
+        Confirm::Quit => {
+            let names: Vec<String> = (0..state.buffers.len())
+                .filter(|idx| buffer_dirty(state, *idx))
+                .map(|idx| state.buffers[idx].name.clone())
+                .collect();
+            format!(
+                "Unsaved changes in {}.\n\nQuit anyway? (y/n)",
+                names.join(", ")
+            )
+        }
+        Confirm::CloseBuffer => format!(
+            "Unsaved changes in {}.\n\nClose it anyway? (y/n)",
+            state.buffers[state.buffer_index].name
+        ),
+    };
+    let confirm_popup = Popup::new(Text::from(question))
+        .style(Style::default().fg(Color::Black).bg(Color::Yellow))
+        .title("Unsaved Changes")
+        .borders(Borders::ALL)
+        .border_set(border::THICK);
+    frame.render_widget(&confirm_popup, frame.area());
 }
 
 // ── Event handling ────────────────────────────────────────────────────────
@@ -655,20 +1027,58 @@ fn handle_event(event: Event, state: &mut ExtendedAppState) -> bool {
     let min_width: u16 = 115;
     let min_height: u16 = SHORTCUTS.len() as u16 + 5;
 
-    // If terminal too small, only allow quit
-    if terminal_size.0 < min_width || terminal_size.1 < min_height {
+    // the prompt is shown on the too small terminal too
+    if let Some(confirm) = state.confirm {
         if let Event::Key(key_event) = event {
-            if key_event.modifiers == KeyModifiers::CONTROL {
-                match key_event.code {
-                    KeyCode::Char('c') | KeyCode::Char('d') => return true,
-                    _ => {}
+            let force_quit = key_event.modifiers == KeyModifiers::CONTROL
+                && matches!(key_event.code, KeyCode::Char('c') | KeyCode::Char('d'));
+            match key_event.code {
+                _ if force_quit => {
+                    state.quit_confirmed = true;
+                    return true;
+                }
+                KeyCode::Char('y') | KeyCode::Enter => {
+                    state.confirm = None;
+                    match confirm {
+                        Confirm::Quit => {
+                            state.quit_confirmed = true;
+                            return true;
+                        }
+                        Confirm::CloseBuffer => close_buffer(state),
+                    }
                 }
+                KeyCode::Char('n') | KeyCode::Esc => state.confirm = None,
+                _ => {}
             }
         }
         return false;
     }
 
-    if state.show_help {
+    // If terminal too small, only allow quit
+    if terminal_size.0 < min_width || terminal_size.1 < min_height {
+        if let Event::Key(key_event) = event
+            && key_event.modifiers == KeyModifiers::CONTROL
+            && matches!(key_event.code, KeyCode::Char('c') | KeyCode::Char('d'))
+        {
+            return true;
+        }
+        return false;
+    }
+
+    if state.error_message.is_some() {
+        if let Event::Key(key_event) = event {
+            if key_event.modifiers == KeyModifiers::CONTROL
+                && matches!(key_event.code, KeyCode::Char('c') | KeyCode::Char('d'))
+            {
+                return true;
+            }
+            match key_event.code {
+                KeyCode::F(12) => return true,
+                KeyCode::Esc | KeyCode::Enter => state.error_message = None,
+                _ => {}
+            }
+        }
+    } else if state.show_help {
         if let Event::Key(key_event) = event {
             if key_event.modifiers == KeyModifiers::CONTROL {
                 match key_event.code {
@@ -685,34 +1095,46 @@ fn handle_event(event: Event, state: &mut ExtendedAppState) -> bool {
                 _ => {}
             }
         }
-    } else if state.db_input {
-        match event {
-            Event::Key(key_event) => {
-                if key_event.modifiers == KeyModifiers::CONTROL {
-                    match key_event.code {
-                        KeyCode::Char('c') | KeyCode::Char('d') => return true,
-                        _ => {}
-                    }
-                }
-                match key_event.code {
-                    KeyCode::F(12) => return true,
-                    KeyCode::Esc | KeyCode::Enter => {
-                        state.db_input = false;
-                        debug!("Exiting DB input mode");
-                    }
-                    _ => {
-                        state
-                            .db_textarea
-                            .input(tui_textarea::Input::from(key_event));
-                    }
-                }
+    } else if state.db_picker.is_some() {
+        if let Event::Key(key_event) = event {
+            if key_event.modifiers == KeyModifiers::CONTROL
+                && matches!(key_event.code, KeyCode::Char('c') | KeyCode::Char('d'))
+            {
+                return true;
             }
-            Event::Mouse(mouse_event) => {
-                debug!("Mouse event: {:?}", mouse_event);
+            if key_event.code == KeyCode::F(12) {
+                return true;
+            }
+            handle_db_picker_key(state, key_event);
+        }
+    } else if state.file_picker.is_some() {
+        if let Event::Key(key_event) = event {
+            if key_event.modifiers == KeyModifiers::CONTROL
+                && matches!(key_event.code, KeyCode::Char('c') | KeyCode::Char('d'))
+            {
+                return true;
+            }
+            if key_event.code == KeyCode::F(12) {
+                return true;
+            }
+            handle_file_picker_key(state, key_event);
+        }
+    } else if state.library.is_some() || state.library_save.is_some() {
+        if let Event::Key(key_event) = event {
+            if key_event.modifiers == KeyModifiers::CONTROL
+                && matches!(key_event.code, KeyCode::Char('c') | KeyCode::Char('d'))
+            {
+                return true;
+            }
+            if key_event.code == KeyCode::F(12) {
+                return true;
+            }
+            if state.library.is_some() {
+                handle_library_key(state, key_event);
+            } else {
+                handle_library_save_key(state, key_event);
             }
-            _ => {}
         }
-        state.shared.db = state.db_textarea.lines().join("\n");
     } else if state.show_file_popup {
         match event {
             Event::Key(key_event) => {
@@ -730,34 +1152,11 @@ fn handle_event(event: Event, state: &mut ExtendedAppState) -> bool {
                         debug!("Exiting DB input mode");
                     }
                     KeyCode::Enter => {
-                        if let Some(action) = &state.file_save {
-                            match action {
-                                FileAction::Save => {
-                                    if let Err(e) = shared::write_file(
-                                        state.file_textarea.lines().join("\n").as_str(),
-                                        state.shared.sql_query.as_str(),
-                                    ) {
-                                        error!("Error saving file: {}", e);
-                                    } else {
-                                        info!("File saved successfully");
-                                    }
-                                }
-                                FileAction::Load => {
-                                    match shared::read_file(
-                                        state.file_textarea.lines().join("\n").as_str(),
-                                    ) {
-                                        Ok(content) => {
-                                            state.shared.sql_query = content;
-                                            state.editor_state.lines =
-                                                Lines::from(state.shared.sql_query.clone());
-                                            info!("File loaded successfully");
-                                        }
-                                        Err(e) => {
-                                            error!("Error loading file: {}", e);
-                                        }
-                                    }
-                                }
-                            }
+                        let path = state.file_textarea.lines().join("\n");
+                        match state.file_save {
+                            Some(FileAction::Export) => export_result(state, &path),
+                            Some(FileAction::Import) => open_import(state, &path),
+                            _ => {}
                         }
                         state.show_file_popup = false;
                         state.file_popup_is_active = false;
@@ -774,14 +1173,46 @@ fn handle_event(event: Event, state: &mut ExtendedAppState) -> bool {
             }
             _ => {}
         }
+    } else if state.schema_browser_focused {
+        if let Event::Key(key_event) = event {
+            if key_event.modifiers == KeyModifiers::CONTROL
+                && matches!(key_event.code, KeyCode::Char('c') | KeyCode::Char('d'))
+            {
+                return true;
+            }
+            if key_event.code == KeyCode::F(12) {
+                return true;
+            }
+            handle_schema_browser_key(state, key_event);
+        }
+    } else if state.import.is_some() {
+        if let Event::Key(key_event) = event {
+            if key_event.modifiers == KeyModifiers::CONTROL
+                && matches!(key_event.code, KeyCode::Char('c') | KeyCode::Char('d'))
+            {
+                return true;
+            }
+            handle_import_key(state, key_event);
+        }
     } else {
+        if let Event::Key(key_event) = event
+            && (handle_completion_key(state, key_event)
+                || handle_history_key(state, key_event)
+                || handle_buffer_key(state, key_event))
+        {
+            return false;
+        }
         // Handle editor events
         EditorEventHandler::default().on_event(event.clone(), &mut state.editor_state);
-        match state.shared.current_tab {
-            shared::Tab::SqlEditor => {
-                state.shared.sql_query = get_editor_lines_as_string(&state);
+        if state.shared.current_tab == shared::Tab::SqlEditor {
+            let sql_query = get_editor_lines_as_string(state);
+            if sql_query != state.shared.sql_query {
+                state.executed_range = None;
             }
-            _ => {}
+            state.shared.sql_query = sql_query;
+        }
+        if let Event::Key(key_event) = event {
+            update_completion(state, key_event);
         }
         // Handle key events
         match event {
@@ -794,66 +1225,67 @@ fn handle_event(event: Event, state: &mut ExtendedAppState) -> bool {
                     }
                     KeyCode::F(2) => {
                         state.shared.current_tab = state.shared.current_tab.next();
-                        info!("Switched to tab: {}", state.shared.current_tab.to_string());
-                    }
-                    KeyCode::F(3) => {
-                        state.db_input = !state.db_input;
+                        info!("Switched to tab: {}", state.shared.current_tab);
                     }
+                    KeyCode::F(3) => open_db_picker(state),
                     KeyCode::F(4) => {
-                        state.shared.set_next_user();
+                        if let Err(e) = state.shared.set_next_user() {
+                            show_error(state, e);
+                        }
                     }
                     KeyCode::F(5) => {
-                        if let Err(e) = shared::run_query(&mut state.shared) {
-                            error!("Error running query: {}", e);
-                        }
+                        state.executed_range = None;
+                        start_query(state, state.shared.sql_query.clone());
                     }
-                    KeyCode::F(8) => {
-                        state.file_save = Some(FileAction::Save);
-                        state.show_file_popup = !state.show_file_popup;
+                    KeyCode::F(6) if state.shared.current_tab == shared::Tab::SqlEditor => {
+                        run_current_statement(state);
+                    }
+                    KeyCode::F(7) if state.shared.current_tab == shared::Tab::SqlEditor => {
+                        run_selection(state);
                     }
-                    KeyCode::F(9) => {
-                        state.file_save = Some(FileAction::Load);
+                    KeyCode::F(8) => open_file_picker(state, FileAction::Save),
+                    KeyCode::F(9) => open_file_picker(state, FileAction::Load),
+                    KeyCode::F(10) => {
+                        state.file_save = Some(FileAction::Export);
                         state.show_file_popup = !state.show_file_popup;
                     }
-                    KeyCode::Down => {
-                        if state.shared.current_tab == shared::Tab::TableView {
-                            let table_len = state.shared.table.lock().unwrap().rows.len();
-                            if table_len > 0 {
-                                if state.table_selected < table_len - 1 {
-                                    state.table_selected += 1;
-                                } else {
-                                    state.table_selected = 0;
-                                }
+                    KeyCode::Down if state.shared.current_tab == shared::Tab::TableView => {
+                        let table_len = current_result(state, |table| table.rows.len());
+                        if table_len > 0 {
+                            if state.table_selected < table_len - 1 {
+                                state.table_selected += 1;
+                            } else {
+                                state.table_selected = 0;
                             }
                         }
                     }
-                    KeyCode::Up => {
-                        if state.shared.current_tab == shared::Tab::TableView {
-                            let table_len = state.shared.table.lock().unwrap().rows.len();
-                            if table_len > 0 {
-                                if state.table_selected > 0 {
-                                    state.table_selected -= 1;
-                                } else {
-                                    state.table_selected = table_len - 1;
-                                }
+                    KeyCode::Up if state.shared.current_tab == shared::Tab::TableView => {
+                        let table_len = current_result(state, |table| table.rows.len());
+                        if table_len > 0 {
+                            if state.table_selected > 0 {
+                                state.table_selected -= 1;
+                            } else {
+                                state.table_selected = table_len - 1;
                             }
                         }
                     }
-                    KeyCode::Right => {
-                        if state.shared.current_tab == shared::Tab::TableView {
-                            let table_headers_len =
-                                state.shared.table.lock().unwrap().headers.len();
-                            if state.table_col_offset < table_headers_len.saturating_sub(1) {
-                                state.table_col_offset += 1;
-                            }
+                    KeyCode::Right if state.shared.current_tab == shared::Tab::TableView => {
+                        let table_headers_len = current_result(state, |table| table.columns.len());
+                        if state.table_col_offset < table_headers_len.saturating_sub(1) {
+                            state.table_col_offset += 1;
                         }
                     }
-                    KeyCode::Left => {
-                        if state.shared.current_tab == shared::Tab::TableView {
-                            if state.table_col_offset > 0 {
-                                state.table_col_offset -= 1;
-                            }
-                        }
+                    KeyCode::Left
+                        if state.shared.current_tab == shared::Tab::TableView
+                            && state.table_col_offset > 0 =>
+                    {
+                        state.table_col_offset -= 1;
+                    }
+                    KeyCode::Tab if state.shared.current_tab == shared::Tab::TableView => {
+                        select_result(state, 1);
+                    }
+                    KeyCode::BackTab if state.shared.current_tab == shared::Tab::TableView => {
+                        select_result(state, -1);
                     }
                     _ => {}
                 }
@@ -861,24 +1293,2405 @@ fn handle_event(event: Event, state: &mut ExtendedAppState) -> bool {
                 if key_event.modifiers == KeyModifiers::CONTROL {
                     match key_event.code {
                         KeyCode::Char('c') | KeyCode::Char('d') => return true,
+                        KeyCode::Char('k') => cancel_query(state),
+                        KeyCode::Char('o') => {
+                            state.file_save = Some(FileAction::Import);
+                            state.show_file_popup = true;
+                        }
+                        KeyCode::Char('b') => {
+                            if !state.show_schema_browser {
+                                state.show_schema_browser = true;
+                                refresh_schema_browser(state);
+                            }
+                            state.schema_browser_focused = true;
+                        }
+                        KeyCode::Char('f')
+                            if state.shared.current_tab == shared::Tab::SqlEditor =>
+                        {
+                            format_editor(state);
+                        }
+                        KeyCode::Char('l') => open_library(state),
+                        KeyCode::Char('s') => open_library_save(state),
                         _ => {}
                     }
                 }
             }
 
-            Event::Mouse(mouse_event) => {
-                debug!("Mouse event: {:?}", mouse_event);
+            Event::Mouse(mouse_event) => {
+                debug!("Mouse event: {:?}", mouse_event);
+            }
+            _ => {}
+        }
+    }
+
+    false
+}
+
+// ── Query execution ───────────────────────────────────────────────────────
+
+/// Reads from the result set currently shown in the Table View.
+fn current_result<T: Default>(state: &ExtendedAppState, f: impl FnOnce(&shared::Table) -> T) -> T {
+    state
+        .shared
+        .results
+        .lock()
+        .unwrap()
+        .get(state.result_index)
+        .map(f)
+        .unwrap_or_default()
+}
+
+/// Writes the result set shown in the Table View to `path`, formatted by its extension.
+fn export_result(state: &mut ExtendedAppState, path: &str) {
+    if let Some(format) = export::FileFormat::from_path(path) {
+        let table = current_result(state, |table| Some(table.clone()));
+        match table.map(|table| export::export_table(&table, format, path)) {
+            Some(Ok(())) => info!("Result set exported to {}", path),
+            Some(Err(e)) => show_error(state, e),
+            None => warn!("No result set to export"),
+        }
+        return;
+    }
+    let Some(format) = formatter::Format::from_path(path) else {
+        error!(
+            "Cannot export to {}: unknown file extension (use csv, tsv, json, ndjson, md, html, sql, txt, xlsx, parquet or arrow)",
+            path
+        );
+        return;
+    };
+    let dialect = state.shared.dialect();
+    let content = current_result(state, |table| {
+        Some(formatter::to_string(table, format, dialect))
+    });
+    match content {
+        Some(content) => match shared::write_file(path, &content) {
+            Ok(()) => info!("Result set exported to {}", path),
+            Err(e) => error!("Error exporting result set: {}", e),
+        },
+        None => warn!("No result set to export"),
+    }
+}
+
+/// Moves `step` result sets forward or backward, wrapping around.
+fn select_result(state: &mut ExtendedAppState, step: isize) {
+    let count = state.shared.results.lock().unwrap().len();
+    if count == 0 {
+        return;
+    }
+    state.result_index = (state.result_index as isize + step).rem_euclid(count as isize) as usize;
+    state.table_selected = 0;
+    state.table_offset = 0;
+    state.table_col_offset = 0;
+}
+
+/// Runs `sql` against the current connection; the editor content is left untouched.
+fn start_query(state: &mut ExtendedAppState, sql: String) {
+    if state.running_query.is_some() {
+        warn!("A query is already running, cancel it with Ctrl+K first");
+        return;
+    }
+    let entry = history::HistoryEntry::start(&state.shared, &sql);
+    let mut shared_state = state.shared.clone();
+    shared_state.sql_query = sql;
+    let Some(runtime) = state.runtime.clone() else {
+        let started = Instant::now();
+        let outcome = shared::run_query(&mut shared_state);
+        remember_query(
+            state,
+            entry.finish(&shared_state, &outcome, started.elapsed()),
+        );
+        if let Err(e) = outcome {
+            show_error(state, e);
+        }
+        state.result_index = state.shared.results.lock().unwrap().len().saturating_sub(1);
+        return;
+    };
+    let cancel = shared::QueryCancelHandle::default();
+    let task_cancel = cancel.clone();
+    let handle = runtime
+        .spawn(async move { shared::run_query_async(&mut shared_state, &task_cancel).await });
+    info!("Query started");
+    state.running_query = Some(RunningQuery {
+        handle,
+        cancel,
+        started: Instant::now(),
+        entry,
+    });
+}
+
+fn run_current_statement(state: &mut ExtendedAppState) {
+    let script = state.shared.sql_query.clone();
+    let offset = byte_offset(&script, state.editor_state.cursor);
+    let Some(statement) = splitter::statement_at(&script, offset, state.shared.dialect()) else {
+        warn!("No statement under the cursor");
+        return;
+    };
+    state.executed_range = Some(byte_range_positions(
+        &script,
+        statement.start,
+        statement.end,
+    ));
+    start_query(state, statement.sql);
+}
+
+fn run_selection(state: &mut ExtendedAppState) {
+    let Some(selection) = state.editor_state.selection.clone() else {
+        warn!("Nothing selected, select the SQL to run in visual mode");
+        return;
+    };
+    let sql: String = selection
+        .extract(&state.editor_state.lines)
+        .flatten(&Some('\n'))
+        .into_iter()
+        .collect();
+    if sql.trim().is_empty() {
+        warn!("The selection is empty");
+        return;
+    }
+    state.executed_range = Some((selection.start(), selection.end()));
+    start_query(state, sql);
+}
+
+/// Rewrites the selected SQL, or the whole editor without a selection, with the formatter.
+fn format_editor(state: &mut ExtendedAppState) {
+    let script = state.shared.sql_query.clone();
+    let (start, end) = match state.editor_state.selection.clone() {
+        Some(selection) => {
+            let last = byte_offset(&script, selection.end());
+            let end = last + script[last..].chars().next().map_or(0, char::len_utf8);
+            (byte_offset(&script, selection.start()), end)
+        }
+        None => (0, script.len()),
+    };
+    let settings = state.shared.config.lock().unwrap().format.clone();
+    let formatted = pretty::format_sql(&script[start..end], state.shared.dialect(), &settings);
+    state.editor_state.selection = None;
+    if state.editor_state.mode == EditorMode::Visual {
+        SwitchMode(EditorMode::Normal).execute(&mut state.editor_state);
+    }
+    if formatted == script[start..end] {
+        info!("The SQL is already formatted");
+        return;
+    }
+    replace_in_editor(state, start, end, &formatted);
+    info!("SQL formatted");
+}
+
+fn show_error(state: &mut ExtendedAppState, error: shared::SimpleSqlError) {
+    error!("{}", error);
+    state.error_message = Some(error.to_string());
+}
+
+fn cancel_query(state: &mut ExtendedAppState) {
+    let (Some(running), Some(runtime)) = (&state.running_query, &state.runtime) else {
+        return;
+    };
+    let cancel = running.cancel.clone();
+    let abort_handle = running.handle.abort_handle();
+    runtime.spawn(async move {
+        match cancel.cancel().await {
+            Ok(true) => info!("Cancel request sent to the server"),
+            Ok(false) => abort_handle.abort(),
+            Err(e) => {
+                warn!("Server-side cancel failed, aborting locally: {}", e);
+                abort_handle.abort();
+            }
+        }
+    });
+}
+
+/// Collects the result of a finished background query.
+fn poll_query(state: &mut ExtendedAppState) {
+    let finished = state
+        .running_query
+        .as_ref()
+        .is_some_and(|running| running.handle.is_finished());
+    if !finished {
+        return;
+    }
+    let (Some(running), Some(runtime)) = (state.running_query.take(), state.runtime.clone()) else {
+        return;
+    };
+    let elapsed = running.started.elapsed();
+    let outcome = runtime.block_on(running.handle);
+    let entry = match &outcome {
+        Ok(result) => running.entry.finish(&state.shared, result, elapsed),
+        Err(e) if e.is_cancelled() => running.entry.fail("Cancelled", elapsed),
+        Err(e) => running.entry.fail(&e.to_string(), elapsed),
+    };
+    remember_query(state, entry);
+    if let Ok(result) = &outcome {
+        // Results are stored even when the script stopped on an error, show the last one.
+        let count = state.shared.results.lock().unwrap().len();
+        state.result_index = count.saturating_sub(1);
+        state.table_selected = 0;
+        state.table_offset = 0;
+        state.table_col_offset = 0;
+        if result.is_ok() {
+            info!(
+                "Query finished in {:.2?} ({} result set(s))",
+                elapsed, count
+            );
+        }
+    }
+    match outcome {
+        Ok(Ok(())) => {}
+        Ok(Err(e)) => show_error(state, e),
+        Err(e) if e.is_cancelled() => info!("Query cancelled after {:.2?}", elapsed),
+        Err(e) => {
+            error!("Query task failed: {}", e);
+            state.error_message = Some(format!("Query task failed: {}", e));
+        }
+    }
+}
+
+/// Runs `task` on the background runtime, so that a slow server does not freeze the UI.
+fn spawn_load<T: Send + 'static>(
+    state: &ExtendedAppState,
+    task: impl Future<Output = T> + Send + 'static,
+) -> tokio::task::JoinHandle<T> {
+    match &state.runtime {
+        Some(runtime) => runtime.spawn(task),
+        None => shared::runtime().spawn(task),
+    }
+}
+
+/// The outcome of a load started with [`spawn_load`] once it is done; a load that is still
+/// running is put back.
+fn finished_load<T>(
+    runtime: &Option<tokio::runtime::Handle>,
+    handle: &mut Option<tokio::task::JoinHandle<T>>,
+) -> Option<Result<T, tokio::task::JoinError>> {
+    if !handle.as_ref().is_some_and(|handle| handle.is_finished()) {
+        return None;
+    }
+    let handle = handle.take()?;
+    Some(match runtime {
+        Some(runtime) => runtime.block_on(handle),
+        None => shared::runtime().block_on(handle),
+    })
+}
+
+/// Adds a finished query to the history file and the History tab.
+fn remember_query(state: &mut ExtendedAppState, entry: history::HistoryEntry) {
+    if let Err(e) = history::record(&entry) {
+        warn!("Cannot save the query history: {}", e);
+    }
+    state.history.push(entry);
+}
+
+// ── History ───────────────────────────────────────────────────────────────
+
+fn history_filter() -> TextArea<'static> {
+    let mut filter = TextArea::default();
+    filter.set_cursor_line_style(Style::default());
+    filter.set_block(
+        Block::default()
+            .borders(Borders::ALL)
+            .border_type(BorderType::Thick)
+            .title("Search History (Enter: load into the Editor, Ctrl+R: run again)"),
+    );
+    filter
+}
+
+/// Handles a key in the History tab.
+///
+/// Returns `true` if the key was used up; function keys and Ctrl+C/Ctrl+D go on as usual.
+fn handle_history_key(state: &mut ExtendedAppState, key_event: KeyEvent) -> bool {
+    if state.shared.current_tab != shared::Tab::History {
+        return false;
+    }
+    state
+        .history
+        .set_filter(&state.history_filter.lines().join(""));
+    let count = state.history.matches().len();
+    if key_event.modifiers == KeyModifiers::CONTROL {
+        if key_event.code != KeyCode::Char('r') {
+            return false;
+        }
+        let Some(entry) = state.history.selected_entry().cloned() else {
+            return true;
+        };
+        if state.running_query.is_some() {
+            warn!("A query is already running, cancel it with Ctrl+K first");
+            return true;
+        }
+        if !state.shared.set_user(&entry.credential) {
+            state.error_message = Some(format!(
+                "The query ran as user \"{}\", which is not in the config anymore",
+                entry.credential
+            ));
+            return true;
+        }
+        if entry.database != state.shared.db {
+            select_database(state, entry.database);
+        }
+        state.executed_range = None;
+        start_query(state, entry.sql);
+        state.shared.current_tab = shared::Tab::TableView;
+        return true;
+    }
+    let history = &mut state.history;
+    match key_event.code {
+        KeyCode::F(_) => return false,
+        KeyCode::Up => history.selected = history.selected.saturating_sub(1),
+        KeyCode::Down => history.selected = (history.selected + 1).min(count.saturating_sub(1)),
+        KeyCode::PageUp => history.selected = history.selected.saturating_sub(10),
+        KeyCode::PageDown => {
+            history.selected = (history.selected + 10).min(count.saturating_sub(1))
+        }
+        KeyCode::Enter => {
+            if let Some(entry) = state.history.selected_entry() {
+                let sql = entry.sql.clone();
+                let name = new_buffer_name(state);
+                open_in_editor(state, EditorBuffer::new(name, sql));
+            }
+        }
+        KeyCode::Esc => state.history_filter = history_filter(),
+        _ => {
+            if state
+                .history_filter
+                .input(tui_textarea::Input::from(key_event))
+            {
+                state.history.selected = 0;
+                state.history.offset = 0;
+            }
+        }
+    }
+    true
+}
+
+fn draw_history(frame: &mut ratatui::Frame, area: Rect, state: &mut ExtendedAppState) {
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(3),
+            Constraint::Fill(2),
+            Constraint::Fill(1),
+        ])
+        .split(area);
+    frame.render_widget(&state.history_filter, chunks[0]);
+    state
+        .history
+        .set_filter(&state.history_filter.lines().join(""));
+    let history = &state.history;
+    let matches = history.matches();
+    let title = format!("History [{}/{}]", matches.len(), history.entries.len());
+    let block = Block::default()
+        .title(title)
+        .borders(Borders::ALL)
+        .border_type(BorderType::Thick);
+    let height = block.inner(chunks[1]).height.saturating_sub(1) as usize;
+    let selected = history.selected.min(matches.len().saturating_sub(1));
+    let mut offset = history.offset;
+    if selected < offset {
+        offset = selected;
+    } else if selected >= offset + height {
+        offset = selected + 1 - height;
+    }
+    let header = Row::new(["Started", "", "Duration", "Rows", "Database", "SQL"])
+        .style(Style::default().add_modifier(Modifier::BOLD));
+    let rows = matches
+        .iter()
+        .enumerate()
+        .skip(offset)
+        .take(height)
+        .map(|(idx, &i)| {
+            let entry = &history.entries[i];
+            let status = match entry.succeeded() {
+                true => Cell::from("✔").fg(Color::Green),
+                false => Cell::from("✖").fg(Color::Red),
+            };
+            let row = Row::new(vec![
+                Cell::from(entry.time()),
+                status,
+                Cell::from(format!("{:.2}s", entry.duration_ms as f64 / 1000.0)),
+                Cell::from(entry.rows.to_string()),
+                Cell::from(format!("{}@{}", entry.credential, entry.database)),
+                Cell::from(entry.one_line()),
+            ]);
+            if idx == selected {
+                row.style(Style::default().bg(Color::White).fg(Color::Black))
+            } else {
+                row
+            }
+        });
+    frame.render_widget(
+        Table::new(
+            rows,
+            [
+                Constraint::Length(19),
+                Constraint::Length(1),
+                Constraint::Length(9),
+                Constraint::Length(7),
+                Constraint::Length(30),
+                Constraint::Fill(1),
+            ],
+        )
+        .header(header)
+        .block(block),
+        chunks[1],
+    );
+    let preview = match matches.get(selected).map(|&i| &history.entries[i]) {
+        Some(entry) => {
+            let mut text = Text::from(entry.sql.clone());
+            if let Some(error) = &entry.error {
+                text.push_line(Line::from(""));
+                text.push_line(Line::from(error.clone()).fg(Color::Red));
+            }
+            text
+        }
+        None if history.entries.is_empty() => Text::from("No query was run yet"),
+        None => Text::from("No query matches the search"),
+    };
+    frame.render_widget(
+        Paragraph::new(preview).wrap(Wrap { trim: false }).block(
+            Block::default()
+                .title("Query")
+                .borders(Borders::ALL)
+                .border_type(BorderType::Thick),
+        ),
+        chunks[2],
+    );
+    state.history.selected = selected;
+    state.history.offset = offset;
+}
+
+// ── File picker ───────────────────────────────────────────────────────────
+
+/// The F8/F9 popup: a path to type with a listing of its directory and the recent files.
+pub struct FilePickerPopup {
+    /// [`FileAction::Save`] or [`FileAction::Load`].
+    pub action: FileAction,
+    pub input: TextArea<'static>,
+    pub picker: FilePicker,
+    /// Existing file waiting for the user to confirm it is overwritten.
+    pub confirm_overwrite: Option<String>,
+    pub error: Option<String>,
+}
+impl FilePickerPopup {
+    fn input_text(&self) -> String {
+        self.input.lines().join("")
+    }
+
+    fn set_input(&mut self, text: &str) {
+        self.input = TextArea::new(vec![text.to_string()]);
+        self.input.set_cursor_line_style(Style::default());
+        self.input.move_cursor(CursorMove::End);
+        self.picker.update(text);
+    }
+}
+
+fn open_file_picker(state: &mut ExtendedAppState, action: FileAction) {
+    let recent = file_picker::load_recent();
+    let separator = std::path::MAIN_SEPARATOR;
+    let file = &state.buffers[state.buffer_index].file;
+    let dir = file
+        .iter()
+        .chain(recent.first())
+        .find_map(|path| std::path::Path::new(path).parent())
+        .map(std::path::Path::to_path_buf)
+        .or_else(|| std::env::current_dir().ok())
+        .map(|dir| {
+            format!(
+                "{}{}",
+                dir.to_string_lossy().trim_end_matches(separator),
+                separator
+            )
+        })
+        .unwrap_or_default();
+    let input = match (&action, file) {
+        (FileAction::Save, Some(path)) => path.clone(),
+        _ => dir,
+    };
+    let mut popup = FilePickerPopup {
+        action,
+        input: TextArea::default(),
+        picker: FilePicker::new(recent, &input),
+        confirm_overwrite: None,
+        error: None,
+    };
+    popup.set_input(&input);
+    state.file_picker = Some(popup);
+}
+
+fn handle_file_picker_key(state: &mut ExtendedAppState, key_event: KeyEvent) {
+    let Some(popup) = &mut state.file_picker else {
+        return;
+    };
+    if let Some(path) = popup.confirm_overwrite.clone() {
+        match key_event.code {
+            KeyCode::Char('y') | KeyCode::Enter => {
+                popup.confirm_overwrite = None;
+                finish_file_picker(state, &path);
+            }
+            KeyCode::Char('n') | KeyCode::Esc => popup.confirm_overwrite = None,
+            _ => {}
+        }
+        return;
+    }
+    let input = popup.input_text();
+    match (key_event.modifiers, key_event.code) {
+        (_, KeyCode::Esc) => state.file_picker = None,
+        (_, KeyCode::Up) => popup.picker.move_selection(-1, &input),
+        (_, KeyCode::Down) => popup.picker.move_selection(1, &input),
+        (_, KeyCode::PageUp) => popup.picker.move_selection(-10, &input),
+        (_, KeyCode::PageDown) => popup.picker.move_selection(10, &input),
+        (_, KeyCode::Tab) => {
+            let completed = match popup.picker.selected_item(&input) {
+                Some(item) if item.is_dir => {
+                    Some(format!("{}{}", item.path, std::path::MAIN_SEPARATOR))
+                }
+                Some(item) => Some(item.path),
+                None => popup.picker.complete(&input),
+            };
+            if let Some(completed) = completed {
+                popup.set_input(&completed);
+            }
+        }
+        (KeyModifiers::CONTROL, KeyCode::Char('a')) => {
+            popup.picker.show_all = !popup.picker.show_all;
+            popup.picker.reload(&input);
+        }
+        (_, KeyCode::Enter) => {
+            let (path, is_dir) = match popup.picker.selected_item(&input) {
+                Some(item) => (item.path, item.is_dir),
+                None => {
+                    let path = file_picker::expand_home(&input);
+                    let is_dir = std::path::Path::new(&path).is_dir();
+                    (path, is_dir)
+                }
+            };
+            if is_dir {
+                let separator = std::path::MAIN_SEPARATOR;
+                popup.set_input(&format!(
+                    "{}{}",
+                    path.trim_end_matches(separator),
+                    separator
+                ));
+                return;
+            }
+            if popup.action == FileAction::Save {
+                let path = file_picker::with_sql_extension(&path);
+                if std::path::Path::new(&path).exists() {
+                    popup.confirm_overwrite = Some(path);
+                    return;
+                }
+                finish_file_picker(state, &path);
+            } else {
+                finish_file_picker(state, &path);
+            }
+        }
+        _ => {
+            if popup.input.input(tui_textarea::Input::from(key_event)) {
+                popup.error = None;
+                popup.picker.update(&popup.input_text());
+            }
+        }
+    }
+}
+
+/// Saves or loads `path` as chosen in the file picker; the picker stays open on errors.
+fn finish_file_picker(state: &mut ExtendedAppState, path: &str) {
+    let Some(popup) = &state.file_picker else {
+        return;
+    };
+    let done = match popup.action {
+        FileAction::Save => save_sql_file(state, path),
+        _ => load_sql_file(state, path),
+    };
+    match done {
+        Ok(()) => state.file_picker = None,
+        Err(e) => {
+            error!("Cannot use {}: {}", path, e);
+            if let Some(popup) = &mut state.file_picker {
+                popup.error = Some(format!("{}: {}", path, e));
+            }
+        }
+    }
+}
+
+/// Writes the active buffer to `path` and makes it the file of the buffer.
+fn save_sql_file(state: &mut ExtendedAppState, path: &str) -> std::io::Result<()> {
+    shared::write_file(path, &state.shared.sql_query)?;
+    info!("Saved {}", path);
+    remember_recent_file(path);
+    let buffer = &mut state.buffers[state.buffer_index];
+    buffer.name = file_label(path);
+    buffer.file = Some(path.to_string());
+    buffer.saved = state.shared.sql_query.clone();
+    Ok(())
+}
+
+/// Opens the file at `path` in the editor, see [`open_in_editor`].
+fn load_sql_file(state: &mut ExtendedAppState, path: &str) -> std::io::Result<()> {
+    let sql = shared::read_file(path)?;
+    info!("Loaded {}", path);
+    remember_recent_file(path);
+    open_in_editor(
+        state,
+        EditorBuffer {
+            file: Some(path.to_string()),
+            ..EditorBuffer::new(file_label(path), sql)
+        },
+    );
+    Ok(())
+}
+
+fn remember_recent_file(path: &str) {
+    if let Err(e) = file_picker::remember_recent(path) {
+        warn!("Cannot update the recent files: {}", e);
+    }
+}
+
+fn file_label(path: &str) -> String {
+    std::path::Path::new(path).file_name().map_or_else(
+        || path.to_string(),
+        |name| name.to_string_lossy().into_owned(),
+    )
+}
+
+fn draw_file_picker(frame: &mut ratatui::Frame, popup: &FilePickerPopup) {
+    let area = frame.area();
+    let area = Rect {
+        x: area.width / 8,
+        y: area.height / 6,
+        width: area.width * 3 / 4,
+        height: area.height * 2 / 3,
+    };
+    frame.render_widget(Clear, area);
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(3),
+            Constraint::Fill(1),
+            Constraint::Length(1),
+        ])
+        .split(area);
+    let title = match popup.action {
+        FileAction::Save => "Save SQL to File (Enter: save or open the Directory, Esc: cancel)",
+        _ => "Load SQL from File (Enter: load or open the Directory, Esc: cancel)",
+    };
+    let mut input = popup.input.clone();
+    input.set_block(
+        Block::default()
+            .borders(Borders::ALL)
+            .border_type(BorderType::Thick)
+            .title(title),
+    );
+    frame.render_widget(&input, chunks[0]);
+
+    let input_text = popup.input_text();
+    let picker = &popup.picker;
+    let filter = if picker.show_all { "all files" } else { ".sql" };
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_type(BorderType::Thick)
+        .title(format!("{} ({})", picker.dir.to_string_lossy(), filter));
+    let height = block.inner(chunks[1]).height as usize;
+    let items = picker.items(&input_text);
+    let mut lines: Vec<Line> = Vec::new();
+    let mut selected_line = 0;
+    for (idx, item) in items.iter().enumerate() {
+        if item.recent && idx == 0 {
+            lines.push(Line::from("Recent files").fg(Color::DarkGray));
+        }
+        if !item.recent && (idx == 0 || items[idx - 1].recent) {
+            lines.push(Line::from("This directory").fg(Color::DarkGray));
+        }
+        let mut line = Line::from(format!("  {}", item.label));
+        if item.is_dir {
+            line = line.fg(Color::Cyan);
+        }
+        if picker.selected == Some(idx) {
+            line = line.style(Style::default().bg(Color::White).fg(Color::Black));
+            selected_line = lines.len();
+        }
+        lines.push(line);
+    }
+    if let Err(e) = &picker.entries {
+        lines.push(Line::from(format!("Cannot list the directory: {}", e)).fg(Color::Red));
+    }
+    let offset = (selected_line + 1).saturating_sub(height);
+    frame.render_widget(
+        Paragraph::new(lines)
+            .scroll((offset as u16, 0))
+            .block(block),
+        chunks[1],
+    );
+    let status = if let Some(path) = &popup.confirm_overwrite {
+        Line::from(format!("{} exists. Overwrite it? (y/n)", path)).fg(Color::Yellow)
+    } else if let Some(error) = &popup.error {
+        Line::from(error.clone()).fg(Color::Red)
+    } else {
+        Line::from("Up/Down: pick, Tab: complete the Path, Ctrl+A: show all Files or only .sql")
+            .fg(Color::DarkGray)
+    };
+    frame.render_widget(Paragraph::new(status).bg(Color::Black), chunks[2]);
+}
+
+// ── Editor buffers ────────────────────────────────────────────────────────
+
+/// An editor buffer. The text and cursor of the active buffer live in `editor_state` and
+/// `shared.sql_query` of [`ExtendedAppState`] while it is shown, and are stored here when
+/// another buffer is shown.
+#[derive(Clone)]
+pub struct EditorBuffer {
+    pub name: String,
+    pub editor_state: EditorState,
+    pub sql: String,
+    /// File the buffer was loaded from or saved to last.
+    pub file: Option<String>,
+    /// Text as it was loaded or saved last, to tell unsaved changes.
+    pub saved: String,
+    /// Credential and database the buffer runs with; showing the buffer selects them.
+    pub binding: Option<(String, String)>,
+    pub executed_range: Option<(Index2, Index2)>,
+    pub library_query: Option<library::SavedQuery>,
+}
+impl EditorBuffer {
+    fn new(name: String, sql: String) -> Self {
+        EditorBuffer {
+            name,
+            editor_state: EditorState::new(Lines::from(sql.as_str())),
+            saved: sql.clone(),
+            sql,
+            file: None,
+            binding: None,
+            executed_range: None,
+            library_query: None,
+        }
+    }
+}
+
+/// What waits for the user to confirm that unsaved changes are dropped.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Confirm {
+    Quit,
+    CloseBuffer,
+}
+
+fn buffer_dirty(state: &ExtendedAppState, idx: usize) -> bool {
+    let buffer = &state.buffers[idx];
+    let sql = match idx == state.buffer_index {
+        true => &state.shared.sql_query,
+        false => &buffer.sql,
+    };
+    *sql != buffer.saved
+}
+
+/// Name of a buffer in the tab bar, with the database it is bound to and a `*` for changes.
+fn buffer_label(state: &ExtendedAppState, idx: usize) -> String {
+    let buffer = &state.buffers[idx];
+    let mut label = buffer.name.clone();
+    if let Some((_, db)) = &buffer.binding {
+        label.push_str(&format!("@{}", db));
+    }
+    if buffer_dirty(state, idx) {
+        label.push('*');
+    }
+    label
+}
+
+fn new_buffer_name(state: &ExtendedAppState) -> String {
+    (1..)
+        .map(|n| format!("Query {}", n))
+        .find(|name| state.buffers.iter().all(|buffer| buffer.name != *name))
+        .unwrap_or_default()
+}
+
+/// Keeps the text and cursor of the active buffer before another one is shown.
+fn store_active_buffer(state: &mut ExtendedAppState) {
+    let buffer = &mut state.buffers[state.buffer_index];
+    buffer.editor_state = state.editor_state.clone();
+    buffer.sql = state.shared.sql_query.clone();
+    buffer.executed_range = state.executed_range;
+    buffer.library_query = state.library_query.clone();
+    // a bound buffer follows F3/F4 while it is shown
+    if buffer.binding.is_some() {
+        buffer.binding = Some((state.shared.user.name.clone(), state.shared.db.clone()));
+    }
+}
+
+/// Shows the buffer at `idx` in the SQL Editor and selects the credential it is bound to.
+fn show_buffer(state: &mut ExtendedAppState, idx: usize) {
+    if idx >= state.buffers.len() {
+        return;
+    }
+    if idx != state.buffer_index {
+        store_active_buffer(state);
+        state.buffer_index = idx;
+    }
+    let buffer = state.buffers[idx].clone();
+    state.editor_state = buffer.editor_state;
+    state.shared.sql_query = buffer.sql;
+    state.executed_range = buffer.executed_range;
+    state.library_query = buffer.library_query;
+    state.completion = None;
+    state.shared.current_tab = shared::Tab::SqlEditor;
+    if let Some((user, db)) = buffer.binding {
+        if !state.shared.set_user(&user) {
+            warn!(
+                "{} is bound to user \"{}\", which is not in the config",
+                buffer.name, user
+            );
+        } else if db != state.shared.db {
+            select_database(state, db);
+        }
+    }
+}
+
+fn open_buffer(state: &mut ExtendedAppState, buffer: EditorBuffer) {
+    store_active_buffer(state);
+    state.buffers.push(buffer);
+    let idx = state.buffers.len() - 1;
+    show_buffer(state, idx);
+}
+
+/// Shows `buffer` in place of the active one, or next to it when the active one has unsaved
+/// changes.
+fn open_in_editor(state: &mut ExtendedAppState, buffer: EditorBuffer) {
+    if buffer_dirty(state, state.buffer_index) {
+        open_buffer(state, buffer);
+    } else {
+        state.buffers[state.buffer_index] = buffer;
+        show_buffer(state, state.buffer_index);
+    }
+}
+
+/// Closes the active buffer; the last one is replaced by an empty buffer.
+fn close_buffer(state: &mut ExtendedAppState) {
+    let closed = state.buffers.remove(state.buffer_index);
+    info!("Closed {}", closed.name);
+    if state.buffers.is_empty() {
+        let name = new_buffer_name(state);
+        state.buffers.push(EditorBuffer::new(name, String::new()));
+    }
+    state.buffer_index = state.buffer_index.min(state.buffers.len() - 1);
+    show_buffer(state, state.buffer_index);
+}
+
+/// Handles the buffer keys before the editor sees them, as it takes Alt+Left for Left.
+///
+/// Returns `true` if the key was used up.
+fn handle_buffer_key(state: &mut ExtendedAppState, key_event: KeyEvent) -> bool {
+    let count = state.buffers.len();
+    match (key_event.modifiers, key_event.code) {
+        (KeyModifiers::CONTROL, KeyCode::Char('n')) => {
+            let name = new_buffer_name(state);
+            open_buffer(state, EditorBuffer::new(name, String::new()));
+        }
+        (KeyModifiers::CONTROL, KeyCode::Char('w')) => {
+            if buffer_dirty(state, state.buffer_index) {
+                state.confirm = Some(Confirm::CloseBuffer);
+            } else {
+                close_buffer(state);
+            }
+        }
+        (KeyModifiers::CONTROL, KeyCode::Char('g')) => {
+            let binding = Some((state.shared.user.name.clone(), state.shared.db.clone()));
+            let buffer = &mut state.buffers[state.buffer_index];
+            buffer.binding = match buffer.binding {
+                Some(_) => None,
+                None => binding,
+            };
+            match &buffer.binding {
+                Some((user, db)) => info!("{} runs as {}@{}", buffer.name, user, db),
+                None => info!("{} runs with the selected user", buffer.name),
+            }
+        }
+        (KeyModifiers::ALT, KeyCode::Right) => {
+            show_buffer(state, (state.buffer_index + 1) % count);
+        }
+        (KeyModifiers::ALT, KeyCode::Left) => {
+            show_buffer(state, (state.buffer_index + count - 1) % count);
+        }
+        (KeyModifiers::ALT, KeyCode::Char(c @ '1'..='9')) => {
+            show_buffer(state, c as usize - '1' as usize);
+        }
+        _ => return false,
+    }
+    true
+}
+
+/// Whether the app may quit now; asks first when a buffer has unsaved changes.
+fn may_quit(state: &mut ExtendedAppState) -> bool {
+    if state.quit_confirmed || !(0..state.buffers.len()).any(|idx| buffer_dirty(state, idx)) {
+        return true;
+    }
+    state.confirm = Some(Confirm::Quit);
+    false
+}
+
+// ── Query library ─────────────────────────────────────────────────────────
+
+/// The Ctrl+L popup listing the saved queries.
+pub struct LibraryBrowser {
+    pub dir: String,
+    pub queries: Vec<library::SavedQuery>,
+    pub filter: TextArea<'static>,
+    /// Index into [`LibraryBrowser::matches`].
+    pub selected: usize,
+    /// Why the library could not be read.
+    pub error: Option<String>,
+}
+impl LibraryBrowser {
+    fn matches(&self) -> Vec<&library::SavedQuery> {
+        library::matches(&self.queries, &self.filter.lines().join(""))
+    }
+
+    fn selected_query(&self) -> Option<library::SavedQuery> {
+        self.matches()
+            .get(self.selected)
+            .map(|query| (*query).clone())
+    }
+}
+
+/// Labels of the fields of [`LibrarySaveForm`], in order.
+const LIBRARY_FIELDS: [&str; 5] = [
+    "Name (folders with /, e.g. reports/Active users)",
+    "Description",
+    "Tags (comma separated)",
+    "Credential to run with (optional)",
+    "Database to run in (optional)",
+];
+
+/// The Ctrl+S form that saves the editor content to the library.
+pub struct LibrarySaveForm {
+    pub dir: String,
+    /// Filled in in the order of [`LIBRARY_FIELDS`].
+    pub fields: Vec<TextArea<'static>>,
+    pub focused: usize,
+    /// Why the query could not be saved yet.
+    pub error: Option<String>,
+    /// File of another query waiting for the user to confirm it is overwritten.
+    pub confirm_overwrite: Option<String>,
+}
+impl LibrarySaveForm {
+    fn value(&self, field: usize) -> String {
+        self.fields[field].lines().join("").trim().to_string()
+    }
+}
+
+fn library_dir(state: &ExtendedAppState) -> Result<String, shared::SimpleSqlError> {
+    library::library_dir(&state.shared.config.lock().unwrap())
+}
+
+fn open_library(state: &mut ExtendedAppState) {
+    let loaded = library_dir(state).and_then(|dir| {
+        let queries = library::load(&dir)?;
+        Ok((dir, queries))
+    });
+    let (dir, queries, error) = match loaded {
+        Ok((dir, queries)) => (dir, queries, None),
+        Err(e) => {
+            warn!("Cannot read the query library: {}", e);
+            (String::new(), Vec::new(), Some(e.to_string()))
+        }
+    };
+    let mut filter = TextArea::default();
+    filter.set_cursor_line_style(Style::default());
+    filter.set_block(
+        Block::default()
+            .borders(Borders::ALL)
+            .border_type(BorderType::Thick)
+            .title("Query Library (type to search, Enter: load, Ctrl+R: run, Esc: close)"),
+    );
+    state.library = Some(LibraryBrowser {
+        dir,
+        queries,
+        filter,
+        selected: 0,
+        error,
+    });
+}
+
+fn handle_library_key(state: &mut ExtendedAppState, key_event: KeyEvent) {
+    let Some(browser) = &mut state.library else {
+        return;
+    };
+    let count = browser.matches().len();
+    match (key_event.modifiers, key_event.code) {
+        (_, KeyCode::Esc) => state.library = None,
+        (_, KeyCode::Up) => browser.selected = browser.selected.saturating_sub(1),
+        (_, KeyCode::Down) => {
+            browser.selected = (browser.selected + 1).min(count.saturating_sub(1))
+        }
+        (_, KeyCode::Enter) => {
+            let Some(query) = browser.selected_query() else {
+                return;
+            };
+            state.library = None;
+            open_in_editor(
+                state,
+                EditorBuffer::new(query.name.clone(), query.sql.clone()),
+            );
+            use_saved_target(state, &query);
+            if query.user.is_some() || query.database.is_some() {
+                state.buffers[state.buffer_index].binding =
+                    Some((state.shared.user.name.clone(), state.shared.db.clone()));
+            }
+            info!("Loaded {} from the query library", query.id);
+            state.library_query = Some(query);
+        }
+        (KeyModifiers::CONTROL, KeyCode::Char('r')) => {
+            let Some(query) = browser.selected_query() else {
+                return;
+            };
+            state.library = None;
+            use_saved_target(state, &query);
+            state.executed_range = None;
+            start_query(state, query.sql);
+            state.shared.current_tab = shared::Tab::TableView;
+        }
+        _ => {
+            if browser.filter.input(tui_textarea::Input::from(key_event)) {
+                browser.selected = 0;
+            }
+        }
+    }
+}
+
+/// Switches to the credential and database saved with `query`, if it has them.
+fn use_saved_target(state: &mut ExtendedAppState, query: &library::SavedQuery) {
+    if let Some(user) = &query.user {
+        if !state.shared.set_user(user) {
+            warn!(
+                "{} is saved for user \"{}\", which is not in the config",
+                query.id, user
+            );
+            return;
+        }
+        info!("Switched to user {} for {}", user, query.id);
+    }
+    if let Some(db) = &query.database
+        && *db != state.shared.db
+    {
+        select_database(state, db.clone());
+    }
+}
+
+fn open_library_save(state: &mut ExtendedAppState) {
+    let dir = match library_dir(state) {
+        Ok(dir) => dir,
+        Err(e) => return show_error(state, e),
+    };
+    let values = match &state.library_query {
+        Some(query) => [
+            match query.folder() {
+                "" => query.name.clone(),
+                folder => format!("{}/{}", folder, query.name),
+            },
+            query.description.clone(),
+            query.tags.join(", "),
+            query.user.clone().unwrap_or_default(),
+            query.database.clone().unwrap_or_default(),
+        ],
+        None => [
+            String::new(),
+            String::new(),
+            String::new(),
+            state.shared.user.name.clone(),
+            state.shared.db.clone(),
+        ],
+    };
+    let fields = values
+        .into_iter()
+        .map(|value| {
+            let mut field = TextArea::new(vec![value]);
+            field.set_cursor_line_style(Style::default());
+            field.move_cursor(CursorMove::End);
+            field
+        })
+        .collect();
+    state.library_save = Some(LibrarySaveForm {
+        dir,
+        fields,
+        focused: 0,
+        error: None,
+        confirm_overwrite: None,
+    });
+}
+
+fn handle_library_save_key(state: &mut ExtendedAppState, key_event: KeyEvent) {
+    let Some(form) = &mut state.library_save else {
+        return;
+    };
+    if form.confirm_overwrite.is_some() {
+        match key_event.code {
+            KeyCode::Char('y') | KeyCode::Enter => {
+                form.confirm_overwrite = None;
+                save_to_library(state, true);
+            }
+            KeyCode::Char('n') | KeyCode::Esc => form.confirm_overwrite = None,
+            _ => {}
+        }
+        return;
+    }
+    match key_event.code {
+        KeyCode::Esc => state.library_save = None,
+        KeyCode::Tab | KeyCode::Down => form.focused = (form.focused + 1) % form.fields.len(),
+        KeyCode::BackTab | KeyCode::Up => {
+            form.focused = (form.focused + form.fields.len() - 1) % form.fields.len()
+        }
+        KeyCode::Enter => save_to_library(state, false),
+        _ => {
+            form.fields[form.focused].input(tui_textarea::Input::from(key_event));
+        }
+    }
+}
+
+/// Saves the SQL Editor with the values of the form. Unless `overwrite` is set, the user has to
+/// confirm first when the file belongs to another query than the loaded one.
+fn save_to_library(state: &mut ExtendedAppState, overwrite: bool) {
+    let Some(form) = &mut state.library_save else {
+        return;
+    };
+    let path = form.value(0);
+    let id = library::id_for(&path);
+    if id.is_empty() {
+        form.error = Some("The query needs a name".to_string());
+        form.focused = 0;
+        return;
+    }
+    let optional = |value: String| Some(value).filter(|value| !value.is_empty());
+    let query = library::SavedQuery {
+        name: path
+            .rsplit('/')
+            .next()
+            .unwrap_or_default()
+            .trim()
+            .to_string(),
+        description: form.value(1),
+        tags: form
+            .value(2)
+            .split(',')
+            .map(|tag| tag.trim().to_string())
+            .filter(|tag| !tag.is_empty())
+            .collect(),
+        user: optional(form.value(3)),
+        database: optional(form.value(4)),
+        sql: state.shared.sql_query.clone(),
+        id,
+    };
+    let path = library::path(&form.dir, &query.id);
+    if !overwrite
+        && std::path::Path::new(&path).exists()
+        && state
+            .library_query
+            .as_ref()
+            .is_none_or(|loaded| loaded.id != query.id)
+    {
+        form.confirm_overwrite = Some(path);
+        return;
+    }
+    match library::save(&form.dir, &query) {
+        Ok(path) => {
+            info!("Saved {} to {}", query.id, path);
+            state.library_save = None;
+            let buffer = &mut state.buffers[state.buffer_index];
+            buffer.saved = query.sql.clone();
+            if buffer.file.is_none() {
+                buffer.name = query.name.clone();
+            }
+            state.library_query = Some(query);
+        }
+        Err(e) => {
+            error!("Cannot save {}: {}", query.id, e);
+            form.error = Some(e.to_string());
+        }
+    }
+}
+
+fn draw_library(frame: &mut ratatui::Frame, browser: &LibraryBrowser) {
+    let area = frame.area();
+    let area = Rect {
+        x: area.width / 10,
+        y: area.height / 6,
+        width: area.width * 4 / 5,
+        height: area.height * 2 / 3,
+    };
+    frame.render_widget(Clear, area);
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Length(3), Constraint::Fill(1)])
+        .split(area);
+    frame.render_widget(&browser.filter, chunks[0]);
+    let columns = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(45), Constraint::Fill(1)])
+        .split(chunks[1]);
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_type(BorderType::Thick)
+        .title(format!("Saved Queries in {}", browser.dir));
+    let matches = browser.matches();
+    let lines: Vec<Line> = if let Some(error) = &browser.error {
+        vec![Line::from(error.clone()).fg(Color::Red)]
+    } else if browser.queries.is_empty() {
+        vec![Line::from(
+            "No saved queries yet, save the SQL Editor with Ctrl+S.",
+        )]
+    } else if matches.is_empty() {
+        vec![Line::from("No saved query matches the search.")]
+    } else {
+        let height = block.inner(columns[0]).height as usize;
+        let offset = (browser.selected + 1).saturating_sub(height);
+        matches
+            .iter()
+            .enumerate()
+            .skip(offset)
+            .take(height)
+            .map(|(idx, query)| {
+                let mut spans = vec![];
+                if !query.folder().is_empty() {
+                    spans.push(Span::styled(
+                        format!("{}/", query.folder()),
+                        Style::default().fg(Color::DarkGray),
+                    ));
+                }
+                spans.push(Span::raw(query.name.clone()));
+                if !query.tags.is_empty() {
+                    spans.push(Span::styled(
+                        format!(" [{}]", query.tags.join(", ")),
+                        Style::default().fg(Color::Cyan),
+                    ));
+                }
+                let line = Line::from(spans);
+                if idx == browser.selected {
+                    line.style(Style::default().bg(Color::White).fg(Color::Black))
+                } else {
+                    line
+                }
+            })
+            .collect()
+    };
+    frame.render_widget(Paragraph::new(lines).block(block), columns[0]);
+    let preview = match matches.get(browser.selected) {
+        Some(query) => {
+            let mut text = Text::from(Line::from(query.name.clone()).bold());
+            if !query.description.is_empty() {
+                text.push_line(Line::from(query.description.clone()));
+            }
+            if !query.target().is_empty() {
+                text.push_line(Line::from(format!("Runs as {}", query.target())).fg(Color::Cyan));
+            }
+            text.push_line(Line::from(""));
+            for line in query.sql.lines() {
+                text.push_line(Line::from(line.to_string()));
+            }
+            text
+        }
+        None => Text::from(""),
+    };
+    frame.render_widget(
+        Paragraph::new(preview).wrap(Wrap { trim: false }).block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_type(BorderType::Thick)
+                .title("Query"),
+        ),
+        columns[1],
+    );
+}
+
+fn draw_library_save(frame: &mut ratatui::Frame, form: &LibrarySaveForm) {
+    let area = frame.area();
+    let height = form.fields.len() as u16 * 3 + 3;
+    let area = Rect {
+        x: area.width / 6,
+        y: area.height.saturating_sub(height) / 2,
+        width: area.width * 2 / 3,
+        height: height.min(area.height),
+    };
+    frame.render_widget(Clear, area);
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_type(BorderType::Thick)
+        .title("Save to the Query Library (Tab: next field, Enter: save, Esc: cancel)");
+    let inner = block.inner(area);
+    frame.render_widget(block, area);
+    let mut constraints = vec![Constraint::Length(3); form.fields.len()];
+    constraints.push(Constraint::Length(1));
+    let rows = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints(constraints)
+        .split(inner);
+    for (idx, field) in form.fields.iter().enumerate() {
+        let mut field = field.clone();
+        let border_color = if idx == form.focused {
+            Color::Cyan
+        } else {
+            Color::White
+        };
+        field.set_block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(border_color))
+                .title(LIBRARY_FIELDS[idx]),
+        );
+        if idx != form.focused {
+            field.set_cursor_style(Style::default());
+        }
+        frame.render_widget(&field, rows[idx]);
+    }
+    let status = match (&form.confirm_overwrite, &form.error) {
+        (Some(path), _) => {
+            Line::from(format!("{} exists. Overwrite it? (y/n)", path)).fg(Color::Yellow)
+        }
+        (None, Some(error)) => Line::from(error.clone()).fg(Color::Red),
+        (None, None) => Line::from(format!(
+            "Saved as {}/{}.sql",
+            form.dir,
+            library::id_for(&form.value(0))
+        ))
+        .fg(Color::DarkGray),
+    };
+    frame.render_widget(Paragraph::new(status), rows[form.fields.len()]);
+}
+
+// ── Database picker ───────────────────────────────────────────────────────
+
+/// The F3 popup listing the databases of the selected credential.
+pub struct DatabasePicker {
+    pub databases: Vec<String>,
+    pub filter: TextArea<'static>,
+    /// Index into [`DatabasePicker::matches`].
+    pub selected: usize,
+    /// Why the databases could not be listed; a typed name can still be used.
+    pub error: Option<String>,
+    /// The databases are still being listed.
+    pub loading: bool,
+}
+impl DatabasePicker {
+    fn new(databases: Vec<String>, current: &str, error: Option<String>) -> Self {
+        let mut filter = TextArea::default();
+        filter.set_cursor_line_style(Style::default());
+        filter.set_block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_type(BorderType::Thick)
+                .title("Database (type to search, Enter to use, Esc to cancel)"),
+        );
+        let selected = databases.iter().position(|db| db == current).unwrap_or(0);
+        DatabasePicker {
+            databases,
+            filter,
+            selected,
+            error,
+            loading: false,
+        }
+    }
+
+    fn filter_text(&self) -> String {
+        self.filter.lines().join("")
+    }
+
+    /// Databases containing the filter text, ignoring case.
+    fn matches(&self) -> Vec<&str> {
+        let filter = self.filter_text().to_lowercase();
+        self.databases
+            .iter()
+            .filter(|db| db.to_lowercase().contains(&filter))
+            .map(String::as_str)
+            .collect()
+    }
+
+    /// The selected database, or the typed name when nothing matches.
+    fn choice(&self) -> Option<String> {
+        match self.matches().get(self.selected) {
+            Some(db) => Some(db.to_string()),
+            None => Some(self.filter_text().trim().to_string()).filter(|db| !db.is_empty()),
+        }
+    }
+}
+
+/// Opens the F3 popup at once and lists the databases in the background.
+fn open_db_picker(state: &mut ExtendedAppState) {
+    let mut picker = DatabasePicker::new(Vec::new(), &state.shared.db, None);
+    picker.loading = true;
+    state.db_picker = Some(picker);
+    let shared_state = state.shared.clone();
+    let handle = spawn_load(state, async move {
+        shared::list_databases_async(&shared_state).await
+    });
+    if let Some(previous) = state.db_picker_loading.replace(handle) {
+        previous.abort();
+    }
+}
+
+/// Fills the F3 popup with the databases once they are listed.
+fn poll_db_picker(state: &mut ExtendedAppState) {
+    let Some(listed) = finished_load(&state.runtime, &mut state.db_picker_loading) else {
+        return;
+    };
+    let Some(picker) = &mut state.db_picker else {
+        return;
+    };
+    let listed = listed
+        .map_err(|e| e.to_string())
+        .and_then(|listed| listed.map_err(|e| e.to_string()));
+    match listed {
+        Ok(databases) => {
+            picker.selected = databases
+                .iter()
+                .position(|db| *db == state.shared.db)
+                .unwrap_or(0);
+            picker.databases = databases;
+        }
+        Err(e) => {
+            warn!("Cannot list databases: {}", e);
+            picker.error = Some(e);
+        }
+    }
+    picker.loading = false;
+}
+
+fn handle_db_picker_key(state: &mut ExtendedAppState, key_event: KeyEvent) {
+    let Some(picker) = &mut state.db_picker else {
+        return;
+    };
+    match key_event.code {
+        KeyCode::Esc => {
+            state.db_picker = None;
+            if let Some(loading) = state.db_picker_loading.take() {
+                loading.abort();
+            }
+        }
+        KeyCode::Up => picker.selected = picker.selected.saturating_sub(1),
+        KeyCode::Down => {
+            picker.selected = (picker.selected + 1).min(picker.matches().len().saturating_sub(1))
+        }
+        KeyCode::Enter => {
+            let choice = picker.choice();
+            state.db_picker = None;
+            if let Some(db) = choice {
+                select_database(state, db);
+            }
+        }
+        _ => {
+            if picker.filter.input(tui_textarea::Input::from(key_event)) {
+                picker.selected = 0;
+            }
+        }
+    }
+}
+
+/// Connects to `db` from now on and remembers it for the selected credential.
+fn select_database(state: &mut ExtendedAppState, db: String) {
+    info!("Switching to database {}", db);
+    state.shared.use_database(db);
+    if state.show_schema_browser {
+        refresh_schema_browser(state);
+    }
+}
+
+fn draw_db_picker(frame: &mut ratatui::Frame, picker: &DatabasePicker, current: &str) {
+    let area = frame.area();
+    let area = Rect {
+        x: area.width / 4,
+        y: area.height / 5,
+        width: area.width / 2,
+        height: area.height * 3 / 5,
+    };
+    frame.render_widget(Clear, area);
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Length(3), Constraint::Fill(1)])
+        .split(area);
+    frame.render_widget(&picker.filter, chunks[0]);
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_type(BorderType::Thick)
+        .title("Databases");
+    let matches = picker.matches();
+    let lines: Vec<Line> = if let Some(error) = &picker.error {
+        vec![
+            Line::from(error.clone()).fg(Color::Red),
+            Line::from("Type the name of the database and press Enter."),
+        ]
+    } else if picker.loading && picker.databases.is_empty() {
+        vec![Line::from("Listing the databases…").fg(Color::DarkGray)]
+    } else if matches.is_empty() {
+        vec![Line::from(
+            "No database matches, Enter uses the typed name.",
+        )]
+    } else {
+        let height = block.inner(chunks[1]).height as usize;
+        let offset = (picker.selected + 1).saturating_sub(height);
+        matches
+            .iter()
+            .enumerate()
+            .skip(offset)
+            .take(height)
+            .map(|(idx, db)| {
+                let marker = if *db == current { "* " } else { "  " };
+                let line = Line::from(format!("{}{}", marker, db));
+                if idx == picker.selected {
+                    line.style(Style::default().bg(Color::White).fg(Color::Black))
+                } else {
+                    line
+                }
+            })
+            .collect()
+    };
+    frame.render_widget(Paragraph::new(lines).block(block), chunks[1]);
+}
+
+// ── Schema browser ────────────────────────────────────────────────────────
+
+/// Reloads the databases and tables of the active credential into the schema browser in the
+/// background; [`poll_schema_browser`] takes them when they are there.
+fn refresh_schema_browser(state: &mut ExtendedAppState) {
+    let shared_state = state.shared.clone();
+    let key = state.shared.pool_key();
+    let load = async move {
+        let tables = shared::describe_schema_async(&shared_state).await?;
+        // servers that hide other databases still show the tables
+        let databases = shared::list_databases_async(&shared_state)
+            .await
+            .unwrap_or_else(|e| {
+                warn!("Cannot list databases: {}", e);
+                Vec::new()
+            });
+        Ok::<_, shared::SimpleSqlError>((key, databases, tables))
+    };
+    let handle = spawn_load(state, load);
+    if let Some(previous) = state.schema_browser_loading.replace(handle) {
+        previous.abort();
+    }
+}
+
+/// Shows the databases and tables of a finished schema browser load.
+fn poll_schema_browser(state: &mut ExtendedAppState) {
+    let Some(loaded) = finished_load(&state.runtime, &mut state.schema_browser_loading) else {
+        return;
+    };
+    match loaded.map_err(|e| e.to_string()) {
+        Ok(Ok((key, databases, tables))) => {
+            info!("Schema browser loaded {} table(s)", tables.len());
+            let db = key.db.clone();
+            state.completion_schema = Some((key, tables.clone()));
+            state.schema_browser.load(databases, db, tables);
+        }
+        Ok(Err(e)) => {
+            warn!("Cannot load the schema: {}", e);
+            state.schema_browser.error = Some(e.to_string());
+        }
+        Err(e) => {
+            warn!("Loading the schema failed: {}", e);
+            state.schema_browser.error = Some(e);
+        }
+    }
+}
+
+fn handle_schema_browser_key(state: &mut ExtendedAppState, key_event: KeyEvent) {
+    let dialect = state.shared.dialect();
+    let browser = &mut state.schema_browser;
+    if key_event.modifiers == KeyModifiers::CONTROL {
+        if key_event.code == KeyCode::Char('b') {
+            state.show_schema_browser = false;
+            state.schema_browser_focused = false;
+        }
+        return;
+    }
+    match key_event.code {
+        KeyCode::Esc => state.schema_browser_focused = false,
+        KeyCode::Up => browser.move_selection(-1),
+        KeyCode::Down => browser.move_selection(1),
+        KeyCode::PageUp => browser.move_selection(-10),
+        KeyCode::PageDown => browser.move_selection(10),
+        KeyCode::Right => browser.expand(),
+        KeyCode::Left => browser.collapse(),
+        KeyCode::Char('r') => refresh_schema_browser(state),
+        KeyCode::Enter | KeyCode::Char(' ') => match browser.selected_item() {
+            Some(item) if item.expandable => browser.toggle(),
+            Some(schema_browser::TreeItem {
+                node: schema_browser::Node::Database(db),
+                ..
+            }) => {
+                select_database(state, db);
+            }
+            _ => {}
+        },
+        KeyCode::Char('s') => {
+            let sql = browser
+                .selected_item()
+                .and_then(|item| browser.select_sql(&item.node, dialect));
+            if let Some(sql) = sql {
+                state.executed_range = None;
+                start_query(state, sql);
+                state.shared.current_tab = shared::Tab::TableView;
+            }
+        }
+        KeyCode::Char('i') => {
+            if let Some(item) = browser.selected_item() {
+                let text = browser.insert_text(&item.node, dialect);
+                insert_into_editor(state, &text);
+                state.schema_browser_focused = false;
+                state.shared.current_tab = shared::Tab::SqlEditor;
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Inserts `text` at the editor cursor and moves the cursor behind it.
+fn insert_into_editor(state: &mut ExtendedAppState, text: &str) {
+    let offset = byte_offset(&state.shared.sql_query, state.editor_state.cursor);
+    replace_in_editor(state, offset, offset, text);
+}
+
+/// Replaces the bytes `start..end` of the editor text and moves the cursor behind `text`.
+fn replace_in_editor(state: &mut ExtendedAppState, start: usize, end: usize, text: &str) {
+    let mut script = state.shared.sql_query.clone();
+    script.replace_range(start..end, text);
+    state.editor_state.lines = Lines::from(script.as_str());
+    state.editor_state.cursor = editor_index(&script, start + text.len());
+    state.shared.sql_query = script;
+    state.executed_range = None;
+}
+
+fn draw_schema_browser(frame: &mut ratatui::Frame, area: Rect, state: &mut ExtendedAppState) {
+    let border_color = if state.schema_browser_focused {
+        Color::Cyan
+    } else {
+        Color::White
+    };
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_type(BorderType::Thick)
+        .border_style(Style::default().fg(border_color))
+        .title(match state.schema_browser_loading {
+            Some(_) => "Schema Browser (loading…)",
+            None => "Schema Browser",
+        });
+    let inner = block.inner(area);
+    frame.render_widget(block, area);
+    let browser = &mut state.schema_browser;
+    if let Some(error) = &browser.error {
+        frame.render_widget(
+            Paragraph::new(format!("{}\n\nPress r to retry", error))
+                .wrap(Wrap { trim: false })
+                .style(Style::default().fg(Color::Red)),
+            inner,
+        );
+        return;
+    }
+    let items = browser.items();
+    let height = inner.height as usize;
+    if browser.selected < browser.offset {
+        browser.offset = browser.selected;
+    } else if browser.selected >= browser.offset + height {
+        browser.offset = browser.selected + 1 - height;
+    }
+    let lines: Vec<Line> = items
+        .iter()
+        .enumerate()
+        .skip(browser.offset)
+        .take(height)
+        .map(|(idx, item)| {
+            let marker = match (item.expandable, item.expanded) {
+                (true, true) => "▾ ",
+                (true, false) => "▸ ",
+                _ => "  ",
+            };
+            let mut line = Line::from(vec![
+                Span::raw(format!("{}{}", "  ".repeat(item.depth), marker)),
+                Span::raw(item.label.clone()),
+                Span::raw(" "),
+                Span::styled(item.detail.clone(), Style::default().fg(Color::DarkGray)),
+            ]);
+            if idx == browser.selected && state.schema_browser_focused {
+                line = line.style(Style::default().bg(Color::White).fg(Color::Black));
+            } else if idx == browser.selected {
+                line = line.style(Style::default().add_modifier(Modifier::UNDERLINED));
+            }
+            line
+        })
+        .collect();
+    frame.render_widget(Paragraph::new(lines), inner);
+}
+
+// ── Completion ────────────────────────────────────────────────────────────
+
+/// Handles a key while the completion popup is open, or Ctrl+Space to open it.
+///
+/// Returns `true` if the key was used up; other keys go on to the editor.
+fn handle_completion_key(state: &mut ExtendedAppState, key_event: KeyEvent) -> bool {
+    if state.shared.current_tab != shared::Tab::SqlEditor {
+        return false;
+    }
+    if key_event.modifiers == KeyModifiers::CONTROL && key_event.code == KeyCode::Char(' ') {
+        if state.editor_state.mode != EditorMode::Insert {
+            SwitchMode(EditorMode::Insert).execute(&mut state.editor_state);
+        }
+        open_completion(state, 0);
+        return true;
+    }
+    let Some(popup) = &mut state.completion else {
+        return false;
+    };
+    let count = popup.completion.suggestions.len();
+    match (key_event.modifiers, key_event.code) {
+        (_, KeyCode::Down) => {
+            popup.selected = (popup.selected + 1) % count;
+            popup.navigated = true;
+        }
+        (_, KeyCode::Up) => {
+            popup.selected = (popup.selected + count - 1) % count;
+            popup.navigated = true;
+        }
+        (_, KeyCode::Tab) => accept_completion(state),
+        (_, KeyCode::Enter) if popup.navigated => accept_completion(state),
+        (_, KeyCode::Esc) => state.completion = None,
+        (KeyModifiers::CONTROL, KeyCode::Char('r')) => {
+            reload_completion_schema(state);
+            open_completion(state, 0);
+        }
+        _ => return false,
+    }
+    true
+}
+
+/// Follows the typing in insert mode: opens the popup on a word or after a `.`, narrows it
+/// down while it is open and closes it on anything else.
+fn update_completion(state: &mut ExtendedAppState, key_event: KeyEvent) {
+    if state.shared.current_tab != shared::Tab::SqlEditor
+        || state.editor_state.mode != EditorMode::Insert
+        || key_event
+            .modifiers
+            .intersects(KeyModifiers::CONTROL | KeyModifiers::ALT)
+    {
+        state.completion = None;
+        return;
+    }
+    let is_open = state.completion.is_some();
+    match key_event.code {
+        KeyCode::Char(c) if c.is_alphanumeric() || c == '_' || c == '.' => {
+            let min_prefix = if is_open { 0 } else { completion::MIN_PREFIX };
+            open_completion(state, min_prefix);
+        }
+        KeyCode::Backspace if is_open => open_completion(state, 1),
+        _ => state.completion = None,
+    }
+}
+
+/// Computes the suggestions at the cursor; the popup closes when there are none.
+fn open_completion(state: &mut ExtendedAppState, min_prefix: usize) {
+    let script = state.shared.sql_query.clone();
+    let offset = byte_offset(&script, state.editor_state.cursor);
+    let dialect = state.shared.dialect();
+    let tables = completion_schema(state);
+    let completion = completion::complete(&script, offset, dialect, tables, min_prefix);
+    // keep the highlighted entry while the list narrows down
+    let previous = state.completion.take().and_then(|popup| {
+        let suggestion = popup.completion.suggestions.get(popup.selected)?.clone();
+        Some((suggestion, popup.navigated))
+    });
+    state.completion = completion.map(|completion| {
+        let (selected, navigated) = match previous {
+            Some((suggestion, navigated)) => (
+                completion
+                    .suggestions
+                    .iter()
+                    .position(|s| *s == suggestion)
+                    .unwrap_or(0),
+                navigated,
+            ),
+            None => (0, false),
+        };
+        CompletionPopup {
+            completion,
+            selected,
+            navigated,
+        }
+    });
+}
+
+fn accept_completion(state: &mut ExtendedAppState) {
+    if let Some(popup) = state.completion.take()
+        && let Some(suggestion) = popup.completion.suggestions.get(popup.selected)
+    {
+        let (start, end) = (popup.completion.start, popup.completion.end);
+        replace_in_editor(state, start, end, &suggestion.text);
+    }
+}
+
+/// Tables of the active pool for the completion. They are loaded in the background on first
+/// use; until they arrive only keywords and functions are suggested.
+fn completion_schema(state: &mut ExtendedAppState) -> &[shared::SchemaTable] {
+    let key = state.shared.pool_key();
+    let cached = matches!(&state.completion_schema, Some((cached, _)) if *cached == key);
+    let loading = matches!(&state.completion_loading, Some((loading, _)) if *loading == key);
+    if !cached && !loading {
+        reload_completion_schema(state);
+    }
+    match &state.completion_schema {
+        Some((cached, tables)) if *cached == key => tables,
+        _ => &[],
+    }
+}
+
+/// Starts fetching the tables and columns for the completion again, e.g. after a CREATE TABLE.
+fn reload_completion_schema(state: &mut ExtendedAppState) {
+    let key = state.shared.pool_key();
+    if !state.shared.has_connection() {
+        state.completion_schema = Some((key, Vec::new()));
+        return;
+    }
+    let shared_state = state.shared.clone();
+    let load = async move { shared::describe_schema_async(&shared_state).await };
+    let handle = match &state.runtime {
+        Some(runtime) => runtime.spawn(load),
+        None => shared::runtime().spawn(load),
+    };
+    state.completion_loading = Some((key, handle));
+}
+
+/// Takes the tables of a finished schema load for the completion.
+fn poll_completion_schema(state: &mut ExtendedAppState) {
+    let finished = state
+        .completion_loading
+        .as_ref()
+        .is_some_and(|(_, handle)| handle.is_finished());
+    if !finished {
+        return;
+    }
+    let Some((key, handle)) = state.completion_loading.take() else {
+        return;
+    };
+    let loaded = match &state.runtime {
+        Some(runtime) => runtime.block_on(handle),
+        None => shared::runtime().block_on(handle),
+    };
+    // a failure is cached too, so that typing does not retry on every key
+    let tables = match loaded {
+        Ok(Ok(tables)) => tables,
+        Ok(Err(e)) => {
+            warn!("Cannot load the schema for the completion: {}", e);
+            Vec::new()
+        }
+        Err(e) => {
+            warn!("Loading the schema for the completion failed: {}", e);
+            Vec::new()
+        }
+    };
+    info!("Completion loaded {} table(s)", tables.len());
+    state.completion_schema = Some((key, tables));
+    if state.completion.is_some() {
+        open_completion(state, 0);
+    }
+}
+
+/// Draws the popup below the word being completed, or above it when there is no room.
+fn draw_completion(frame: &mut ratatui::Frame, area: Rect, state: &ExtendedAppState) {
+    let Some(popup) = &state.completion else {
+        return;
+    };
+    let suggestions = &popup.completion.suggestions;
+    let inner = editor_text_area(area);
+    let text_width = suggestions
+        .iter()
+        .map(|s| s.text.chars().count())
+        .max()
+        .unwrap_or(0);
+    let detail_width = suggestions
+        .iter()
+        .map(|s| s.detail.chars().count())
+        .max()
+        .unwrap_or(0);
+    let width = ((text_width + detail_width + 4) as u16)
+        .clamp(20, 60)
+        .min(inner.width);
+    let visible = suggestions.len().min(10);
+    let height = (visible as u16 + 2).min(inner.height);
+
+    let script = &state.shared.sql_query;
+    let word = editor_index(script, popup.completion.start.min(script.len()));
+    let (word_x, word_y) = state
+        .editor_viewport
+        .position(&state.editor_state, word)
+        .unwrap_or((0, 0));
+    let word_y = inner.y + word_y as u16;
+    let x = (inner.x + word_x as u16).min(inner.right().saturating_sub(width));
+    let y = if word_y + 1 + height <= inner.bottom() {
+        word_y + 1
+    } else {
+        word_y.saturating_sub(height).max(inner.y)
+    };
+    let popup_area = Rect {
+        x,
+        y,
+        width,
+        height,
+    };
+
+    let offset = (popup.selected + 1).saturating_sub(visible);
+    let detail_style = Style::default().fg(Color::from_u32(shared::NordColor::Nord3.value() >> 8));
+    let lines: Vec<Line> = suggestions
+        .iter()
+        .enumerate()
+        .skip(offset)
+        .take(visible)
+        .map(|(idx, suggestion)| {
+            let color = match suggestion.kind {
+                completion::SuggestionKind::Column => shared::NordColor::Nord8,
+                completion::SuggestionKind::Table => shared::NordColor::Nord7,
+                completion::SuggestionKind::Function => shared::NordColor::Nord15,
+                completion::SuggestionKind::Keyword => shared::NordColor::Nord9,
+            };
+            let padding = text_width.saturating_sub(suggestion.text.chars().count()) + 2;
+            let line = Line::from(vec![
+                Span::styled(
+                    suggestion.text.clone(),
+                    Style::default().fg(Color::from_u32(color.value() >> 8)),
+                ),
+                Span::raw(" ".repeat(padding)),
+                Span::styled(suggestion.detail.clone(), detail_style),
+            ]);
+            if idx == popup.selected {
+                line.style(Style::default().add_modifier(Modifier::REVERSED))
+            } else {
+                line
+            }
+        })
+        .collect();
+    frame.render_widget(Clear, popup_area);
+    frame.render_widget(
+        Paragraph::new(lines).block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_type(BorderType::Rounded)
+                .border_style(Style::default().fg(Color::Cyan))
+                .title(format!("{}/{}", popup.selected + 1, suggestions.len())),
+        ),
+        popup_area,
+    );
+}
+
+// ── Import ────────────────────────────────────────────────────────────────
+
+/// Reads the file at `path` and opens the import wizard for it.
+fn open_import(state: &mut ExtendedAppState, path: &str) {
+    let data = match import::read_file(path) {
+        Ok(data) => data,
+        Err(e) => return show_error(state, e),
+    };
+    let shared_state = state.shared.clone();
+    let schema_loading = spawn_load(state, async move {
+        shared::describe_schema_async(&shared_state).await
+    });
+    let table = std::path::Path::new(path)
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let mut table_textarea = TextArea::new(vec![table]);
+    table_textarea.move_cursor(CursorMove::End);
+    table_textarea.set_cursor_line_style(Style::default());
+    table_textarea.set_block(
+        Block::default()
+            .borders(Borders::ALL)
+            .border_type(BorderType::Thick)
+            .title("Target Table"),
+    );
+    info!("Import of {} opened ({} rows)", path, data.rows.len());
+    let mut wizard = ImportWizard {
+        path: path.to_string(),
+        misfits: vec![0; data.columns.len()],
+        data,
+        schema: Vec::new(),
+        schema_loading: Some(schema_loading),
+        table_textarea,
+        plan: Err(String::new()),
+        column: None,
+        running: None,
+        report: None,
+    };
+    update_import_plan(&mut wizard, state.shared.dialect());
+    state.import = Some(wizard);
+}
+
+/// Takes the tables of the database once they are loaded and plans the import with them.
+fn poll_import_schema(state: &mut ExtendedAppState) {
+    let dialect = state.shared.dialect();
+    let Some(wizard) = &mut state.import else {
+        return;
+    };
+    let Some(loaded) = finished_load(&state.runtime, &mut wizard.schema_loading) else {
+        return;
+    };
+    match loaded {
+        Ok(Ok(schema)) => {
+            wizard.schema = schema;
+            update_import_plan(wizard, dialect);
+        }
+        Ok(Err(e)) => {
+            state.import = None;
+            show_error(state, e);
+        }
+        Err(e) => {
+            state.import = None;
+            state.error_message = Some(format!("Loading the tables failed: {}", e));
+        }
+    }
+}
+
+fn update_import_plan(wizard: &mut ImportWizard, dialect: shared::Dialect) {
+    if wizard.schema_loading.is_some() {
+        wizard.plan = Err("Loading the tables of the database…".to_string());
+        return;
+    }
+    wizard.plan = import::ImportPlan::new(
+        &wizard.data,
+        &wizard.table_textarea.lines().join(""),
+        &wizard.schema,
+        dialect,
+        import::DEFAULT_BATCH_SIZE,
+    );
+}
+
+/// Typing edits the table name, Tab switches to the column list, Enter starts the import or
+/// closes the report, Esc closes the wizard or cancels the running import.
+fn handle_import_key(state: &mut ExtendedAppState, key_event: KeyEvent) {
+    let dialect = state.shared.dialect();
+    let Some(wizard) = &mut state.import else {
+        return;
+    };
+    let editing = wizard.running.is_none() && wizard.report.is_none();
+    if editing && key_event.code == KeyCode::Tab {
+        wizard.column = match wizard.column {
+            Some(_) => None,
+            None => Some(0),
+        };
+        return;
+    }
+    if editing && let Some(column) = wizard.column {
+        let count = wizard.data.columns.len();
+        match key_event.code {
+            KeyCode::Up => wizard.column = Some((column + count - 1) % count),
+            KeyCode::Down => wizard.column = Some((column + 1) % count),
+            KeyCode::Left | KeyCode::Right => {
+                let step = if key_event.code == KeyCode::Left {
+                    -1
+                } else {
+                    1
+                };
+                if let Ok(plan) = &mut wizard.plan {
+                    plan.cycle_mapping(&wizard.data, column, step);
+                }
             }
+            KeyCode::Char(' ') => {
+                let kind = wizard.data.kinds[column].next();
+                wizard.misfits[column] = wizard.data.set_kind(column, kind);
+            }
+            KeyCode::Esc => wizard.column = None,
+            KeyCode::Enter => start_import(state),
             _ => {}
         }
+        return;
+    }
+    match key_event.code {
+        KeyCode::Esc if wizard.running.is_some() => {
+            if let Some(running) = &wizard.running {
+                running.handle.abort();
+                info!("Import cancelled");
+            }
+        }
+        KeyCode::Esc => state.import = None,
+        KeyCode::Enter if wizard.report.is_some() => state.import = None,
+        KeyCode::Enter if wizard.running.is_none() => start_import(state),
+        _ if wizard.running.is_none() && wizard.report.is_none() => {
+            wizard
+                .table_textarea
+                .input(tui_textarea::Input::from(key_event));
+            update_import_plan(wizard, dialect);
+        }
+        _ => {}
     }
+}
 
-    false
+/// Runs the statements of the import plan in one transaction on the background runtime.
+fn start_import(state: &mut ExtendedAppState) {
+    let Some(wizard) = &mut state.import else {
+        return;
+    };
+    let Ok(plan) = &wizard.plan else {
+        return;
+    };
+    if plan.problem().is_some() {
+        return;
+    }
+    let shared_state = state.shared.clone();
+    let task_plan = plan.clone();
+    let data = wizard.data.clone();
+    let progress = shared::QueryCancelHandle::default();
+    let task_progress = progress.clone();
+    let task =
+        async move { import::execute(&shared_state, &task_plan, &data, &task_progress).await };
+    let handle = match &state.runtime {
+        Some(runtime) => runtime.spawn(task),
+        None => shared::runtime().spawn(task),
+    };
+    info!("Import of {} started", wizard.path);
+    wizard.running = Some(RunningImport {
+        handle,
+        progress,
+        started: Instant::now(),
+    });
+}
+
+/// Turns a finished import into the report shown by the wizard.
+fn poll_import(state: &mut ExtendedAppState) {
+    let Some(wizard) = &mut state.import else {
+        return;
+    };
+    if !wizard
+        .running
+        .as_ref()
+        .is_some_and(|running| running.handle.is_finished())
+    {
+        return;
+    }
+    let (Some(running), Ok(plan)) = (wizard.running.take(), &wizard.plan) else {
+        return;
+    };
+    let elapsed = running.started.elapsed();
+    let outcome = match &state.runtime {
+        Some(runtime) => runtime.block_on(running.handle),
+        None => shared::runtime().block_on(running.handle),
+    };
+    let report = match outcome {
+        Ok(Ok((outcome, undo))) => plan.report(&wizard.data, &outcome, &undo, elapsed),
+        Ok(Err(e)) => e.to_string(),
+        // the batches before the cancel are committed where CREATE TABLE ended the transaction
+        Err(e) if e.is_cancelled() && plan.commits_early(state.shared.dialect()) => {
+            let cleanup = import::drop_created_table(&state.shared, plan);
+            let undo = match &state.runtime {
+                Some(runtime) => runtime.block_on(cleanup),
+                None => shared::runtime().block_on(cleanup),
+            };
+            match undo {
+                import::Undo::Failed(e) => format!(
+                    "Import cancelled, {} keeps the rows imported so far, dropping it failed: {}",
+                    plan.table, e
+                ),
+                _ => format!("Import cancelled, {} was dropped again.", plan.table),
+            }
+        }
+        Err(e) if e.is_cancelled() => {
+            "Import cancelled, the transaction was rolled back.".to_string()
+        }
+        Err(e) => format!("Import task failed: {}", e),
+    };
+    info!("{}", report);
+    wizard.report = Some(report);
+}
+
+fn draw_import(frame: &mut ratatui::Frame, wizard: &ImportWizard, dialect: shared::Dialect) {
+    const PREVIEW_ROWS: usize = 20;
+    let area = frame.area();
+    let area = Rect {
+        x: area.width / 10,
+        y: area.height / 10,
+        width: area.width * 8 / 10,
+        height: area.height * 8 / 10,
+    };
+    frame.render_widget(Clear, area);
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_type(BorderType::Thick)
+        .title(format!(
+            "Import {} ({} rows)",
+            wizard.path,
+            wizard.data.rows.len()
+        ));
+    let inner = block.inner(area);
+    frame.render_widget(block, area);
+
+    let plan_lines: Vec<Line> = match &wizard.plan {
+        Ok(plan) => import_column_lines(wizard, plan, dialect),
+        Err(message) => vec![Line::from(message.clone()).fg(Color::Red)],
+    };
+    let plan_title = match (&wizard.plan, wizard.column) {
+        (Err(_), _) => "Cannot Import".to_string(),
+        (Ok(_), Some(_)) => {
+            "Columns (Up/Down: column, Left/Right: target or skip, Space: type, Tab: table name)"
+                .to_string()
+        }
+        (Ok(plan), None) => format!(
+            "{} (Tab: edit the columns)",
+            match plan.target {
+                import::Target::Create => "New Table",
+                import::Target::Existing(_) => "Column Mapping",
+            }
+        ),
+    };
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(3),
+            Constraint::Length((plan_lines.len() as u16 + 2).min(inner.height / 2)),
+            Constraint::Fill(1),
+            Constraint::Length(4),
+        ])
+        .split(inner);
+    frame.render_widget(&wizard.table_textarea, chunks[0]);
+    // keep the selected column in view
+    let visible = chunks[1].height.saturating_sub(2);
+    let scroll = (wizard.column.unwrap_or(0) as u16).saturating_sub(visible.saturating_sub(1));
+    frame.render_widget(
+        Paragraph::new(plan_lines).scroll((scroll, 0)).block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_type(BorderType::Thick)
+                .title(plan_title),
+        ),
+        chunks[1],
+    );
+
+    let header = Row::new(
+        wizard
+            .data
+            .columns
+            .iter()
+            .zip(&wizard.data.kinds)
+            .map(|(name, kind)| Cell::from(format!("{}\n{}", name, kind.sql_type(dialect)))),
+    )
+    .height(2)
+    .style(Style::default().add_modifier(Modifier::BOLD));
+    let rows = wizard.data.rows.iter().take(PREVIEW_ROWS).map(|row| {
+        Row::new(
+            row.iter()
+                .map(|value| value_cell(value, value.to_plain_string())),
+        )
+    });
+    let widths = vec![Constraint::Fill(1); wizard.data.columns.len()];
+    frame.render_widget(
+        Table::new(rows, widths).header(header).block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_type(BorderType::Thick)
+                .title(format!(
+                    "Preview (first {} rows)",
+                    PREVIEW_ROWS.min(wizard.data.rows.len())
+                )),
+        ),
+        chunks[2],
+    );
+
+    let footer_block = Block::default()
+        .borders(Borders::ALL)
+        .border_type(BorderType::Thick);
+    match (&wizard.running, &wizard.report, &wizard.plan) {
+        (Some(running), _, Ok(plan)) => {
+            let total = wizard.data.rows.len();
+            let rows = plan.rows_done(running.progress.completed(), total);
+            let ratio = if total == 0 {
+                1.0
+            } else {
+                rows as f64 / total as f64
+            };
+            frame.render_widget(
+                Gauge::default()
+                    .block(footer_block.title("Importing, Esc to cancel"))
+                    .gauge_style(Style::default().fg(Color::Green))
+                    .ratio(ratio)
+                    .label(format!("{}/{} rows", rows, total)),
+                chunks[3],
+            );
+        }
+        (_, Some(report), _) => frame.render_widget(
+            Paragraph::new(report.as_str())
+                .wrap(Wrap { trim: true })
+                .block(footer_block.title("Done, Enter or Esc to close")),
+            chunks[3],
+        ),
+        (_, _, plan) => {
+            let mut lines = vec![Line::from(
+                "Type the target table, Enter to import, Esc to cancel",
+            )];
+            if let Ok(plan) = plan {
+                let skipped = plan.skipped(&wizard.data);
+                if let Some(problem) = plan.problem() {
+                    lines.push(Line::from(problem).fg(Color::Red));
+                } else if !skipped.is_empty() {
+                    lines.push(
+                        Line::from(format!("Not imported: {}", skipped.join(", ")))
+                            .fg(Color::Yellow),
+                    );
+                }
+            }
+            frame.render_widget(Paragraph::new(lines).block(footer_block), chunks[3]);
+        }
+    }
+}
+
+/// One line per file column with its type and target, the selected one highlighted,
+/// followed by the table columns left to their defaults.
+fn import_column_lines<'a>(
+    wizard: &ImportWizard,
+    plan: &import::ImportPlan,
+    dialect: shared::Dialect,
+) -> Vec<Line<'a>> {
+    let mut lines: Vec<Line> = wizard
+        .data
+        .columns
+        .iter()
+        .zip(&wizard.data.kinds)
+        .zip(&plan.mapping)
+        .enumerate()
+        .map(|(idx, ((column, kind), target))| {
+            let target = match (target, &plan.target) {
+                (None, _) => "(skipped)".to_string(),
+                (Some(_), import::Target::Create) => "new column".to_string(),
+                (Some(target), import::Target::Existing(_)) => format!("-> {}", target),
+            };
+            let mut text = format!("{} {} {}", column, kind.sql_type(dialect), target);
+            if wizard.misfits[idx] > 0 {
+                text.push_str(&format!(
+                    " ({} value(s) do not fit and are sent as text)",
+                    wizard.misfits[idx]
+                ));
+            }
+            let line = Line::from(text);
+            match wizard.column {
+                Some(selected) if selected == idx => line.black().on_white(),
+                _ if plan.mapping[idx].is_none() => line.fg(Color::DarkGray),
+                _ => line,
+            }
+        })
+        .collect();
+    lines.extend(
+        plan.unfilled()
+            .into_iter()
+            .map(|column| Line::from(format!("{} (not in file, left to its default)", column))),
+    );
+    lines
 }
 
 // ── Entry point ───────────────────────────────────────────────────────────
 
-pub fn main_tui(file_content: String) -> Result<(), Box<dyn Error>> {
+/// Leaves raw mode and the alternate screen, also when the app panics.
+fn restore_terminal() {
+    let _ = crossterm::terminal::disable_raw_mode();
+    let _ = crossterm::execute!(
+        std::io::stdout(),
+        crossterm::terminal::LeaveAlternateScreen,
+        crossterm::event::DisableMouseCapture,
+        crossterm::cursor::Show
+    );
+}
+
+/// Runs the TUI until the user quits; `file` is the path and content of a FILE given on the
+/// command line, opened in the first buffer.
+pub fn main_tui(file: Option<(&str, String)>) -> Result<(), Box<dyn Error>> {
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        restore_terminal();
+        default_hook(info);
+    }));
+
     // Terminal init
     crossterm::terminal::enable_raw_mode()?;
     let mut stdout = std::io::stdout();
@@ -890,22 +3703,40 @@ pub fn main_tui(file_content: String) -> Result<(), Box<dyn Error>> {
     let backend = ratatui::backend::CrosstermBackend::new(stdout);
     let mut terminal = ratatui::Terminal::new(backend)?;
 
+    // Background runtime for query execution
+    let runtime = tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()?;
+
     // App state
-    let mut state = ExtendedAppState::default();
-    if !file_content.is_empty() {
-        state.shared.sql_query = file_content;
+    let mut state = ExtendedAppState {
+        runtime: Some(runtime.handle().clone()),
+        ..ExtendedAppState::default()
+    };
+    if let Some((path, content)) = file {
+        state.buffers[0] = EditorBuffer {
+            file: Some(path.to_string()),
+            ..EditorBuffer::new(file_label(path), content)
+        };
+        show_buffer(&mut state, 0);
     }
 
-    let tick_rate = std::time::Duration::from_millis(100);
+    let tick_rate = Duration::from_millis(100);
 
     loop {
+        poll_query(&mut state);
+        poll_import(&mut state);
+        poll_import_schema(&mut state);
+        poll_schema_browser(&mut state);
+        poll_db_picker(&mut state);
+        poll_completion_schema(&mut state);
         terminal.draw(|frame| {
             ui(frame, &mut state);
         })?;
 
         if crossterm::event::poll(tick_rate)? {
             let event = crossterm::event::read()?;
-            if handle_event(event, &mut state) {
+            if handle_event(event, &mut state) && may_quit(&mut state) {
                 break;
             }
         }
@@ -919,9 +3750,161 @@ pub fn main_tui(file_content: String) -> Result<(), Box<dyn Error>> {
         crossterm::event::DisableMouseCapture
     )?;
     terminal.show_cursor()?;
+    runtime.shutdown_background();
     Ok(())
 }
 
+// ── Syntax highlighting ───────────────────────────────────────────────────
+
+/// Where edtui scrolled the SQL Editor to. edtui keeps its viewport private, so it is followed
+/// here the same way `EditorView` moves it with line wrapping, to know where each character of
+/// the text is drawn.
+#[derive(Clone, Debug, Default)]
+pub struct EditorViewport {
+    /// First editor line on screen.
+    pub row: usize,
+    /// Editor lines drawn in the last frame.
+    pub num_rows: usize,
+    width: usize,
+    height: usize,
+}
+impl EditorViewport {
+    /// Scrolls to the cursor after the editor was drawn into `width` x `height` cells.
+    fn update(&mut self, editor_state: &EditorState, width: usize, height: usize) {
+        self.width = width;
+        self.height = height;
+        let lines = &editor_state.lines;
+        let cursor_row = editor_state.cursor.row;
+        if cursor_row < self.row {
+            self.row = cursor_row;
+        }
+        if cursor_row >= self.row + self.num_rows && self.num_rows > 0 {
+            let mut remaining = height;
+            let skip = lines.len().saturating_sub(cursor_row + 1);
+            for (i, line) in lines.iter_row().rev().skip(skip).enumerate() {
+                let line_height = wrapped_height(line, width);
+                if remaining < line_height {
+                    // the same off-by-one as edtui, to stay in step with it
+                    self.row = cursor_row.saturating_sub(i.saturating_sub(1));
+                    break;
+                }
+                remaining -= line_height;
+            }
+        }
+        let mut y = 0;
+        self.num_rows = 0;
+        for line in lines.iter_row().skip(self.row) {
+            self.num_rows += 1;
+            y += wrapped_height(line, width);
+            if y >= height {
+                break;
+            }
+        }
+    }
+
+    /// Screen cells of the characters on screen as `(index, x, y)`, relative to the text area.
+    fn cells<'a>(
+        &'a self,
+        editor_state: &'a EditorState,
+    ) -> impl Iterator<Item = (Index2, usize, usize)> + 'a {
+        let mut y = 0;
+        editor_state
+            .lines
+            .iter_row()
+            .enumerate()
+            .skip(self.row)
+            .flat_map(move |(row, line)| {
+                let top = y;
+                y += wrapped_height(line, self.width);
+                let mut x = 0;
+                let mut line_y = top;
+                line.iter()
+                    .enumerate()
+                    .map(move |(col, ch)| {
+                        let width = ch.width().unwrap_or(0);
+                        if x > 0 && x + width > self.width {
+                            x = 0;
+                            line_y += 1;
+                        }
+                        let cell = (Index2::new(row, col), x, line_y);
+                        x += width;
+                        cell
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .take_while(|(_, _, y)| *y < self.height)
+    }
+
+    /// Screen cell of an editor position relative to the text area, if it is on screen.
+    fn position(&self, editor_state: &EditorState, index: Index2) -> Option<(usize, usize)> {
+        let line_len = editor_state.lines.len_col(index.row)?;
+        if index.col >= line_len {
+            // behind the last character, where the cursor stands while typing
+            let last = index.col.checked_sub(1).and_then(|col| {
+                self.cells(editor_state)
+                    .find(|(cell, _, _)| *cell == Index2::new(index.row, col))
+            });
+            return match last {
+                Some((cell, x, y)) => {
+                    let width = editor_state
+                        .lines
+                        .get(cell)
+                        .map_or(1, |ch| ch.width().unwrap_or(0));
+                    Some((x + width, y))
+                }
+                None if index.row >= self.row => {
+                    let y: usize = editor_state
+                        .lines
+                        .iter_row()
+                        .skip(self.row)
+                        .take(index.row - self.row)
+                        .map(|line| wrapped_height(line, self.width))
+                        .sum();
+                    (y < self.height).then_some((0, y))
+                }
+                None => None,
+            };
+        }
+        self.cells(editor_state)
+            .find(|(cell, _, _)| *cell == index)
+            .map(|(_, x, y)| (x, y))
+    }
+}
+
+/// Screen rows of an editor line, like edtui wraps it.
+fn wrapped_height(line: &[char], width: usize) -> usize {
+    let line_width: usize = line.iter().map(|ch| ch.width().unwrap_or(0)).sum();
+    line_width.div_ceil(width.max(1)).max(1)
+}
+
+/// The part of the editor the text is drawn in, inside the border and above the status line.
+fn editor_text_area(area: Rect) -> Rect {
+    Rect {
+        x: area.x + 1,
+        y: area.y + 1,
+        width: area.width.saturating_sub(2),
+        height: area.height.saturating_sub(3),
+    }
+}
+
+/// Colors the editor text with the dialect's tokens. The cursor and selections keep their
+/// colors.
+fn paint_highlighting(buf: &mut Buffer, text_area: Rect, state: &ExtendedAppState) {
+    for (index, x, y) in state.editor_viewport.cells(&state.editor_state) {
+        let Some(color) = state.highlighted.color(index.row, index.col) else {
+            continue;
+        };
+        let position = (text_area.x + x as u16, text_area.y + y as u16);
+        if let Some(cell) = buf.cell_mut(position)
+            // the text color of the editor theme
+            && cell.fg == Color::White
+            && cell.bg == Color::Reset
+        {
+            cell.set_fg(color);
+        }
+    }
+}
+
 // ── Theme ─────────────────────────────────────────────────────────────────
 
 #[derive(Default)]
@@ -961,6 +3944,75 @@ pub fn get_editor_lines_as_string(state: &ExtendedAppState) -> String {
         .collect()
 }
 
+/// Converts an editor position into a byte offset of the editor text.
+fn byte_offset(text: &str, index: Index2) -> usize {
+    let line_start: usize = text
+        .split('\n')
+        .take(index.row)
+        .map(|line| line.len() + 1)
+        .sum();
+    let line = text[line_start.min(text.len())..]
+        .split('\n')
+        .next()
+        .unwrap_or_default();
+    line_start
+        + line
+            .char_indices()
+            .nth(index.col)
+            .map(|(i, _)| i)
+            .unwrap_or(line.len())
+}
+
+/// Converts a byte offset of the editor text into an editor position.
+fn editor_index(text: &str, offset: usize) -> Index2 {
+    let before = &text[..offset];
+    let row = before.matches('\n').count();
+    let col = before
+        .rsplit('\n')
+        .next()
+        .map(|line| line.chars().count())
+        .unwrap_or(0);
+    Index2::new(row, col)
+}
+
+/// Editor positions of the first and last character in the bytes `start..end`.
+fn byte_range_positions(text: &str, start: usize, end: usize) -> (Index2, Index2) {
+    let last_char = text[..end]
+        .char_indices()
+        .next_back()
+        .map(|(i, _)| i)
+        .unwrap_or(start)
+        .max(start);
+    (editor_index(text, start), editor_index(text, last_char))
+}
+
+/// Selects `start..=end` in the editor. edtui does not export its selection type, so the
+/// selection is created the way visual mode does it and then moved.
+fn select_range(editor_state: &mut EditorState, start: Index2, end: Index2) {
+    let mut scratch = EditorState::default();
+    SwitchMode(EditorMode::Visual).execute(&mut scratch);
+    editor_state.selection = scratch.selection.map(|mut selection| {
+        selection.start = start;
+        selection.end = end;
+        selection
+    });
+}
+
+/// Table View cell for a value: NULL stands out from the text 'NULL' and numbers are right-aligned.
+fn value_cell(value: &shared::Value, text: String) -> Cell<'static> {
+    if value.is_null() {
+        Cell::from(text).style(
+            Style::default()
+                .fg(Color::from_u32(shared::NordColor::Nord9.value() >> 8))
+                .add_modifier(Modifier::ITALIC),
+        )
+    } else if value.is_numeric() {
+        Cell::from(Line::from(text).alignment(Alignment::Right))
+    } else {
+        Cell::from(text)
+    }
+}
+
 // ── Custom scrollbar ──────────────────────────────────────────────────────
 
 fn draw_custom_scrollbar(
@@ -1022,3 +4074,159 @@ fn draw_custom_scrollbar(
         frame.render_widget(Paragraph::new(Line::from(spans)), area);
     }
 }
+
+// tests
+
+#[test]
+fn test_editor_positions_and_byte_offsets() {
+    let text = "select 'ä';\nselect 2;";
+    let index = Index2::new(1, 7);
+    assert_eq!(byte_offset(text, index), 20);
+    assert_eq!(editor_index(text, 20), index);
+    assert_eq!(byte_offset(text, Index2::new(0, 9)), 10);
+    assert_eq!(
+        byte_range_positions(text, 13, 21),
+        (Index2::new(1, 0), Index2::new(1, 7))
+    );
+}
+
+#[test]
+fn test_database_picker_filters_and_falls_back_to_typed_name() {
+    let databases = vec![
+        "app".to_string(),
+        "App_Test".to_string(),
+        "postgres".to_string(),
+    ];
+    let mut picker = DatabasePicker::new(databases, "postgres", None);
+    assert_eq!(picker.choice(), Some("postgres".to_string()));
+    picker.filter.insert_str("APP");
+    picker.selected = 1;
+    assert_eq!(picker.matches(), vec!["app", "App_Test"]);
+    assert_eq!(picker.choice(), Some("App_Test".to_string()));
+    picker.filter.insert_str("_new");
+    picker.selected = 0;
+    assert_eq!(picker.choice(), Some("APP_new".to_string()));
+}
+
+#[test]
+fn test_editor_buffers_keep_their_text_and_changes() {
+    let mut state = ExtendedAppState::default();
+    let key = |code, modifiers| KeyEvent::new(code, modifiers);
+    let text = format!("select 1{}", state.shared.sql_query);
+    insert_into_editor(&mut state, "select 1");
+    assert!(handle_buffer_key(
+        &mut state,
+        key(KeyCode::Char('n'), KeyModifiers::CONTROL)
+    ));
+    assert_eq!(state.buffer_index, 1);
+    assert_eq!(state.shared.sql_query, "");
+    assert_eq!(buffer_label(&state, 0), "Query 1*");
+    assert_eq!(buffer_label(&state, 1), "Query 2");
+
+    assert!(handle_buffer_key(
+        &mut state,
+        key(KeyCode::Left, KeyModifiers::ALT)
+    ));
+    assert_eq!(state.shared.sql_query, text);
+    assert!(!may_quit(&mut state));
+    assert_eq!(state.confirm, Some(Confirm::Quit));
+
+    state.confirm = None;
+    assert!(handle_buffer_key(
+        &mut state,
+        key(KeyCode::Char('2'), KeyModifiers::ALT)
+    ));
+    assert!(handle_buffer_key(
+        &mut state,
+        key(KeyCode::Char('w'), KeyModifiers::CONTROL)
+    ));
+    assert_eq!(state.buffers.len(), 1);
+    assert_eq!(state.shared.sql_query, text);
+    assert!(!handle_buffer_key(
+        &mut state,
+        key(KeyCode::Char('w'), KeyModifiers::NONE)
+    ));
+}
+
+#[test]
+fn test_library_save_asks_before_overwriting_another_query() {
+    let dir = std::env::temp_dir().join(format!("simplesql_tui_library_{}", std::process::id()));
+    let dir = dir.to_str().unwrap().to_string();
+    let other = library::SavedQuery {
+        id: "active_users".to_string(),
+        name: "active_users".to_string(),
+        sql: "SELECT 1".to_string(),
+        ..library::SavedQuery::default()
+    };
+    let path = library::save(&dir, &other).unwrap();
+    let mut state = ExtendedAppState::default();
+    state.shared.sql_query = "SELECT 2".to_string();
+    let mut fields: Vec<TextArea> = LIBRARY_FIELDS.iter().map(|_| TextArea::default()).collect();
+    fields[0] = TextArea::new(vec!["Active users".to_string()]);
+    state.library_save = Some(LibrarySaveForm {
+        dir: dir.clone(),
+        fields,
+        focused: 0,
+        error: None,
+        confirm_overwrite: None,
+    });
+    let key = |code| KeyEvent::new(code, KeyModifiers::NONE);
+    handle_library_save_key(&mut state, key(KeyCode::Enter));
+    let form = state.library_save.as_ref().unwrap();
+    assert_eq!(form.confirm_overwrite.as_deref(), Some(path.as_str()));
+    handle_library_save_key(&mut state, key(KeyCode::Char('n')));
+    assert!(
+        state
+            .library_save
+            .as_ref()
+            .unwrap()
+            .confirm_overwrite
+            .is_none()
+    );
+    assert!(shared::read_file(&path).unwrap().contains("SELECT 1"));
+
+    handle_library_save_key(&mut state, key(KeyCode::Enter));
+    handle_library_save_key(&mut state, key(KeyCode::Char('y')));
+    assert!(state.library_save.is_none());
+    assert!(shared::read_file(&path).unwrap().contains("SELECT 2"));
+    // the loaded query is saved again without asking
+    state.shared.sql_query = "SELECT 3".to_string();
+    open_library_save(&mut state);
+    let mut loaded = state.library_save.take().unwrap();
+    loaded.dir = dir.clone();
+    state.library_save = Some(loaded);
+    handle_library_save_key(&mut state, key(KeyCode::Enter));
+    assert!(state.library_save.is_none());
+    assert!(shared::read_file(&path).unwrap().contains("SELECT 3"));
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_editor_viewport_follows_edtui() {
+    // EditorViewport copies edtui's private scrolling, this fails when an edtui update changes it
+    let text: Vec<String> = (0..30)
+        .map(|row| format!("{row:02} {}", "wrapped ".repeat(row % 4 * 3)))
+        .collect();
+    let mut editor_state = EditorState::new(Lines::from(text.join("\n").as_str()));
+    let mut viewport = EditorViewport::default();
+    let area = Rect::new(0, 0, 30, 12);
+    let text_area = editor_text_area(area);
+    let rows = (0..30).chain((0..30).rev()).chain([25, 3, 29, 0, 17]);
+    for row in rows {
+        editor_state.cursor = Index2::new(row, 0);
+        let mut buffer = Buffer::empty(area);
+        EditorView::new(&mut editor_state)
+            .wrap(true)
+            .theme(Theme::new().editor)
+            .render(area, &mut buffer);
+        viewport.update(
+            &editor_state,
+            text_area.width as usize,
+            text_area.height as usize,
+        );
+        let top: String = (0..2)
+            .map(|x| buffer[(text_area.x + x, text_area.y)].symbol().to_string())
+            .collect();
+        assert_eq!(top, format!("{:02}", viewport.row), "cursor on row {row}");
+    }
+}